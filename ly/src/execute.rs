@@ -1,4 +1,4 @@
-use crate::Args;
+use crate::{report_all, Args};
 use lylib::{
     anyhow::{Context, Result},
     LyConfig,
@@ -11,18 +11,14 @@ use std::{
 /// Executes a file.
 pub fn execute(args: Args) -> Result<()> {
     //read file to buffer
-    let buf = fs::read_to_string(args.buffer).context("failed to open file")?;
+    let path = args.buffer.as_deref().context("no file provided")?;
+    let buf = fs::read_to_string(path).context("failed to open file")?;
 
-    // create lily config & execute file
-    let mut cfg = LyConfig::default();
-    if !args.no_std {
-        cfg.include_as("math", include_str!("./std/math.ly").to_string());
-        cfg.include_as("complex", include_str!("./std/complex.ly").to_string());
-    }
-    let interp = cfg
-        .debug_parser(args.debug_parser)
-        .debug_lexer(args.debug_lexer)
-        .execute(buf, stdout(), stdin())?;
+    // create lily config & execute file, reporting warnings even if execution fails
+    let mut interp = config(&args).filename(path).build(stdout(), stdin())?;
+    let result = interp.eval(buf);
+    report_all(&interp.take_warnings(), args.error_format);
+    result?;
 
     // for debugging
     #[cfg(debug_assertions)]
@@ -30,29 +26,49 @@ pub fn execute(args: Args) -> Result<()> {
     Ok(())
 }
 
+/// Creates the configuration described by the arguments, including the standard library unless
+/// it is disabled.
+pub fn config(args: &Args) -> LyConfig {
+    let mut cfg = LyConfig::default();
+    if !args.no_std {
+        cfg.math_as("math")
+            .include_as("math", include_str!("./std/math.ly").to_string());
+        cfg.include_as("complex", include_str!("./std/complex.ly").to_string());
+    }
+    cfg.debug_parser(args.debug_parser)
+        .debug_lexer(args.debug_lexer)
+        .backend(args.backend.into());
+    cfg
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Backend;
+
+    /// Executes a test file with every backend.
+    fn execute_all(buffer: &str) {
+        for backend in [Backend::Ast, Backend::Bytecode] {
+            let res = execute(Args {
+                command: None,
+                buffer: Some(buffer.into()),
+                no_std: false,
+                debug_parser: false,
+                debug_lexer: false,
+                error_format: Default::default(),
+                backend,
+            });
+            assert!(res.is_ok(), "{backend:?}: {res:?}");
+        }
+    }
 
     #[test]
     fn math() {
-        let res = execute(Args {
-            buffer: "./src/std/test/math.test.ly".into(),
-            no_std: false,
-            debug_parser: false,
-            debug_lexer: false,
-        });
-        assert!(res.is_ok());
+        execute_all("./src/std/test/math.test.ly");
     }
 
     #[test]
     fn complex() {
-        let res = execute(Args {
-            buffer: "./src/std/test/complex.test.ly".into(),
-            no_std: false,
-            debug_parser: false,
-            debug_lexer: false,
-        });
-        assert!(res.is_ok());
+        execute_all("./src/std/test/complex.test.ly");
     }
 }
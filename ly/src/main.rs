@@ -1,12 +1,62 @@
 mod execute;
-use clap::{arg, command, Parser};
+mod repl;
+use clap::{Parser, Subcommand, ValueEnum};
 use execute::execute;
+use lylib::{
+    anyhow,
+    diagnostics::{Diagnostic, Sources},
+    interpreter,
+};
+use repl::repl;
+use std::{
+    io::{stderr, IsTerminal},
+    process::ExitCode,
+};
+
+/// Format used to report errors.
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+enum ErrorFormat {
+    /// Source snippets, colored when writing to a terminal.
+    #[default]
+    Human,
+    /// One JSON object per error.
+    Json,
+}
+
+/// Backend used to execute files.
+#[derive(ValueEnum, Clone, Copy, Debug, Default)]
+enum Backend {
+    /// Walk the syntax tree.
+    #[default]
+    Ast,
+    /// Compile to bytecode, and run it on a virtual machine.
+    Bytecode,
+}
+
+impl From<Backend> for interpreter::Backend {
+    fn from(backend: Backend) -> Self {
+        match backend {
+            Backend::Ast => interpreter::Backend::Ast,
+            Backend::Bytecode => interpreter::Backend::Bytecode,
+        }
+    }
+}
+
+/// Commands other than executing a file.
+#[derive(Subcommand, Clone, Copy, Debug)]
+enum Command {
+    /// Start an interactive session. This is the default when no file is given.
+    Repl,
+}
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about=None)]
 struct Args {
-    /// Target file.
-    buffer: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Target file. Starts an interactive session if omitted.
+    buffer: Option<String>,
 
     /// Run without adding standard library modules.
     #[arg(long)]
@@ -19,14 +69,56 @@ struct Args {
     /// Debug lexer output during execution.
     #[arg(long)]
     debug_lexer: bool,
+
+    /// Format used to report errors.
+    #[arg(long, value_enum, default_value_t)]
+    error_format: ErrorFormat,
+
+    /// Backend used to execute the file.
+    #[arg(long, value_enum, default_value_t)]
+    backend: Backend,
 }
 
-fn main() {
+fn main() -> ExitCode {
     // parse arguments
     let cmd = Args::parse();
+    let format = cmd.error_format;
+
+    // execute file or start a session, reporting errors as diagnostics
+    let result = match (cmd.command, &cmd.buffer) {
+        (Some(Command::Repl), _) | (None, None) => repl(cmd),
+        (None, Some(_)) => execute(cmd),
+    };
+    if let Err(e) = result {
+        report(&e, format);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+/// Reports an error to `stderr` in the given format.
+fn report(e: &anyhow::Error, format: ErrorFormat) {
+    report_all(&Diagnostic::collect(e), format);
+}
 
-    // execute file
-    if let Err(e) = execute(cmd) {
-        eprintln!("{e:?}");
+/// Reports diagnostics, such as warnings, to `stderr` in the given format.
+fn report_all(diagnostics: &[Diagnostic], format: ErrorFormat) {
+    // sources are read from disk, since every span points to the file it came from
+    let sources = Sources::new();
+    for (i, diagnostic) in diagnostics.iter().enumerate() {
+        match format {
+            ErrorFormat::Json => eprintln!("{}", diagnostic.to_json()),
+            ErrorFormat::Human => {
+                // separate human-readable reports with a blank line
+                if i > 0 {
+                    eprintln!();
+                }
+                if stderr().is_terminal() {
+                    eprint!("{}", diagnostic.render_colored(&sources))
+                } else {
+                    eprint!("{}", diagnostic.render(&sources))
+                }
+            }
+        }
     }
 }
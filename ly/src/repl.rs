@@ -0,0 +1,244 @@
+use crate::{execute::config, report, report_all, Args, ErrorFormat};
+use lylib::{
+    anyhow::{Context, Result},
+    interpreter::{Interpreter, Value},
+    lexer::{Lexer, Token},
+};
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, stdin, stdout, Read, Write},
+    iter,
+    path::PathBuf,
+};
+
+/// Starts an interactive session on the standard streams.
+pub fn repl(args: Args) -> Result<()> {
+    let mut interp = config(&args).build(stdout(), stdin())?;
+
+    // read one line at a time, so that stdin stays available to the interpreter between inputs
+    let lines = iter::from_fn(|| {
+        let mut line = String::new();
+        match stdin().read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => Some(Ok(line)),
+            Err(e) => Some(Err(e)),
+        }
+    });
+
+    // history is kept in the home directory, if there is one
+    let file = env::var_os("HOME").map(|home| PathBuf::from(home).join(".ly_history"));
+    let mut session = Session::new(file);
+    session.run(&mut interp, lines, stdout(), args.error_format)
+}
+
+/// An interactive session, which remembers every input it has executed.
+struct Session {
+    /// Previous inputs, oldest first. Inputs spanning multiple lines are joined with `;`.
+    history: Vec<String>,
+    /// File that history is loaded from and saved to, if any.
+    file: Option<PathBuf>,
+}
+
+impl Session {
+    /// Creates a session, loading the history in `file` if it exists.
+    fn new(file: Option<PathBuf>) -> Self {
+        let history = file
+            .as_ref()
+            .and_then(|file| fs::read_to_string(file).ok())
+            .map(|history| history.lines().map(String::from).collect())
+            .unwrap_or_default();
+        Self { history, file }
+    }
+
+    /// Reads inputs from `lines` until they run out or the session is quit, executing each of
+    /// them in `interp`. Prompts and the values of expressions are written to `out`, while errors
+    /// are reported without ending the session.
+    fn run<Out: Write, In: Read>(
+        &mut self,
+        interp: &mut Interpreter<Out, In>,
+        mut lines: impl Iterator<Item = io::Result<String>>,
+        mut out: impl Write,
+        format: ErrorFormat,
+    ) -> Result<()> {
+        let mut buffer = String::new();
+        loop {
+            // prompt for a new input, or for the rest of an incomplete one
+            write!(out, "{}", if buffer.is_empty() { "> " } else { ". " })?;
+            out.flush()?;
+            let Some(line) = lines.next() else {
+                writeln!(out)?;
+                return Ok(());
+            };
+            buffer.push_str(line.context("failed to read input")?.trim_end());
+            buffer.push('\n');
+            if is_incomplete(&buffer) {
+                continue;
+            }
+            let input = std::mem::take(&mut buffer);
+
+            // handle session commands
+            match input.trim() {
+                "" => continue,
+                ":quit" | ":exit" => return Ok(()),
+                ":history" => {
+                    for (i, entry) in self.history.iter().enumerate() {
+                        writeln!(out, "{i:>4}  {entry}")?;
+                    }
+                    continue;
+                }
+                _ => self.remember(&input),
+            }
+
+            // execute input, printing the value of trailing expressions
+            let result = interp.eval(input);
+            report_all(&interp.take_warnings(), format);
+            match result {
+                Ok(Value::Undefined) => (),
+                Ok(value) => writeln!(out, "{value:?}")?,
+                Err(e) => report(&e, format),
+            }
+        }
+    }
+
+    /// Adds an input to the history, saving it to the history file.
+    fn remember(&mut self, input: &str) {
+        let entry = input
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        // failing to save history shouldn't interrupt the session
+        if let Some(file) = &self.file {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(file) {
+                let _ = writeln!(file, "{entry}");
+            }
+        }
+        self.history.push(entry);
+    }
+}
+
+/// Returns true if `buffer` opens more blocks, lists or dictionaries than it closes, meaning that
+/// it continues on the next line.
+fn is_incomplete(buffer: &str) -> bool {
+    let Ok(tokens) = Lexer::default().lex(buffer.to_string()) else {
+        // let execution report lexer errors
+        return false;
+    };
+    let mut depth = 0isize;
+    // the `do` of a `catch` or an `else if` continues the block that's already open, rather than
+    // opening one
+    let mut continues = false;
+    let mut previous = None;
+    for token in tokens.iter().map(|t| &t.token) {
+        match token {
+            Token::BlockStart if continues => continues = false,
+            Token::BlockStart | Token::Struct | Token::BracketOpen | Token::BraceOpen => depth += 1,
+            Token::BlockEnd | Token::BracketClose | Token::BraceClose => depth -= 1,
+            Token::Catch => continues = true,
+            Token::If if previous == Some(&Token::Else) => continues = true,
+            _ => (),
+        }
+        previous = Some(token);
+    }
+    depth > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lylib::LyConfig;
+    use std::io::{BufRead, Cursor};
+
+    /// Runs a session over `input` with every backend, returning what it wrote to the prompt
+    /// and to the interpreter's output.
+    fn session(input: &str) -> Vec<(String, String)> {
+        use lylib::interpreter::Backend;
+        [Backend::Ast, Backend::Bytecode]
+            .into_iter()
+            .map(|backend| {
+                let mut cfg = LyConfig::default();
+                cfg.backend(backend);
+                let mut interp = cfg.build(Cursor::new(vec![]), Cursor::new(vec![])).unwrap();
+                let mut prompt = vec![];
+                Session::new(None)
+                    .run(
+                        &mut interp,
+                        Cursor::new(input).lines(),
+                        &mut prompt,
+                        ErrorFormat::Human,
+                    )
+                    .unwrap();
+                let output = String::from_utf8(interp.output().get_ref().clone()).unwrap();
+                (String::from_utf8(prompt).unwrap(), output)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keeps_memory() {
+        for (prompt, output) in session("let a = 2\nlet b = a * 3\nprint(b)\n") {
+            assert_eq!(prompt, "> > > > \n");
+            assert_eq!(output, "6\n");
+        }
+    }
+
+    #[test]
+    fn prints_expressions() {
+        for (prompt, _) in session("1 + 2\n\"a\" + 'b'\nlet c = 1\n[c, 2.5]\n") {
+            assert_eq!(prompt, "> 3\n> \"ab\"\n> > [1, 2.5]\n> \n");
+        }
+    }
+
+    #[test]
+    fn multiline_blocks() {
+        let input = "func double n do\nreturn n * 2\nend\ndouble(4)\n";
+        for (prompt, _) in session(input) {
+            assert_eq!(prompt, "> . . > 8\n> \n");
+        }
+    }
+
+    #[test]
+    fn multiline_dicts() {
+        let input = "let d = {\n\"a\": [1,\n2],\n}\nd[\"a\"]\n";
+        for (prompt, _) in session(input) {
+            assert_eq!(prompt, "> . . . > [1, 2]\n> \n");
+        }
+    }
+
+    #[test]
+    fn multiline_else_if() {
+        let input =
+            "let x = 2\nif x == 1 do\nprint(1)\nelse if x == 2 do\nprint(2)\nelse\nprint(3)\nend\n";
+        for (prompt, output) in session(input) {
+            assert_eq!(prompt, "> > . . . . . . > \n");
+            assert_eq!(output, "2\n");
+        }
+    }
+
+    #[test]
+    fn multiline_try() {
+        let input = "try do\nthrow 1\ncatch err do\nprint(err)\nend\n\
+                     try do\nthrow 2\ncatch do\nprint(3)\nfinally\nprint(4)\nend\n";
+        for (prompt, output) in session(input) {
+            assert_eq!(prompt, "> . . . . > . . . . . . > \n");
+            assert_eq!(output, "1\n3\n4\n");
+        }
+    }
+
+    #[test]
+    fn recovers_from_errors() {
+        for (prompt, _) in session("undefined_variable\n1\n") {
+            assert_eq!(prompt, "> > 1\n> \n");
+        }
+    }
+
+    #[test]
+    fn history() {
+        let mut session = Session::new(None);
+        session.remember("if true do\n  print(1)\nend\n");
+        assert_eq!(session.history, vec!["if true do; print(1); end"]);
+    }
+}
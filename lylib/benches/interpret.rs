@@ -1,6 +1,6 @@
 use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
 use lylib::{
-    interpreter::Interpreter,
+    interpreter::{Backend, Interpreter},
     lexer::Lexer,
     parser::{ASTNode, Parser},
 };
@@ -13,41 +13,48 @@ use std::{
 macro_rules! ast {
     ($src:expr) => {
         black_box(
-            Parser::new(Lexer::default().lex(include_str!($src).to_string()).unwrap())
-                .parse()
-                .unwrap(),
+            Parser::new(
+                Lexer::default()
+                    .lex(include_str!($src).to_string())
+                    .unwrap(),
+            )
+            .parse()
+            .unwrap(),
         )
     };
 }
 
 fn criterion_benchmark(c: &mut Criterion) {
-    fn interpret(ast: Rc<ASTNode>) {
+    fn interpret(ast: Rc<ASTNode>, backend: Backend) {
         // Direct in/out to in-memory locations to avoid introducing system instability
         let mut i = Interpreter::new(Cursor::new(""), io::sink());
+        i.set_backend(backend);
         i.execute(ast).expect("failed to execute benchmark AST.");
     }
 
-    c.bench_function("fibonacci", |b| {
-        b.iter_batched(
-            || ast!("../src/interpreter/tests/implementation/fibonacci.ly"),
-            interpret,
-            BatchSize::LargeInput,
-        )
-    });
-    c.bench_function("matrix rotation", |b| {
-        b.iter_batched(
-            || ast!("../src/interpreter/tests/implementation/matrix_rotation.ly"),
-            interpret,
-            BatchSize::LargeInput,
-        )
-    });
-    c.bench_function("tree", |b| {
-        b.iter_batched(
-            || ast!("../src/interpreter/tests/implementation/tree.ly"),
-            interpret,
-            BatchSize::LargeInput,
-        )
-    });
+    for (suffix, backend) in [("", Backend::Ast), (" (bytecode)", Backend::Bytecode)] {
+        c.bench_function(&format!("fibonacci{suffix}"), |b| {
+            b.iter_batched(
+                || ast!("../src/interpreter/tests/implementation/fibonacci.ly"),
+                |ast| interpret(ast, backend),
+                BatchSize::LargeInput,
+            )
+        });
+        c.bench_function(&format!("matrix rotation{suffix}"), |b| {
+            b.iter_batched(
+                || ast!("../src/interpreter/tests/implementation/matrix_rotation.ly"),
+                |ast| interpret(ast, backend),
+                BatchSize::LargeInput,
+            )
+        });
+        c.bench_function(&format!("tree{suffix}"), |b| {
+            b.iter_batched(
+                || ast!("../src/interpreter/tests/implementation/tree.ly"),
+                |ast| interpret(ast, backend),
+                BatchSize::LargeInput,
+            )
+        });
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,272 @@
+//! Diagnostics turn errors into structured reports that point at the offending source.
+//!
+//! Errors produced by the lexer, parser and interpreter are plain `anyhow` errors carrying a
+//! `Span` and a `Stage`. A `Diagnostic` collects those into a severity, code, labelled source
+//! locations, notes and the Lily stack trace, which can then be rendered as plain text, colored
+//! text, or JSON.
+//!
+//! ```
+//! use lylib::{LyConfig, diagnostics::{Diagnostic, Sources}};
+//! use std::io::{sink, empty};
+//! # fn main() {
+//! let source = "let a = 1\nlet b = a + c\n";
+//! let err = LyConfig::default().execute(source, sink(), empty()).unwrap_err();
+//! let report = Diagnostic::from_error(&err).render(&Sources::new().with(None, source));
+//! assert!(report.starts_with("error[E0003]: failed to get owned value"));
+//! # }
+//! ```
+//!
+//! # Codes
+//!
+//! | Code | Stage |
+//! |------|-------|
+//! | `E0001` | Lexing |
+//! | `E0002` | Parsing |
+//! | `E0003` | Execution |
+
+mod render;
+mod tests;
+
+use crate::{interpreter::StackTrace, lexer::Span, parser::SyntaxErrors};
+use rustc_hash::FxHashMap;
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The start of the notes the parser adds to its errors.
+const PARSE_NOTE: &str = "failed to parse ";
+
+/// How serious a diagnostic is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// The stage of execution an error came from. Attached to errors as context by `LyConfig`, and
+/// used to pick the code of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Lex,
+    Parse,
+    Execute,
+}
+
+impl Stage {
+    /// Returns the diagnostic code for errors raised during this stage.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Stage::Lex => "E0001",
+            Stage::Parse => "E0002",
+            Stage::Execute => "E0003",
+        }
+    }
+}
+
+impl Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stage::Lex => write!(f, "failed to lex buffer"),
+            Stage::Parse => write!(f, "failed to parse buffer"),
+            Stage::Execute => write!(f, "failed to execute buffer"),
+        }
+    }
+}
+
+/// A message attached to a location in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+/// A structured report of an error or warning.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    /// The location the diagnostic is about, if known.
+    pub primary: Option<Label>,
+    /// Other related locations.
+    pub secondary: Vec<Label>,
+    /// Additional context, from the outermost to the innermost.
+    pub notes: Vec<String>,
+    /// The Lily functions that were active, innermost first. Each label points at the call site
+    /// of a function, and holds its name.
+    pub trace: Vec<Label>,
+}
+
+impl Diagnostic {
+    /// Creates a new diagnostic without any labels or notes.
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            primary: None,
+            secondary: vec![],
+            notes: vec![],
+            trace: vec![],
+        }
+    }
+
+    /// Creates a new error diagnostic.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    /// Creates a new warning diagnostic.
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    /// Sets the code of this diagnostic.
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Sets the primary label of this diagnostic.
+    pub fn with_primary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.primary = Some(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Adds a secondary label to this diagnostic.
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.secondary.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Adds a note to this diagnostic.
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// Builds a diagnostic from an error.
+    ///
+    /// The innermost cause becomes the message, the location attached to the error becomes the
+    /// primary label, the stack trace becomes the trace, and the rest of the context chain
+    /// becomes notes.
+    pub fn from_error(err: &anyhow::Error) -> Self {
+        let span = err.downcast_ref::<Span>();
+        let stage = err.downcast_ref::<Stage>();
+        let trace = err.downcast_ref::<StackTrace>();
+        let mut diagnostic = Self::error(err.root_cause().to_string());
+        if let Some(stage) = stage {
+            diagnostic = diagnostic.with_code(stage.code());
+        }
+        if let Some(span) = span {
+            diagnostic = diagnostic.with_primary(span.clone(), "");
+        }
+        if let Some(StackTrace(frames)) = trace {
+            diagnostic.trace = frames
+                .iter()
+                .map(|frame| Label {
+                    span: frame.call_site.clone(),
+                    message: frame.to_string(),
+                })
+                .collect();
+        }
+
+        // the span, stage & trace are shown on their own, so they're skipped as notes
+        let skipped = [
+            span.map(Span::to_string),
+            stage.map(Stage::to_string),
+            trace.map(StackTrace::to_string),
+        ];
+        let mut chain = err.chain().map(|e| e.to_string()).collect::<Vec<String>>();
+        chain.pop();
+        for note in chain {
+            if skipped.contains(&Some(note.clone())) {
+                continue;
+            }
+
+            // the parser adds a note for every construct it was in, so only the innermost of
+            // them is kept
+            match diagnostic.notes.last_mut() {
+                Some(last) if last.starts_with(PARSE_NOTE) && note.starts_with(PARSE_NOTE) => {
+                    *last = note
+                }
+                _ => diagnostic.notes.push(note),
+            }
+        }
+        diagnostic
+    }
+
+    /// Builds one diagnostic for each error within `err`. Most errors produce a single
+    /// diagnostic, but parsing with recovery can report several syntax errors at once.
+    pub fn collect(err: &anyhow::Error) -> Vec<Self> {
+        let Some(SyntaxErrors(errors)) = err.downcast_ref::<SyntaxErrors>() else {
+            return vec![Self::from_error(err)];
+        };
+        let code = err.downcast_ref::<Stage>().map(Stage::code);
+        errors
+            .iter()
+            .map(|e| match (Self::from_error(e), code) {
+                (diagnostic, Some(code)) if diagnostic.code.is_none() => diagnostic.with_code(code),
+                (diagnostic, _) => diagnostic,
+            })
+            .collect()
+    }
+}
+
+/// Source text used when rendering diagnostics, looked up by the file of a span.
+///
+/// Files that weren't added are read from disk when needed.
+#[derive(Debug, Default, Clone)]
+pub struct Sources {
+    files: FxHashMap<Option<PathBuf>, String>,
+}
+
+impl Sources {
+    /// Creates an empty set of sources.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the source text of `file`. Use `None` for buffers that weren't read from a file.
+    pub fn add(&mut self, file: Option<&Path>, text: impl Into<String>) -> &mut Self {
+        self.files.insert(file.map(Path::to_path_buf), text.into());
+        self
+    }
+
+    /// Adds the source text of `file`, returning the modified set of sources.
+    pub fn with(mut self, file: Option<&Path>, text: impl Into<String>) -> Self {
+        self.add(file, text);
+        self
+    }
+
+    /// Returns the text of line `line` in the file `span` points to, if it can be found.
+    pub(crate) fn line(&self, span: &Span) -> Option<String> {
+        let key = span.file.as_ref().map(|f| f.to_path_buf());
+        let text = match self.files.get(&key) {
+            Some(text) => text.clone(),
+            None => fs::read_to_string(key?).ok()?,
+        };
+        text.lines()
+            .nth(span.line.checked_sub(1)?)
+            .map(|line| line.trim_end_matches('\r').to_string())
+    }
+}
@@ -0,0 +1,232 @@
+//! Renders diagnostics in the style of `rustc`, or as JSON for tooling.
+
+use super::*;
+use std::fmt::Write;
+
+/// ANSI escape sequences used while rendering. All empty when rendering plain text.
+struct Style {
+    reset: &'static str,
+    bold: &'static str,
+    gutter: &'static str,
+    secondary: &'static str,
+    colored: bool,
+}
+
+const PLAIN: Style = Style {
+    reset: "",
+    bold: "",
+    gutter: "",
+    secondary: "",
+    colored: false,
+};
+
+const COLORED: Style = Style {
+    reset: "\x1b[0m",
+    bold: "\x1b[1m",
+    gutter: "\x1b[1;34m",
+    secondary: "\x1b[1;34m",
+    colored: true,
+};
+
+impl Style {
+    /// Returns the escape sequence for the color of `severity`.
+    fn severity(&self, severity: Severity) -> &'static str {
+        if !self.colored {
+            return "";
+        }
+        match severity {
+            Severity::Error => "\x1b[1;31m",
+            Severity::Warning => "\x1b[1;33m",
+            Severity::Note => "\x1b[1;36m",
+        }
+    }
+}
+
+/// Formats the location of a span as `file:line:column`.
+fn location(span: &Span) -> String {
+    match &span.file {
+        Some(file) => format!("{}:{}:{}", file.display(), span.line, span.column),
+        None => format!("<buffer>:{}:{}", span.line, span.column),
+    }
+}
+
+impl Diagnostic {
+    /// Renders this diagnostic as plain text.
+    pub fn render(&self, sources: &Sources) -> String {
+        self.render_with(sources, &PLAIN)
+    }
+
+    /// Renders this diagnostic as text colored with ANSI escape sequences, for terminals.
+    pub fn render_colored(&self, sources: &Sources) -> String {
+        self.render_with(sources, &COLORED)
+    }
+
+    fn render_with(&self, sources: &Sources, style: &Style) -> String {
+        let Style {
+            reset,
+            bold,
+            gutter,
+            ..
+        } = style;
+        let color = style.severity(self.severity);
+        let mut out = String::new();
+
+        // header
+        write!(out, "{color}{}", self.severity).unwrap();
+        if let Some(code) = &self.code {
+            write!(out, "[{code}]").unwrap();
+        }
+        writeln!(out, "{reset}{bold}: {}{reset}", self.message).unwrap();
+
+        // collect known labels, primary first, along with their underline character & color
+        let mut labels = vec![];
+        if let Some(label) = &self.primary {
+            labels.push((label, '^', color));
+        }
+        for label in &self.secondary {
+            labels.push((label, '-', style.secondary));
+        }
+        labels.retain(|(label, ..)| label.span.is_known());
+
+        // pad the gutter to fit the widest line number
+        let width = labels
+            .iter()
+            .map(|(label, ..)| label.span.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(width);
+
+        // source snippets
+        let mut previous: Option<&Span> = None;
+        for (label, underline, label_color) in &labels {
+            let span = &label.span;
+            let same_file = previous.is_some_and(|p| p.file == span.file);
+            if !same_file {
+                let arrow = if previous.is_none() { "-->" } else { ":::" };
+                writeln!(out, "{pad}{gutter}{arrow}{reset} {}", location(span)).unwrap();
+                writeln!(out, "{pad} {gutter}|{reset}").unwrap();
+            }
+            let Some(line) = sources.line(span) else {
+                previous = Some(span);
+                continue;
+            };
+            if !same_file || previous.is_some_and(|p| p.line != span.line) {
+                writeln!(out, "{gutter}{:>width$} |{reset} {line}", span.line).unwrap();
+            }
+
+            // keep tabs so the underline lines up with the source line
+            let indent: String = line
+                .chars()
+                .take(span.column - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let available = line.chars().count().saturating_sub(span.column - 1);
+            let length = span.length.min(available).max(1);
+            let marks = underline.to_string().repeat(length);
+            write!(out, "{pad} {gutter}|{reset} {indent}{label_color}{marks}").unwrap();
+            if !label.message.is_empty() {
+                write!(out, " {}", label.message).unwrap();
+            }
+            writeln!(out, "{reset}").unwrap();
+            previous = Some(span);
+        }
+
+        // notes
+        let annotated = !self.notes.is_empty() || !self.trace.is_empty();
+        if annotated && !labels.is_empty() {
+            writeln!(out, "{pad} {gutter}|{reset}").unwrap();
+        }
+        for note in &self.notes {
+            writeln!(out, "{pad} {gutter}={reset} {bold}note{reset}: {note}").unwrap();
+        }
+
+        // stack trace, with runs of the same call collapsed
+        let mut frames = self.trace.iter().peekable();
+        while let Some(frame) = frames.next() {
+            let mut repeats = 1;
+            while frames.next_if_eq(&frame).is_some() {
+                repeats += 1;
+            }
+            let site = match frame.span.is_known() {
+                true => format!("called at {}", location(&frame.span)),
+                false => "called by an external function".to_string(),
+            };
+            write!(
+                out,
+                "{pad} {gutter}={reset} {bold}in{reset} `{}`, {site}",
+                frame.message
+            )
+            .unwrap();
+            if repeats > 1 {
+                write!(out, " (repeated {repeats} times)").unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+        out
+    }
+
+    /// Renders this diagnostic as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        fn label(label: &Label, primary: bool) -> String {
+            let file = match &label.span.file {
+                Some(file) => escape(&file.display().to_string()),
+                None => "null".to_string(),
+            };
+            format!(
+                "{{\"primary\":{primary},\"file\":{file},\"line\":{},\"column\":{},\"length\":{},\"message\":{}}}",
+                label.span.line,
+                label.span.column,
+                label.span.length,
+                escape(&label.message)
+            )
+        }
+
+        let code = match &self.code {
+            Some(code) => escape(code),
+            None => "null".to_string(),
+        };
+        let labels = self
+            .primary
+            .iter()
+            .map(|l| label(l, true))
+            .chain(self.secondary.iter().map(|l| label(l, false)))
+            .collect::<Vec<String>>()
+            .join(",");
+        let notes = self
+            .notes
+            .iter()
+            .map(|n| escape(n))
+            .collect::<Vec<String>>()
+            .join(",");
+        let trace = self
+            .trace
+            .iter()
+            .map(|l| label(l, false))
+            .collect::<Vec<String>>()
+            .join(",");
+        format!(
+            "{{\"severity\":\"{}\",\"code\":{code},\"message\":{},\"labels\":[{labels}],\"notes\":[{notes}],\"trace\":[{trace}]}}",
+            self.severity,
+            escape(&self.message)
+        )
+    }
+}
+
+/// Escapes a string as a quoted JSON string.
+fn escape(string: &str) -> String {
+    let mut out = String::with_capacity(string.len() + 2);
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
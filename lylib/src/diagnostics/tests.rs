@@ -0,0 +1,155 @@
+#![cfg(test)]
+
+use super::*;
+use crate::LyConfig;
+use std::io::{empty, sink};
+
+/// Runs `source` and builds a diagnostic from the resulting error.
+fn diagnose(source: &str) -> Diagnostic {
+    let err = LyConfig::default()
+        .execute(source, sink(), empty())
+        .expect_err("expected source to fail");
+    Diagnostic::from_error(&err)
+}
+
+#[test]
+fn runtime_error() {
+    let diagnostic = diagnose("let a = 1\nlet b = a + c\n");
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.code.as_deref(), Some("E0003"));
+    assert_eq!(diagnostic.message, "failed to get owned value \"c\"");
+    let span = &diagnostic.primary.as_ref().unwrap().span;
+    assert_eq!((span.line, span.column, span.length), (2, 13, 1));
+    assert_eq!(
+        diagnostic.notes,
+        vec![
+            "failed to evaluate expression",
+            "failed to evaluate declaration value",
+            "failed to evaluate right operand"
+        ]
+    );
+}
+
+#[test]
+fn render_plain() {
+    let source = "let a = 1\nlet b = a + c\n";
+    let rendered = diagnose(source).render(&Sources::new().with(None, source));
+    assert_eq!(
+        rendered,
+        "error[E0003]: failed to get owned value \"c\"
+ --> <buffer>:2:13
+  |
+2 | let b = a + c
+  |             ^
+  |
+  = note: failed to evaluate expression
+  = note: failed to evaluate declaration value
+  = note: failed to evaluate right operand
+"
+    );
+}
+
+#[test]
+fn render_trace() {
+    let source = "func down n do\n  if n == 0 do\n    return missing\n  end\n  return down(n - 1)\nend\ndown(2)";
+    let diagnostic = diagnose(source);
+    assert_eq!(diagnostic.trace.len(), 3);
+    let rendered = diagnostic.render(&Sources::new().with(None, source));
+    assert!(rendered.ends_with(
+        "  = in `down`, called at <buffer>:5:10 (repeated 2 times)
+  = in `down`, called at <buffer>:7:1
+"
+    ));
+}
+
+#[test]
+fn render_labels() {
+    let source = "let x = 1\nlet x = 2";
+    let rendered = Diagnostic::error("variable 'x' already exists")
+        .with_primary(Span::new(None, 2, 5, 1), "declared again here")
+        .with_secondary(Span::new(None, 1, 5, 1), "first declared here")
+        .render(&Sources::new().with(None, source));
+    assert_eq!(
+        rendered,
+        "error: variable 'x' already exists
+ --> <buffer>:2:5
+  |
+2 | let x = 2
+  |     ^ declared again here
+1 | let x = 1
+  |     - first declared here
+"
+    );
+}
+
+#[test]
+fn render_colored() {
+    let rendered = diagnose("let a = b\n").render_colored(&Sources::new());
+    assert!(rendered.starts_with("\x1b[1;31merror[E0003]"));
+}
+
+#[test]
+fn render_without_source() {
+    let rendered = diagnose("let a = b\n").render(&Sources::new());
+    assert!(rendered.contains(" --> <buffer>:1:9\n  |\n  |\n  = note:"));
+}
+
+#[test]
+fn json() {
+    let json = Diagnostic::error("bad \"thing\"\n")
+        .with_code("E0002")
+        .with_primary(Span::new(None, 3, 1, 2), "here")
+        .with_note("note")
+        .to_json();
+    assert_eq!(
+        json,
+        "{\"severity\":\"error\",\"code\":\"E0002\",\"message\":\"bad \\\"thing\\\"\\n\",\
+         \"labels\":[{\"primary\":true,\"file\":null,\"line\":3,\"column\":1,\"length\":2,\
+         \"message\":\"here\"}],\"notes\":[\"note\"],\"trace\":[]}"
+    );
+}
+
+#[test]
+fn stages() {
+    assert_eq!(diagnose("let c = 'ab'").code.as_deref(), Some("E0001"));
+    assert_eq!(diagnose("let a = )").code.as_deref(), Some("E0002"));
+}
+
+#[test]
+fn collect() {
+    let err = LyConfig::default()
+        .execute("let a = )\nlet b = 1\nlet = 2\n", sink(), empty())
+        .unwrap_err();
+    let diagnostics = Diagnostic::collect(&err);
+    assert_eq!(diagnostics.len(), 2);
+    for (diagnostic, line) in diagnostics.iter().zip([1, 3]) {
+        assert_eq!(diagnostic.code.as_deref(), Some("E0002"));
+        assert_eq!(diagnostic.primary.as_ref().unwrap().span.line, line);
+    }
+    assert_eq!(diagnose("let a = b\n").code.as_deref(), Some("E0003"));
+}
+
+#[test]
+fn syntax_error_messages() {
+    let err = LyConfig::default()
+        .execute("let a = [1, 2\nlet b = 3\nlet c = (1 +\n", sink(), empty())
+        .unwrap_err();
+    let diagnostics = Diagnostic::collect(&err);
+    let reports = diagnostics
+        .iter()
+        .map(|diagnostic| (diagnostic.message.as_str(), diagnostic.notes.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reports,
+        vec![
+            (
+                "expected expression, found `let`",
+                vec!["failed to parse list item".to_string()]
+            ),
+            (
+                "expected expression, found end of line",
+                vec!["failed to parse high precedence operand".to_string()]
+            ),
+        ]
+    );
+}
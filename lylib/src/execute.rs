@@ -1,12 +1,18 @@
 //! Implements the outward-facing functions for executing a file with a given set of configuration
 //! options. This allows the end user to customize the behavior of the interpreter.
 
-use crate::{interpreter::*, lexer::*, parser::*};
+use crate::{
+    diagnostics::{Diagnostic, Stage},
+    interpreter::*,
+    lexer::*,
+    parser::*,
+};
 use anyhow::{Context, Result};
 use std::{
     io::{Read, Write},
     path::PathBuf,
     rc::Rc,
+    time::Duration,
 };
 
 /// Lily configuration.
@@ -32,12 +38,69 @@ pub struct LyConfig {
     /// Files to include during parsing, if applicable.
     /// Each value must be a tuple in which the values correspond to `(module alias, module source)`.
     include: Vec<(Option<usize>, String)>,
+    /// Alias of the module native math functions are added to, if applicable.
+    math: Option<String>,
+    /// Options used to lex & parse buffers.
+    source: SourceOptions,
+    /// Backend used to execute the parsed buffer.
+    backend: Backend,
+    /// The number of function calls that may be active at once.
+    max_call_depth: usize,
+    /// Operations buffers may perform, if limited.
+    fuel: Option<u64>,
+    /// Asked for more fuel once buffers run out, if set.
+    refuel: Option<Rc<Refuel>>,
+    /// Time each buffer may take to execute, if limited.
+    timeout: Option<Duration>,
+    /// Approximate number of bytes memory may hold, if limited.
+    memory_limit: Option<usize>,
+}
+
+/// Options used to lex & parse buffers, kept by interpreters so that every buffer they evaluate is
+/// read the same way.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SourceOptions {
+    /// File name reported in the locations of errors, if applicable.
+    file: Option<PathBuf>,
     /// If true, debug lexer output.
     dbg_tokens: bool,
     /// If true, debug parser output.
     dbg_ast: bool,
 }
 
+impl SourceOptions {
+    /// Lexes & parses a buffer, reporting every syntax error at once. Returns the syntax tree,
+    /// along with any warnings found while parsing.
+    pub(crate) fn parse(&self, buffer: String) -> Result<(Rc<ASTNode>, Vec<Diagnostic>)> {
+        // Lex file
+        let mut lexer = match &self.file {
+            Some(path) => Lexer::with_file(path),
+            None => Lexer::default(),
+        };
+        let tokens = lexer.lex(buffer).context(Stage::Lex)?;
+
+        // Debug lexer, if applicable
+        if self.dbg_tokens {
+            println!("[TOKENS]\n{tokens:#?}");
+        }
+
+        // Parse file, reporting every syntax error at once
+        let mut parser = Parser::new(tokens);
+        let (ast, mut errors) = parser.parse_recovering(vec![]);
+        match errors.len() {
+            0 => (),
+            1 => return Err(errors.remove(0).context(Stage::Parse)),
+            _ => return Err(anyhow::Error::new(SyntaxErrors(errors)).context(Stage::Parse)),
+        }
+
+        // Debug parser, if applicable
+        if self.dbg_ast {
+            println!("[AST]\n{ast:#?}");
+        }
+        Ok((ast, parser.take_warnings()))
+    }
+}
+
 impl Default for LyConfig {
     fn default() -> Self {
         Self::new()
@@ -49,8 +112,14 @@ impl LyConfig {
     pub fn new() -> Self {
         Self {
             include: vec![],
-            dbg_ast: false,
-            dbg_tokens: false,
+            math: None,
+            source: SourceOptions::default(),
+            backend: Backend::default(),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            fuel: None,
+            refuel: None,
+            timeout: None,
+            memory_limit: None,
         }
     }
 
@@ -68,15 +137,73 @@ impl LyConfig {
         self
     }
 
+    /// Adds the native math functions, like `sqrt` & `random`, as a module under a provided
+    /// alias. Files included under the same alias are added to the same module.
+    pub fn math_as(&mut self, alias: impl Into<String>) -> &mut Self {
+        self.math = Some(alias.into());
+        self
+    }
+
+    /// Sets the file name used when reporting error locations in the executed buffer.
+    pub fn filename(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.source.file = Some(path.into());
+        self
+    }
+
+    /// Sets the backend used to execute the buffer.
+    pub fn backend(&mut self, backend: Backend) -> &mut Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Sets the number of function calls that may be active at once, which defaults to
+    /// `DEFAULT_MAX_CALL_DEPTH`. Runaway recursion raises a catchable `recursion` error once it
+    /// reaches this depth, instead of overflowing the stack.
+    pub fn max_call_depth(&mut self, depth: usize) -> &mut Self {
+        self.max_call_depth = depth;
+        self
+    }
+
+    /// Limits the number of operations executed buffers may perform in total. Once they run out,
+    /// execution stops with a `ResourceExhausted` error that Lily programs can't catch, unless
+    /// `refuel` gives more. Later buffers can be given more with `Interpreter::add_fuel`.
+    /// Included files don't use any fuel.
+    pub fn fuel(&mut self, fuel: u64) -> &mut Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Sets a function asked for more fuel each time executed buffers run out of it. Returning
+    /// `Some` lets execution continue where it was, and returning `None` stops it.
+    pub fn refuel(&mut self, refuel: impl Fn() -> Option<u64> + 'static) -> &mut Self {
+        self.refuel = Some(Rc::new(refuel));
+        self
+    }
+
+    /// Limits the time each executed buffer may take. Once it passes, execution stops with a
+    /// `ResourceExhausted` error that Lily programs can't catch.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Limits the approximate number of bytes that lists, strings, dictionaries & instances held
+    /// in memory may use, including those of included files. Once exceeded, execution stops with
+    /// a `ResourceExhausted` error that Lily programs can't catch.
+    pub fn memory_limit(&mut self, bytes: usize) -> &mut Self {
+        self.memory_limit = Some(bytes);
+        self
+    }
+
     /// Toggles debug mode on parser output.
     pub fn debug_parser(&mut self, debug: bool) -> &mut Self {
-        self.dbg_ast = debug;
+        self.source.dbg_ast = debug;
         self
     }
 
     /// Toggles debug mode on lexer output.
     pub fn debug_lexer(&mut self, debug: bool) -> &mut Self {
-        self.dbg_tokens = debug;
+        self.source.dbg_tokens = debug;
         self
     }
 
@@ -88,53 +215,80 @@ impl LyConfig {
         output: Out,
         input: In,
     ) -> Result<Interpreter<Out, In>> {
-        // Interpret file
-        let mut lexer = Lexer::default();
-        let tokens = lexer.lex(buffer.into()).context("failed to lex buffer")?;
-
-        // Debug lexer, if applicable
-        if self.dbg_tokens {
-            println!("[TOKENS]\n{tokens:#?}");
-        }
+        let mut interpreter = self.build(output, input)?;
+        interpreter.eval(buffer)?;
+        Ok(interpreter)
+    }
 
-        // Parse includes before main file
+    /// Creates an interpreter with the options of this configuration, and executes the included
+    /// files into its memory. Further buffers can be evaluated against it with
+    /// `Interpreter::eval`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lylib::{LyConfig, interpreter::Value};
+    /// use std::io::{stdin, stdout};
+    /// # fn main() {
+    /// let mut interpreter = LyConfig::default()
+    ///     .include_as("math", "func add a b do return a + b end;")
+    ///     .build(stdout(), stdin())
+    ///     .unwrap();
+    /// interpreter.eval("let a = math.add(1, 2)").unwrap();
+    /// assert_eq!(interpreter.eval("a * 2").unwrap(), Value::Int(6));
+    /// # }
+    /// ```
+    pub fn build<Out: Write, In: Read>(
+        &self,
+        output: Out,
+        input: In,
+    ) -> Result<Interpreter<Out, In>> {
+        // Parse includes
         let includes = self
             .include
             .iter()
             .map(|(alias, source)| {
                 let tokens = Lexer::default()
                     .lex(source.clone().to_string())
-                    .context("failed to lex included module")?;
+                    .context("failed to lex included module")
+                    .context(Stage::Lex)?;
                 let body = Parser::new(tokens)
                     .parse()
-                    .context("failed to parse included module")?;
+                    .context("failed to parse included module")
+                    .context(Stage::Parse)?;
                 Ok::<Rc<ASTNode>, anyhow::Error>(
                     ASTNode::Module {
                         alias: *alias,
                         path: Some(PathBuf::from(source)),
                         body,
+                        span: Span::default(),
                     }
                     .into(),
                 )
             })
             .collect::<Result<Vec<Rc<ASTNode>>>>()?;
 
-        // Parse file
-        let mut parser = Parser::new(tokens);
-        let ast = parser
-            .parse_with_imports(includes)
-            .context("failed to parse buffer")?;
-
-        // Debug parser, if applicable
-        if self.dbg_ast {
-            println!("[AST]\n{ast:#?}");
-        }
-
-        // Interpret AST
+        // Create interpreter & execute includes
         let mut interpreter = Interpreter::new(input, output);
+        interpreter.set_backend(self.backend);
+        interpreter.set_max_call_depth(self.max_call_depth);
+        interpreter.source = self.source.clone();
+        if let Some(alias) = &self.math {
+            interpreter.inject_math(alias)?;
+        }
         interpreter
-            .execute(ast)
-            .context("failed to execute buffer")?;
+            .execute(
+                ASTNode::Block {
+                    statements: includes,
+                    span: Span::default(),
+                }
+                .into(),
+            )
+            .context(Stage::Execute)?;
+        interpreter.set_fuel(self.fuel);
+        interpreter.set_refuel(self.refuel.clone());
+        interpreter.set_timeout(self.timeout);
+        interpreter.set_memory_limit(self.memory_limit);
         Ok(interpreter)
     }
 }
@@ -0,0 +1,131 @@
+//! External functions on lists. Those that add or remove items change the list passed to them in
+//! place, rather than returning a changed copy, so that building a list in a loop doesn't copy it
+//! on every step.
+
+use super::*;
+
+impl<Out: Write, In: Read> Interpreter<Out, In> {
+    /// Adds the external functions on lists to this interpreter.
+    pub(super) fn inject_lists(&mut self) -> Result<()> {
+        // push (add an item to the end of a list)
+        exfn!(self, push, |&mut list, value| {
+            list_mut(list)?.push(Variable::Owned(value.clone()).into());
+            Ok(Value::Undefined)
+        });
+
+        // pop (remove the last item of a list, returning it, or undefined if the list is empty)
+        exfn!(self, pop, |&mut list| {
+            match list_mut(list)?.pop() {
+                Some(item) => item_value(&item),
+                None => Ok(Value::Undefined),
+            }
+        });
+
+        // insert (add an item before an index, or at the end if the index is the length)
+        exfn!(self, insert, |&mut list, index, value| {
+            let (list, index) = (list_mut(list)?, index.as_index()?);
+            if index > list.len() {
+                raise!(Index, "index out of bounds");
+            }
+            list.insert(index, Variable::Owned(value.clone()).into());
+            Ok(Value::Undefined)
+        });
+
+        // remove (remove an item from a list by index, or a key from a dictionary, returning its
+        // value. missing dictionary keys return undefined)
+        exfn!(self, remove, |&mut target, key| {
+            match target {
+                Value::List(items) => {
+                    let index = key.as_index()?;
+                    if index >= items.len() {
+                        raise!(Index, "index out of bounds");
+                    }
+                    item_value(&items.remove(index))
+                }
+                Value::Dict(dict) => match dict.borrow_mut().remove(&key.as_key()?) {
+                    Some(Variable::Owned(value)) => Ok(value),
+                    Some(other) => bail!("expected a value, found {other:?}"),
+                    None => Ok(Value::Undefined),
+                },
+                _ => raise!(Type, "cannot remove from {target:?}"),
+            }
+        });
+
+        // clear (remove every item of a list, or every entry of a dictionary)
+        exfn!(self, clear, |&mut target| {
+            match target {
+                Value::List(items) => items.clear(),
+                Value::Dict(dict) => *dict.borrow_mut() = Dict::default(),
+                _ => raise!(Type, "cannot clear {target:?}"),
+            }
+            Ok(Value::Undefined)
+        });
+
+        // reverse (reverse the order of the items of a list)
+        exfn!(self, reverse, |&mut list| {
+            list_mut(list)?.reverse();
+            Ok(Value::Undefined)
+        });
+
+        // index_of (index of the first item equal to a value, or undefined if there is none.
+        // strings are searched for a substring instead)
+        exfn!(self, index_of, |list, value; _host| {
+            if let Value::Str(string) = list {
+                return Ok(strings::find(string, strings::text(value)?));
+            }
+            let index = position(list, value)?;
+            Ok(index.map_or(Value::Undefined, |index| Value::Int(index as i64)))
+        });
+
+        // contains (check if a list has an item equal to a value, or a string has a substring)
+        exfn!(self, contains, |list, value; _host| {
+            match list {
+                Value::Str(string) => Ok(Value::Bool(string.contains(strings::text(value)?))),
+                _ => Ok(Value::Bool(position(list, value)?.is_some())),
+            }
+        });
+
+        // slice (items or characters from a start, up to but not including an end)
+        exfn!(self, slice, |list, start, end; _host| {
+            let (start, end) = (start.as_index()?, end.as_index()?);
+            match list {
+                Value::List(items) => {
+                    let end = end.min(items.len());
+                    let items = items[start.min(end)..end].iter();
+                    Ok(Value::List(items.map(|item| item.borrow().clone().into()).collect()))
+                }
+                Value::Str(string) => Ok(Value::Str(string.chars().take(end).skip(start).collect())),
+                _ => raise!(Type, "cannot slice {list:?}"),
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// Gets the items of a list, to change them in place.
+fn list_mut(value: &mut Value) -> Result<&mut Vec<Rc<RefCell<Variable>>>> {
+    match value {
+        Value::List(items) => Ok(items),
+        _ => raise!(Type, "expected a list, found {value:?}"),
+    }
+}
+
+/// Finds the index of the first item of a list equal to `value`, as compared by `==`.
+fn position(list: &Value, value: &Value) -> Result<Option<usize>> {
+    let Value::List(items) = list else {
+        raise!(Type, "cannot search {list:?}");
+    };
+    Ok(items.iter().position(|item| match &*item.borrow() {
+        Variable::Owned(item) => ops::equals(item, value),
+        _ => false,
+    }))
+}
+
+/// Gets the value of a list item.
+fn item_value(item: &RefCell<Variable>) -> Result<Value> {
+    match &*item.borrow() {
+        Variable::Owned(value) => Ok(value.clone()),
+        other => bail!("expected a value, found {other:?}"),
+    }
+}
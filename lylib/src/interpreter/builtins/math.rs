@@ -0,0 +1,190 @@
+//! Native math functions, along with a seedable random number generator. Unlike other external
+//! functions, these are added to a module rather than globally, so that they don't take common
+//! names like `max` or `round` from programs.
+
+use super::*;
+use std::{
+    cell::Cell,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+impl<Out: Write, In: Read> Interpreter<Out, In> {
+    /// Adds the native math functions to the module `alias`, creating it if it doesn't exist yet.
+    /// Files included under the same alias share the module with them.
+    pub fn inject_math(&mut self, alias: impl Into<String>) -> Result<()> {
+        let module = self.memory.borrow_mut().add_module(intern!(alias.into()));
+        self.with_context(Some(module), |interpreter| {
+            interpreter.inject_math_functions()
+        })
+    }
+
+    /// Adds the native math functions to the current memory context.
+    fn inject_math_functions(&mut self) -> Result<()> {
+        // functions of one number, which always return floats
+        for (id, f) in [
+            ("sqrt", f64::sqrt as fn(f64) -> f64),
+            ("sin", f64::sin),
+            ("cos", f64::cos),
+            ("tan", f64::tan),
+            ("exp", f64::exp),
+            ("ln", f64::ln),
+            ("asin", f64::asin),
+            ("acos", f64::acos),
+            ("atan", f64::atan),
+        ] {
+            self.inject_extern(
+                id,
+                Rc::new(move |_host, args| {
+                    let [x] = args else {
+                        bail!("invalid arguments provided to external function");
+                    };
+                    Ok(Value::Float(f(number(x)?)))
+                }),
+            )?;
+        }
+
+        // atan2 & hypot (angle & length of the vector from the origin to a point)
+        exfn!(self, atan2, |y, x; _host| {
+            Ok(Value::Float(number(y)?.atan2(number(x)?)))
+        });
+        exfn!(self, hypot, |x, y; _host| {
+            Ok(Value::Float(number(x)?.hypot(number(y)?)))
+        });
+
+        // log (logarithm in a base)
+        exfn!(self, log, |x, base; _host| {
+            Ok(Value::Float(number(x)?.log(number(base)?)))
+        });
+
+        // floor, ceil, round & trunc (round to an integer. integers are returned as they are)
+        exfn!(self, floor, |x; _host| integer(x, f64::floor));
+        exfn!(self, ceil, |x; _host| integer(x, f64::ceil));
+        exfn!(self, round, |x; _host| integer(x, f64::round));
+        exfn!(self, trunc, |x; _host| integer(x, f64::trunc));
+
+        // abs (absolute value, keeping integers as integers)
+        exfn!(self, abs, |x; _host| {
+            match x {
+                Value::Int(n) => match n.checked_abs() {
+                    Some(n) => Ok(Value::Int(n)),
+                    None => raise!(Arithmetic, "integer overflow taking absolute value of {n}"),
+                },
+                _ => Ok(Value::Float(number(x)?.abs())),
+            }
+        });
+
+        // max, min & clamp (return one of their arguments, as it was given)
+        exfn!(self, max, |a, b; _host| {
+            Ok(if number(b)? > number(a)? { b.clone() } else { a.clone() })
+        });
+        exfn!(self, min, |a, b; _host| {
+            Ok(if number(b)? < number(a)? { b.clone() } else { a.clone() })
+        });
+        exfn!(self, clamp, |x, low, high; _host| {
+            let (n, l, h) = (number(x)?, number(low)?, number(high)?);
+            if l > h {
+                raise!(Arithmetic, "cannot clamp between {low:?} and {high:?}");
+            }
+            Ok(if n < l { low.clone() } else if n > h { high.clone() } else { x.clone() })
+        });
+
+        // is_nan & is_infinite
+        exfn!(self, is_nan, |x; _host| Ok(Value::Bool(number(x)?.is_nan())));
+        exfn!(self, is_infinite, |x; _host| Ok(Value::Bool(number(x)?.is_infinite())));
+
+        // seed, random & random_int share a generator, which is seeded from the clock until a
+        // program seeds it itself
+        let rng = Rc::new(Rng::from_clock());
+        let seeded = rng.clone();
+        exfn!(self, seed, |seed; _host| {
+            let Value::Int(seed) = seed else {
+                raise!(Type, "expected an integer seed, found {seed:?}");
+            };
+            seeded.seed(*seed as u64);
+            Ok(Value::Undefined)
+        });
+
+        // random (float from 0 up to, but not including, 1)
+        let random = rng.clone();
+        exfn!(self, random, |; _host| Ok(Value::Float(random.float())));
+
+        // random_int (integer from a start up to, but not including, an end)
+        exfn!(self, random_int, |start, end; _host| {
+            let (Value::Int(start), Value::Int(end)) = (start, end) else {
+                raise!(Type, "expected integers, found {start:?} and {end:?}");
+            };
+            if start >= end {
+                raise!(Arithmetic, "cannot pick an integer from {start} up to {end}");
+            }
+            let span = (*end as i128 - *start as i128) as u64;
+            Ok(Value::Int((*start as i128 + rng.below(span) as i128) as i64))
+        });
+
+        Ok(())
+    }
+}
+
+/// Gets a number as a float.
+fn number(value: &Value) -> Result<f64> {
+    match value.as_float() {
+        Some(n) => Ok(n),
+        None => raise!(Type, "expected a number, found {value:?}"),
+    }
+}
+
+/// Rounds a number to an integer with `f`. Fails if the result doesn't fit in an integer.
+fn integer(value: &Value, f: fn(f64) -> f64) -> Result<Value> {
+    if let Value::Int(_) = value {
+        return Ok(value.clone());
+    }
+    let n = f(number(value)?);
+    // floats at or above 2^63 would saturate when converted
+    if !n.is_finite() || n < i64::MIN as f64 || n >= i64::MAX as f64 {
+        raise!(Arithmetic, "{n} does not fit in an integer");
+    }
+    Ok(Value::Int(n as i64))
+}
+
+/// A SplitMix64 random number generator. Every seed gives a good sequence, and the same seed
+/// always gives the same one, but it isn't suitable for cryptography.
+struct Rng(Cell<u64>);
+
+impl Rng {
+    /// Creates a generator seeded from the current time.
+    fn from_clock() -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        Self(Cell::new(now.map_or(0, |now| now.as_nanos() as u64)))
+    }
+
+    /// Restarts the sequence from a seed.
+    fn seed(&self, seed: u64) {
+        self.0.set(seed);
+    }
+
+    /// Gets the next number of the sequence.
+    fn next(&self) -> u64 {
+        let state = self.0.get().wrapping_add(0x9E3779B97F4A7C15);
+        self.0.set(state);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    /// Gets a float from 0 up to, but not including, 1.
+    fn float(&self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Gets an integer from 0 up to, but not including, `bound`, without favoring any.
+    fn below(&self, bound: u64) -> u64 {
+        // reject numbers from the incomplete cycle at the top of the range
+        let limit = u64::MAX - u64::MAX % bound;
+        loop {
+            let n = self.next();
+            if n < limit {
+                return n % bound;
+            }
+        }
+    }
+}
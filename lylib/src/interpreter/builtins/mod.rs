@@ -0,0 +1,356 @@
+//! External functions added to every interpreter. Functions working on particular kinds of values
+//! are kept in their own modules.
+
+use super::*;
+
+/// Adds an external function to an interpreter, unpacking its arguments by name. Functions whose
+/// first argument is written `&mut` change it in place, and don't see the interpreter.
+macro_rules! exfn {
+    ($interpreter:expr, $id:tt, |$($arg:ident),*; $host:ident| $body:expr) => {
+        $interpreter.inject_extern(
+            stringify!($id),
+            Rc::new(move |host, args| {
+                let $host = host;
+                let [$($arg),*] = args else { bail!("invalid arguments provided to external function"); };
+                $body
+            })
+        )?
+    };
+    ($interpreter:expr, $id:tt, |&mut $target:ident $(, $arg:ident)*| $body:expr) => {
+        $interpreter.inject_extern_mut(
+            stringify!($id),
+            Rc::new(move |$target, args| {
+                let [$($arg),*] = args else { bail!("invalid arguments provided to external function"); };
+                $body
+            })
+        )?
+    };
+}
+
+mod lists;
+mod math;
+mod strings;
+mod types;
+
+impl<Out: Write, In: Read> Interpreter<Out, In> {
+    /// Adds an arbitrary external function to this interpreter.
+    pub fn inject_extern(&mut self, id: impl Into<String>, closure: Rc<ExFn>) -> Result<()> {
+        let id = id.into();
+        self.declare(&id.as_id(), Variable::Owned(Value::Native(closure)))
+    }
+
+    /// Adds an arbitrary external function that changes its first argument in place to this
+    /// interpreter.
+    pub fn inject_extern_mut(&mut self, id: impl Into<String>, closure: Rc<ExFnMut>) -> Result<()> {
+        let id = id.into();
+        self.declare(&id.as_id(), Variable::Owned(Value::NativeMut(closure)))
+    }
+
+    // `Interpreter::new`, and we don't want anyone using it twice.
+    /// Adds the default external functions to this interpreter.
+    pub(crate) fn inject_builtins(&mut self) -> Result<()> {
+        // print
+        exfn!(self, print, |value; host| {
+            writeln!(host.writer(), "{value}")?;
+            Ok(Value::Undefined)
+        });
+
+        // input (print a prompt, then read a line, or undefined at the end of input)
+        exfn!(self, input, |prompt; host| {
+            write!(host.writer(), "{prompt}")?;
+            host.writer().flush()?;
+            Ok(read_line(host.reader())?.map_or(Value::Undefined, Value::Str))
+        });
+
+        // read_line (read a line without its line ending, or undefined at the end of input)
+        exfn!(self, read_line, |; host| {
+            Ok(read_line(host.reader())?.map_or(Value::Undefined, Value::Str))
+        });
+
+        // read_lines (read the rest of input as a list of lines, or undefined at the end of input)
+        exfn!(self, read_lines, |; host| {
+            let mut lines = vec![];
+            while let Some(line) = read_line(host.reader())? {
+                lines.push(Value::Str(line));
+            }
+            match lines.is_empty() {
+                true => Ok(Value::Undefined),
+                false => Ok(to_list(lines)),
+            }
+        });
+
+        // read_all (read the rest of input as a string, or undefined at the end of input)
+        exfn!(self, read_all, |; host| {
+            let mut buf = vec![];
+            host.reader().read_to_end(&mut buf).context("failed to read input")?;
+            match buf.is_empty() {
+                true => Ok(Value::Undefined),
+                false => Ok(Value::Str(String::from_utf8(buf).context("input is not valid UTF-8")?)),
+            }
+        });
+
+        // length (strings are measured in characters, like they are indexed)
+        exfn!(self, len, |item; _host| {
+            match item {
+                Value::List(items) => Ok(Value::Int(items.len() as i64)),
+                Value::Str(string) => Ok(Value::Int(string.chars().count() as i64)),
+                Value::Dict(dict) => Ok(Value::Int(dict.borrow().len() as i64)),
+                _ => raise!(Type, "cannot take length of {item:?}")
+            }
+        });
+
+        // sort
+        exfn!(self, sort, |list; _host| {
+            let values = items(list).context("cannot sort")?;
+            Ok(to_list(sorted(values.clone(), &values)?))
+        });
+
+        // sort_by (sort by the keys a function returns for each item)
+        exfn!(self, sort_by, |list, f; host| {
+            let values = items(list).context("cannot sort")?;
+            let keys = values
+                .iter()
+                .map(|value| host.call(f, std::slice::from_ref(value)))
+                .collect::<Result<Vec<Value>>>()?;
+            Ok(to_list(sorted(values, &keys)?))
+        });
+
+        // map (apply a function to every item)
+        exfn!(self, map, |list, f; host| {
+            let values = items(list).context("cannot map")?;
+            let mapped = values
+                .into_iter()
+                .map(|value| host.call(f, &[value]))
+                .collect::<Result<Vec<Value>>>()?;
+            Ok(to_list(mapped))
+        });
+
+        // filter (keep items a function returns a truthy value for)
+        exfn!(self, filter, |list, f; host| {
+            let mut kept = vec![];
+            for value in items(list).context("cannot filter")? {
+                if host.call(f, std::slice::from_ref(&value))?.is_truthy() {
+                    kept.push(value);
+                }
+            }
+            Ok(to_list(kept))
+        });
+
+        // reduce (fold items into an accumulator, starting from an initial value)
+        exfn!(self, reduce, |list, f, initial; host| {
+            let mut acc = initial.clone();
+            for value in items(list).context("cannot reduce")? {
+                acc = host.call(f, &[acc, value])?;
+            }
+            Ok(acc)
+        });
+
+        // any (true if a function returns a truthy value for some item)
+        exfn!(self, any, |list, f; host| {
+            for value in items(list).context("cannot check")? {
+                if host.call(f, &[value])?.is_truthy() {
+                    return Ok(Value::Bool(true));
+                }
+            }
+            Ok(Value::Bool(false))
+        });
+
+        // all (true if a function returns a truthy value for every item)
+        exfn!(self, all, |list, f; host| {
+            for value in items(list).context("cannot check")? {
+                if !host.call(f, &[value])?.is_truthy() {
+                    return Ok(Value::Bool(false));
+                }
+            }
+            Ok(Value::Bool(true))
+        });
+
+        // find (first item a function returns a truthy value for, or undefined)
+        // strings are searched for a substring instead, finding the index it starts at
+        exfn!(self, find, |list, f; host| {
+            if let Value::Str(string) = list {
+                return Ok(strings::find(string, strings::text(f)?));
+            }
+            for value in items(list).context("cannot search")? {
+                if host.call(f, std::slice::from_ref(&value))?.is_truthy() {
+                    return Ok(value);
+                }
+            }
+            Ok(Value::Undefined)
+        });
+
+        // keys (get keys of dictionary as list, in order of insertion)
+        exfn!(self, keys, |dict; _host| {
+            let Value::Dict(dict) = dict else {
+                raise!(Type, "cannot get keys of {dict:?}");
+            };
+            Ok(to_list(dict.borrow().iter().map(|(key, _)| key).collect()))
+        });
+
+        // values (get values of dictionary as list, in order of insertion)
+        exfn!(self, values, |dict; _host| {
+            let Value::Dict(dict) = dict else {
+                raise!(Type, "cannot get values of {dict:?}");
+            };
+            let values = dict
+                .borrow()
+                .iter()
+                .map(|(_, variable)| match &*variable.borrow() {
+                    Variable::Owned(value) => Ok(value.clone()),
+                    other => bail!("expected a value, found {other:?}"),
+                })
+                .collect::<Result<Vec<Value>>>()?;
+            Ok(to_list(values))
+        });
+
+        // has (check if dictionary has a key)
+        exfn!(self, has, |dict, key; _host| {
+            let Value::Dict(dict) = dict else {
+                raise!(Type, "cannot check keys of {dict:?}");
+            };
+            Ok(Value::Bool(dict.borrow().get(&key.as_key()?).is_some()))
+        });
+
+        // chars (get characters of string as list)
+        exfn!(self, chars, |string; _host| {
+            match string {
+                Value::Str(v) => {
+                    // collect chars into a list of values
+                    let values = v.chars().map(|ch| Variable::Owned(Value::Char(ch)).into()).collect();
+                    Ok(Value::List(values))
+                },
+                _ => raise!(Type, "cannot fetch characters of {string:?}"),
+            }
+        });
+
+        // range (integers from a start, up to but not including an end, counting by a step)
+        self.inject_extern(
+            "range",
+            Rc::new(|host, args| {
+                let (start, end, step) = match args {
+                    [Value::Int(end)] => (0, *end, 1),
+                    [Value::Int(start), Value::Int(end)] => (*start, *end, 1),
+                    [Value::Int(start), Value::Int(end), Value::Int(step)] => (*start, *end, *step),
+                    _ => raise!(
+                        Type,
+                        "range expects an end, a start & end, or a start, end & step as integers"
+                    ),
+                };
+                if step == 0 {
+                    bail!("range step cannot be zero");
+                }
+
+                // reserve the whole list up front, since it's only counted once it's built
+                let span = end as i128 - start as i128 + step as i128 - step.signum() as i128;
+                let count = span / step as i128;
+                host.reserve(limits::list_size(
+                    count.clamp(0, usize::MAX as i128) as usize
+                ))?;
+
+                let mut values = vec![];
+                let mut n = Some(start);
+                while let Some(i) = n
+                    && (step > 0 && i < end || step < 0 && i > end)
+                {
+                    values.push(Value::Int(i));
+                    n = i.checked_add(step);
+                }
+                Ok(to_list(values))
+            }),
+        )?;
+
+        // assert (returns err if condition != true)
+        exfn!(self, assert, |condition; _host| {
+            // return err unless condition is true
+            if *condition != Value::Bool(true) {
+                raise!(Assertion, "assertion failed");
+            }
+            Ok(Value::Undefined)
+        });
+
+        self.inject_lists()?;
+        self.inject_strings()?;
+        self.inject_types()?;
+        Ok(())
+    }
+}
+
+/// Reads a line, without its line ending. Reads a byte at a time, so that nothing past the line is
+/// taken from the reader. Returns `None` at the end of input.
+fn read_line(reader: &mut dyn Read) -> Result<Option<String>> {
+    let (mut line, mut ended) = (vec![], false);
+    let mut byte = [0];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) => break,
+            Ok(_) if byte[0] == b'\n' => {
+                ended = true;
+                break;
+            }
+            Ok(_) => line.push(byte[0]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).context("failed to read input"),
+        }
+    }
+    if line.is_empty() && !ended {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(Some(
+        String::from_utf8(line).context("input is not valid UTF-8")?,
+    ))
+}
+
+/// Copies the items of a list into values.
+fn items(list: &Value) -> Result<Vec<Value>> {
+    let Value::List(items) = list else {
+        raise!(Type, "expected a list, found {list:?}");
+    };
+    items
+        .iter()
+        .map(|item| match &*item.borrow() {
+            Variable::Owned(value) => Ok(value.clone()),
+            other => bail!("expected a value, found {other:?}"),
+        })
+        .collect()
+}
+
+/// Creates a list from values.
+fn to_list(values: Vec<Value>) -> Value {
+    Value::List(
+        values
+            .into_iter()
+            .map(|value| Variable::Owned(value).into())
+            .collect(),
+    )
+}
+
+/// Sorts values by the key at the same position in `keys`. Only numbers or strings can be
+/// ordered, and every key must be of the same kind. NaN is ordered after every other number. The
+/// sort is stable.
+fn sorted(values: Vec<Value>, keys: &[Value]) -> Result<Vec<Value>> {
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    let ints = keys.iter().map(|key| match key {
+        Value::Int(n) => Some(*n),
+        _ => None,
+    });
+    let strings = keys.iter().map(|key| match key {
+        Value::Str(string) => Some(string.as_str()),
+        _ => None,
+    });
+    if let Some(ints) = ints.collect::<Option<Vec<_>>>() {
+        order.sort_by_key(|&idx| ints[idx]);
+    } else if let Some(strings) = strings.collect::<Option<Vec<_>>>() {
+        order.sort_by_key(|&idx| strings[idx]);
+    } else if let Some(numbers) = keys.iter().map(Value::as_float).collect::<Option<Vec<_>>>() {
+        order.sort_by(|&a, &b| {
+            let (a, b) = (numbers[a], numbers[b]);
+            a.is_nan().cmp(&b.is_nan()).then(a.total_cmp(&b))
+        });
+    } else {
+        raise!(Type, "cannot order {:?}", to_list(keys.to_vec()));
+    }
+    Ok(order.into_iter().map(|idx| values[idx].clone()).collect())
+}
@@ -0,0 +1,104 @@
+//! External functions on strings. Like indexing, they count positions in characters rather than
+//! bytes, so they work with any text.
+
+use super::*;
+
+impl<Out: Write, In: Read> Interpreter<Out, In> {
+    /// Adds the external functions on strings to this interpreter.
+    pub(super) fn inject_strings(&mut self) -> Result<()> {
+        // split (split a string around a separator, or into characters if the separator is empty)
+        exfn!(self, split, |string, separator; _host| {
+            let (string, separator) = (text(string)?, text(separator)?);
+            let parts = match separator.is_empty() {
+                true => string.chars().map(|ch| Value::Str(ch.to_string())).collect(),
+                false => string.split(separator).map(|part| Value::Str(part.into())).collect(),
+            };
+            Ok(to_list(parts))
+        });
+
+        // join (join the items of a list into a string, with a separator between them)
+        exfn!(self, join, |list, separator; _host| {
+            let parts = items(list)
+                .context("cannot join")?
+                .iter()
+                .map(Value::to_string)
+                .collect::<Vec<String>>();
+            Ok(Value::Str(parts.join(text(separator)?)))
+        });
+
+        // trim (remove whitespace from both ends of a string)
+        exfn!(self, trim, |string; _host| {
+            Ok(Value::Str(text(string)?.trim().into()))
+        });
+
+        // starts_with & ends_with
+        exfn!(self, starts_with, |string, prefix; _host| {
+            Ok(Value::Bool(text(string)?.starts_with(text(prefix)?)))
+        });
+        exfn!(self, ends_with, |string, suffix; _host| {
+            Ok(Value::Bool(text(string)?.ends_with(text(suffix)?)))
+        });
+
+        // replace (replace every occurrence of a substring)
+        exfn!(self, replace, |string, from, to; _host| {
+            Ok(Value::Str(text(string)?.replace(text(from)?, text(to)?)))
+        });
+
+        // upper & lower (change the case of every character)
+        exfn!(self, upper, |string; _host| {
+            Ok(Value::Str(text(string)?.to_uppercase()))
+        });
+        exfn!(self, lower, |string; _host| {
+            Ok(Value::Str(text(string)?.to_lowercase()))
+        });
+
+        // repeat (repeat a string a number of times)
+        exfn!(self, repeat, |string, count; host| {
+            let (string, count) = (text(string)?, count.as_index()?);
+            let Some(len) = string.len().checked_mul(count) else {
+                raise!(Arithmetic, "string repeated {count} times is too long");
+            };
+            host.reserve(len)?;
+            Ok(Value::Str(string.repeat(count)))
+        });
+
+        // char_code & from_char_code (convert between characters and their Unicode code points)
+        exfn!(self, char_code, |ch; _host| {
+            let code = match ch {
+                Value::Char(ch) => *ch,
+                Value::Str(string) if string.chars().count() == 1 => {
+                    string.chars().next().unwrap() // safety: the string has one character
+                }
+                _ => raise!(Type, "expected a character, found {ch:?}"),
+            };
+            Ok(Value::Int(code as i64))
+        });
+        exfn!(self, from_char_code, |code; _host| {
+            let Value::Int(n) = code else {
+                raise!(Type, "expected an integer, found {code:?}");
+            };
+            match u32::try_from(*n).ok().and_then(char::from_u32) {
+                Some(ch) => Ok(Value::Char(ch)),
+                None => raise!(Type, "{n} is not a valid character code"),
+            }
+        });
+
+        Ok(())
+    }
+}
+
+/// Gets the text of a string value.
+pub(super) fn text(value: &Value) -> Result<&str> {
+    match value {
+        Value::Str(string) => Ok(string),
+        _ => raise!(Type, "expected a string, found {value:?}"),
+    }
+}
+
+/// Finds the character index `part` first appears at in `string`, or undefined if it doesn't.
+pub(super) fn find(string: &str, part: &str) -> Value {
+    match string.find(part) {
+        Some(at) => Value::Int(string[..at].chars().count() as i64),
+        None => Value::Undefined,
+    }
+}
@@ -0,0 +1,65 @@
+//! External functions for inspecting the types of values, and converting between them.
+
+use super::*;
+
+impl<Out: Write, In: Read> Interpreter<Out, In> {
+    /// Adds the external functions on types to this interpreter.
+    pub(super) fn inject_types(&mut self) -> Result<()> {
+        // type_of (name of the type of a value. instances are named after their structure)
+        exfn!(self, type_of, |value; _host| {
+            let name = match value {
+                Value::Int(_) | Value::Float(_) => "number",
+                Value::Str(_) => "string",
+                Value::Char(_) => "char",
+                Value::Bool(_) => "bool",
+                Value::Undefined => "undefined",
+                Value::List(_) => "list",
+                Value::Dict(_) => "dict",
+                Value::Instance { kind, .. } => return Ok(Value::Str(resolve!(kind.name))),
+                Value::Function { .. } | Value::Native(_) | Value::NativeMut(_) => "function",
+                Value::Type(_) => "type",
+            };
+            Ok(Value::Str(name.into()))
+        });
+
+        // is_instance (check if a value is an instance of a structure)
+        exfn!(self, is_instance, |value, structure; _host| {
+            let Value::Type(structure) = structure else {
+                raise!(Type, "expected a structure, found {structure:?}");
+            };
+            Ok(Value::Bool(matches!(
+                value,
+                Value::Instance { kind, .. } if Rc::ptr_eq(kind, structure)
+            )))
+        });
+
+        // num (convert a value to a number. strings that aren't numbers become undefined)
+        exfn!(self, num, |value; _host| {
+            match value {
+                Value::Int(_) | Value::Float(_) => Ok(value.clone()),
+                Value::Bool(b) => Ok(Value::Int(*b as i64)),
+                Value::Str(string) => {
+                    let string = string.trim();
+                    match (string.parse::<i64>(), string.parse::<f64>()) {
+                        (Ok(n), _) => Ok(Value::Int(n)),
+                        (_, Ok(n)) => Ok(Value::Float(n)),
+                        _ => Ok(Value::Undefined),
+                    }
+                }
+                _ => raise!(Type, "cannot convert {value:?} to a number"),
+            }
+        });
+
+        // str (convert a value to a string, as print shows it)
+        exfn!(self, str, |value; _host| {
+            Ok(Value::Str(value.to_string()))
+        });
+
+        // bool (convert a value to its truthiness)
+        exfn!(self, bool, |value; _host| {
+            Ok(Value::Bool(value.is_truthy()))
+        });
+
+        Ok(())
+    }
+}
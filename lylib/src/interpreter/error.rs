@@ -0,0 +1,218 @@
+//! Runtime errors. Most errors are plain `anyhow` errors, but those raised for common mistakes
+//! carry a kind, which Lily programs see when they catch them. Errors leaving function calls also
+//! carry a stack trace of the calls that were active when they were raised.
+
+use super::*;
+use crate::lexer::Span;
+use std::fmt::Display;
+
+/// The kind of a runtime error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A variable, member or module doesn't exist.
+    Name,
+    /// A list or string was indexed out of its bounds.
+    Index,
+    /// A dictionary doesn't have a key.
+    Key,
+    /// An operation doesn't support the values it was given.
+    Type,
+    /// Division by zero, or an integer overflowed.
+    Arithmetic,
+    /// No arm of a match matched its value.
+    Match,
+    /// An assertion failed.
+    Assertion,
+    /// Too many function calls were active at once, usually because of runaway recursion.
+    Recursion,
+    /// The program ran out of a resource its embedder limited, such as fuel or time. These
+    /// errors can't be caught.
+    ResourceExhausted,
+    /// A Lily program threw a value, which the interpreter holds until it is caught.
+    Thrown,
+    /// Any other error.
+    Runtime,
+}
+
+impl ErrorKind {
+    /// Gets the kind of an error. Errors without a kind are runtime errors.
+    pub fn of(err: &anyhow::Error) -> Self {
+        err.downcast_ref::<RuntimeError>()
+            .map_or(ErrorKind::Runtime, |e| e.kind)
+    }
+
+    /// Returns true if try statements can catch errors of this kind.
+    pub fn is_catchable(&self) -> bool {
+        *self != ErrorKind::ResourceExhausted
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Name => write!(f, "name"),
+            ErrorKind::Index => write!(f, "index"),
+            ErrorKind::Key => write!(f, "key"),
+            ErrorKind::Type => write!(f, "type"),
+            ErrorKind::Arithmetic => write!(f, "arithmetic"),
+            ErrorKind::Match => write!(f, "match"),
+            ErrorKind::Assertion => write!(f, "assertion"),
+            ErrorKind::Recursion => write!(f, "recursion"),
+            ErrorKind::ResourceExhausted => write!(f, "resource exhausted"),
+            ErrorKind::Thrown => write!(f, "thrown"),
+            ErrorKind::Runtime => write!(f, "runtime"),
+        }
+    }
+}
+
+/// An error raised while executing, along with its kind. Displays as its message.
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl RuntimeError {
+    /// Creates a new error of `kind`.
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// A function call, as recorded on the call stack.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Name of the function, or `None` if it is anonymous.
+    pub function: Option<String>,
+    /// Path of the module the function was called through, like `math` in `math.double(2)`.
+    pub module: Option<String>,
+    /// Where the function was called. Unknown for calls made by external functions.
+    pub call_site: Span,
+}
+
+impl Frame {
+    /// Creates a frame for a call to the function node `function`.
+    pub(crate) fn new(function: &ASTNode, module: Option<String>, call_site: Span) -> Self {
+        let function = match function {
+            ASTNode::Function { id: Some(id), .. } => match id.get_kind() {
+                IDKind::Symbol(sym) => Some(resolve!(sym)),
+                _ => None,
+            },
+            _ => None,
+        };
+        Self {
+            function,
+            module,
+            call_site,
+        }
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(module) = &self.module {
+            write!(f, "{module}.")?;
+        }
+        match &self.function {
+            Some(function) => write!(f, "{function}"),
+            None => write!(f, "<anonymous>"),
+        }
+    }
+}
+
+/// The calls that were active when an error was raised, innermost first. Attached to errors as
+/// context when they leave a function call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackTrace(pub Vec<Frame>);
+
+impl Display for StackTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "raised {} calls deep", self.0.len())
+    }
+}
+
+impl<Out: Write, In: Read> Interpreter<Out, In> {
+    /// Runs `f` as the call described by `frame`, recording it on the call stack. Errors leaving
+    /// the call are given a stack trace, unless a call inside of it already gave them one. Fails
+    /// without running `f` if the call stack is already as deep as it may be.
+    pub(crate) fn traced<T>(
+        &mut self,
+        frame: Frame,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        if self.calls.len() >= self.max_call_depth {
+            raise!(
+                Recursion,
+                "maximum call depth of {} exceeded calling {frame}",
+                self.max_call_depth
+            );
+        }
+        self.calls.push(frame);
+        let result = f(self).map_err(|e| match e.downcast_ref::<StackTrace>() {
+            Some(_) => e,
+            None => e.context(StackTrace(self.calls.iter().rev().cloned().collect())),
+        });
+        self.calls.pop();
+        result
+    }
+
+    /// Throws `value` from a Lily program. The value is held until a try statement catches it.
+    pub(crate) fn throw(&mut self, value: Value) -> anyhow::Error {
+        // errors that were caught & thrown again keep their message
+        let message = match error_member(&value, "message") {
+            Some(Value::Str(message)) => message,
+            _ => format!("uncaught {value:?}"),
+        };
+        self.thrown = Some(value);
+        RuntimeError::new(ErrorKind::Thrown, message).into()
+    }
+
+    /// Converts an error caught by a try statement into the value its handler sees. Thrown values
+    /// are caught as they were thrown, while other errors become instances of `Error`, with the
+    /// kind & message of the error as members.
+    pub(crate) fn catch(&mut self, err: &anyhow::Error) -> Value {
+        let kind = ErrorKind::of(err);
+        if kind == ErrorKind::Thrown
+            && let Some(value) = self.thrown.take()
+        {
+            return value;
+        }
+        let mut svt = self.error.template.clone();
+        for (member, value) in [
+            ("kind", kind.to_string()),
+            ("message", err.root_cause().to_string()),
+        ] {
+            svt.assign(intern!(member), Variable::Owned(Value::Str(value)), 0)
+                .expect("errors have a kind & message");
+        }
+        Value::Instance {
+            kind: self.error.clone(),
+            svt: RefCell::new(svt).into(),
+        }
+    }
+}
+
+/// Gets a member of an instance of `Error`, if `value` is one.
+fn error_member(value: &Value, member: &str) -> Option<Value> {
+    let Value::Instance { kind, svt } = value else {
+        return None;
+    };
+    if resolve!(kind.name) != "Error" {
+        return None;
+    }
+    match svt.borrow().get_owned(intern!(member)) {
+        Ok(Variable::Owned(value)) => Some(value),
+        _ => None,
+    }
+}
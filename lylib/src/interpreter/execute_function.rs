@@ -1,32 +1,186 @@
 use super::*;
+use crate::lexer::Span;
 
 impl<Out: Write, In: Read> Interpreter<Out, In> {
-    /// Executes a given function with the given arguments.
+    /// Executes a given function with the given arguments. `captures` holds the variables it
+    /// captured when it was created.
     pub(crate) fn execute_function(
         &mut self,
-        call_args: &[Rc<ASTNode>],
+        call_args: &[Value],
         function: Rc<ASTNode>,
-    ) -> Result<Option<Rc<ASTNode>>> {
+        captures: &Rc<Captures>,
+    ) -> Result<Value> {
         if let ASTNode::Function {
             arguments, body, ..
         } = &*function
         {
             // push arguments
-            assert_eq!(call_args.len(), arguments.len());
-            self.scope_id += 1;
-            for (idx, arg_sym) in arguments.iter().enumerate() {
-                self.declare(
-                    &ID::new_sym(*arg_sym),
-                    Variable::Owned(ASTNode::inner_to_owned(&call_args[idx])),
-                )?;
+            check_arity(arguments.len(), call_args.len())?;
+            let flow = self.with_frame(Some(&function), captures, |interpreter| {
+                for (arg_sym, arg) in arguments.iter().zip(call_args) {
+                    interpreter.declare(&ID::new_sym(*arg_sym), Variable::Owned(arg.clone()))?;
+                }
+                interpreter.execute_block(body.clone())
+            })?;
+
+            return match flow {
+                Some(Flow::Return(value)) => Ok(value),
+                Some(flow) => Err(flow.escaped()),
+                None => Ok(Value::Undefined),
+            };
+        }
+        bail!("failed to execute non-function value")
+    }
+
+    /// Captures the variables visible to a function created at the current scope. Variables
+    /// declared by the innermost function being called and by its blocks are copied into new
+    /// cells, inner scopes shadowing outer ones. Variables of the base scope aren't captured, and
+    /// stay visible as they are.
+    pub(crate) fn capture(&self) -> Captures {
+        let table = self.table();
+        let table = table.borrow();
+        let mut captures = Captures::default();
+        for frame in table.iter().take(self.scope_id + 1).skip(self.frame.max(1)) {
+            for (&sym, variable) in frame {
+                captures.insert(sym, Rc::new(RefCell::new(variable.borrow().clone())));
             }
+        }
+        captures
+    }
 
-            // get result and clear scoped vars
-            let result = self.execute(body.clone())?;
-            self.drop_scope();
+    /// Runs `f` in a new function frame, holding `captures` below a fresh scope for the
+    /// parameters. The frame is dropped afterwards, even if `f` fails.
+    ///
+    /// `function` is the function being called, if any. Named functions declared inside of other
+    /// functions aren't among their own captures, so their name is bound to them in the frame when
+    /// it isn't visible otherwise, letting them call themselves.
+    pub(crate) fn with_frame<T, F>(
+        &mut self,
+        function: Option<&Rc<ASTNode>>,
+        captures: &Rc<Captures>,
+        f: F,
+    ) -> T
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        let (scope_id, frame) = (self.scope_id, self.frame);
+        self.scope_id += 1;
+        self.frame = self.scope_id;
 
-            return Ok(result);
+        // the callers' scopes are hidden, and captured cells are shared, so that changes made by
+        // one call are seen by the next
+        let table = self.table();
+        let hidden = table.borrow_mut().set_frame(self.frame);
+        let own = function.and_then(|function| own_binding(function, captures, &table.borrow()));
+        if !captures.is_empty() || own.is_some() {
+            let mut table = table.borrow_mut();
+            table.share(self.scope_id, captures);
+            if let Some((sym, cell)) = own {
+                table.inner_mut()[self.scope_id].insert(sym, cell);
+            }
+            self.scope_id += 1;
         }
-        bail!("failed to execute non-function value")
+        let result = f(self);
+
+        // drop everything above the previous scope
+        self.scope_id = scope_id + 1;
+        self.drop_scope();
+        self.frame = frame;
+        table.borrow_mut().set_frame(hidden);
+        result
+    }
+
+    /// Creates a new instance of `structure`. If the structure defines a constructor, `construct`
+    /// is called with it while the new instance is the memory context.
+    pub(crate) fn instantiate<F>(&mut self, structure: Rc<Structure>, construct: F) -> Result<Value>
+    where
+        F: FnOnce(&mut Self, Rc<ASTNode>) -> Result<()>,
+    {
+        // copy the template into a new memory table
+        let svt = Rc::new(RefCell::new(structure.template.clone()));
+
+        // if there is a defined constructor, run it
+        if let Some(constructor) = structure.constructor.clone() {
+            self.with_context(Some(svt.clone()), |interpreter| {
+                construct(interpreter, constructor)
+            })?;
+        }
+
+        self.allocate(Value::Instance {
+            kind: structure,
+            svt,
+        })
+    }
+}
+
+impl<Out: Write, In: Read> Host for Interpreter<Out, In> {
+    fn writer(&mut self) -> &mut dyn Write {
+        &mut self.output
+    }
+
+    fn reader(&mut self) -> &mut dyn Read {
+        &mut self.input
+    }
+
+    fn call(&mut self, function: &Value, args: &[Value]) -> Result<Value> {
+        match (self.backend, function) {
+            (Backend::Bytecode, _) => self.call_bytecode(function, args),
+            (Backend::Ast, Value::Native(closure)) => closure.clone()(self, args),
+            (Backend::Ast, Value::NativeMut(closure)) => {
+                self.charge_args(args)?;
+                mutate_copy(&**closure, args)
+            }
+            (Backend::Ast, Value::Function { node, captures }) => {
+                let frame = Frame::new(node, None, Span::default());
+                self.traced(frame, |interpreter| {
+                    interpreter.execute_function(args, node.clone(), captures)
+                })
+            }
+            (Backend::Ast, other) => raise!(Type, "cannot call {other:?}"),
+        }
+    }
+
+    fn reserve(&mut self, bytes: usize) -> Result<()> {
+        Interpreter::reserve(self, bytes)
+    }
+}
+
+/// Binds the name of a named function to itself, unless the name is captured or visible in
+/// `table`.
+fn own_binding(
+    function: &Rc<ASTNode>,
+    captures: &Rc<Captures>,
+    table: &SVTable,
+) -> Option<(usize, Rc<RefCell<Variable>>)> {
+    let ASTNode::Function { id: Some(id), .. } = &**function else {
+        return None;
+    };
+    let IDKind::Symbol(sym) = id.get_kind() else {
+        return None;
+    };
+    if captures.contains_key(&sym) || table.contains(sym) {
+        return None;
+    }
+    let own = Value::Function {
+        node: function.clone(),
+        captures: captures.clone(),
+    };
+    Some((sym, Rc::new(RefCell::new(Variable::Owned(own)))))
+}
+
+/// Calls an external function that changes its first argument on a copy of it, for calls whose
+/// first argument isn't a variable.
+pub(crate) fn mutate_copy(closure: &ExFnMut, args: &[Value]) -> Result<Value> {
+    let Some((first, rest)) = args.split_first() else {
+        bail!("invalid arguments provided to external function");
+    };
+    closure(&mut first.clone(), rest)
+}
+
+/// Returns an error if a function taking `expected` arguments was called with `found` arguments.
+pub(crate) fn check_arity(expected: usize, found: usize) -> Result<()> {
+    if expected != found {
+        bail!("expected {expected} arguments, found {found}");
     }
+    Ok(())
 }
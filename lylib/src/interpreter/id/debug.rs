@@ -1,4 +1,5 @@
 use super::*;
+use crate::interpreter::Value;
 use std::fmt::Debug;
 
 impl Debug for ID {
@@ -11,7 +12,7 @@ impl Debug for IDKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             IDKind::Symbol(sym) => write!(f, "{}", resolve!(*sym)),
-            IDKind::Literal(val) => write!(f, "{val}"),
+            IDKind::Literal(key) => write!(f, "{:?}", Value::from(key)),
             IDKind::Member { parent, member } => write!(f, "{parent:#?} -> {member:#?}"),
         }
     }
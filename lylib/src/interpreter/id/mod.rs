@@ -3,6 +3,7 @@
 /// Debug implementations for `ID` & `IDKind`.
 mod debug;
 
+use super::Key;
 use std::rc::Rc;
 
 #[derive(PartialEq, Eq, Hash, Clone)]
@@ -13,7 +14,7 @@ pub struct ID {
 #[derive(PartialEq, Eq, Hash, Clone)]
 pub enum IDKind {
     Symbol(usize),
-    Literal(usize),
+    Literal(Key),
     Member {
         parent: Rc<IDKind>,
         member: Rc<IDKind>,
@@ -23,18 +24,18 @@ pub enum IDKind {
 /// This trait provides an easy way to convert strings to symbolic IDs.
 pub(crate) trait AsID {
     /// Converts into an `ID` type.
-    fn as_id(self) -> ID;
+    fn as_id(&self) -> ID;
 }
 
 impl AsID for String {
-    fn as_id(self) -> ID {
-        ID::new_sym(intern!(self))
+    fn as_id(&self) -> ID {
+        ID::new_sym(intern!(self.as_str()))
     }
 }
 
 impl AsID for &'static str {
-    fn as_id(self) -> ID {
-        ID::new_sym(intern!(self))
+    fn as_id(&self) -> ID {
+        ID::new_sym(intern!(*self))
     }
 }
 
@@ -63,37 +64,15 @@ impl ID {
         path
     }
 
-    /// Converts an `ID` into a vector of interned identifiers (usize).
-    ///
-    /// Note: This method loses type information about whether components are symbols or literals.
-    /// Use `to_path_kinds()` when you need to distinguish between them.
-    pub fn to_path(&self) -> Vec<usize> {
-        let mut path = Vec::new();
-        self.collect_path_interned(&self.id, &mut path);
-        path
-    }
-
     /// Helper function to recursively collect path components with type information.
     fn collect_path_kinds(&self, kind: &IDKind, path: &mut Vec<IDKind>) {
         match kind {
             IDKind::Symbol(sym) => path.push(IDKind::Symbol(*sym)),
-            IDKind::Literal(val) => path.push(IDKind::Literal(*val)),
+            IDKind::Literal(key) => path.push(IDKind::Literal(key.clone())),
             IDKind::Member { parent, member } => {
                 self.collect_path_kinds(parent, path);
                 self.collect_path_kinds(member, path);
             }
         }
     }
-
-    /// Helper function to recursively collect interned path components.
-    fn collect_path_interned(&self, kind: &IDKind, path: &mut Vec<usize>) {
-        match kind {
-            IDKind::Symbol(sym) => path.push(*sym),
-            IDKind::Literal(val) => path.push(*val),
-            IDKind::Member { parent, member } => {
-                self.collect_path_interned(parent, path);
-                self.collect_path_interned(member, path);
-            }
-        }
-    }
 }
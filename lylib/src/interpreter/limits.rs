@@ -0,0 +1,297 @@
+//! Limits on the resources a Lily program may use, for embedders running untrusted code. Running
+//! out of a resource raises an error that Lily programs can't catch, which leaves the interpreter
+//! usable once the embedder grants more of it.
+
+use super::*;
+use derivative::Derivative;
+use rustc_hash::FxHashSet;
+use std::{
+    mem::size_of,
+    time::{Duration, Instant},
+};
+
+/// Operations between checks of the deadline, since reading the clock is slow compared to an
+/// operation.
+const DEADLINE_INTERVAL: u64 = 1024;
+
+/// Gives more fuel to an execution that ran out, letting it continue where it was. Returns `None`
+/// to stop it instead.
+pub type Refuel = dyn Fn() -> Option<u64>;
+
+/// The limits an interpreter enforces, along with what's left of them.
+#[derive(Derivative, Default)]
+#[derivative(Debug)]
+pub(crate) struct Limits {
+    /// Operations left before execution stops, or `None` if there is no budget.
+    pub(crate) fuel: Option<u64>,
+    /// Asked for more fuel whenever it runs out, if set.
+    #[derivative(Debug = "ignore")]
+    refuel: Option<Rc<Refuel>>,
+    /// Time each execution may take, or `None` if it may take any amount of time.
+    pub(crate) timeout: Option<Duration>,
+    /// When the current execution has to stop, if it has a timeout.
+    deadline: Option<Instant>,
+    /// Operations performed since the deadline was last checked.
+    ticks: u64,
+    /// Approximate number of bytes memory may hold, or `None` if it may hold any amount.
+    pub(crate) memory: Option<usize>,
+    /// Approximate number of bytes allocated since memory usage was last measured.
+    allocated: usize,
+}
+
+impl Limits {
+    /// Starts the timeout of a new execution.
+    pub(crate) fn start(&mut self) {
+        self.deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        self.ticks = 0;
+    }
+
+    /// Spends one operation, failing if there is no fuel left or the deadline has passed. Running
+    /// out of fuel asks for more first, if there's a way to get it.
+    #[inline]
+    fn tick(&mut self) -> Result<()> {
+        if let Some(fuel) = &mut self.fuel {
+            if *fuel == 0 {
+                match self.refuel.as_ref().and_then(|refuel| refuel()) {
+                    Some(more) if more > 0 => *fuel = more,
+                    _ => raise!(ResourceExhausted, "ran out of fuel"),
+                }
+            }
+            *fuel -= 1;
+        }
+        if let Some(deadline) = self.deadline {
+            self.ticks += 1;
+            if self.ticks.is_multiple_of(DEADLINE_INTERVAL) && Instant::now() >= deadline {
+                raise!(ResourceExhausted, "ran out of time");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the approximate number of bytes used by a list of `items` numbers.
+pub(crate) fn list_size(items: usize) -> usize {
+    items.saturating_mul(size_of::<Rc<RefCell<Variable>>>() + size_of::<Value>())
+}
+
+/// Returns the approximate number of bytes used by a copy of a value. Dictionaries, instances &
+/// functions share what they hold when copied, so only their handles are counted.
+pub(crate) fn copy_size(value: &Value) -> usize {
+    let held = match value {
+        Value::Str(string) => string.len(),
+        Value::List(items) => items
+            .iter()
+            .map(|item| {
+                size_of::<Rc<RefCell<Variable>>>()
+                    + match item.try_borrow().as_deref() {
+                        Ok(Variable::Owned(value)) => copy_size(value),
+                        _ => size_of::<Variable>(),
+                    }
+            })
+            .sum(),
+        _ => 0,
+    };
+    size_of::<Value>() + held
+}
+
+/// Approximate memory usage of values, counting values shared between holders once.
+#[derive(Default)]
+struct Usage {
+    /// Addresses of the shared tables, dictionaries & captures already counted.
+    visited: FxHashSet<*const ()>,
+    /// Approximate number of bytes used.
+    bytes: usize,
+}
+
+impl Usage {
+    /// Returns true the first time it's called with the address of `shared`.
+    fn visit<T>(&mut self, shared: &Rc<T>) -> bool {
+        self.visited.insert(Rc::as_ptr(shared) as *const ())
+    }
+
+    /// Counts the variables of a table, along with its modules. Tables that are being changed
+    /// are skipped.
+    fn table(&mut self, table: &Rc<RefCell<SVTable>>) {
+        if !self.visit(table) {
+            return;
+        }
+        let Ok(table) = table.try_borrow() else {
+            return;
+        };
+        for scope in table.iter() {
+            self.cells(scope.values());
+        }
+        for module in table.modules() {
+            self.table(module);
+        }
+    }
+
+    /// Counts the variables held by a collection of cells.
+    fn cells<'a>(&mut self, cells: impl Iterator<Item = &'a Rc<RefCell<Variable>>>) {
+        for cell in cells {
+            self.bytes += size_of::<Rc<RefCell<Variable>>>();
+            if let Ok(variable) = cell.try_borrow() {
+                self.variable(&variable);
+            }
+        }
+    }
+
+    /// Counts a variable. Structures are counted by their declarations, so they're free.
+    fn variable(&mut self, variable: &Variable) {
+        match variable {
+            Variable::Owned(value) => self.value(value),
+            Variable::Type(_) => self.bytes += size_of::<Variable>(),
+        }
+    }
+
+    /// Counts a value, along with every value it holds.
+    fn value(&mut self, value: &Value) {
+        self.bytes += size_of::<Value>();
+        match value {
+            Value::Str(string) => self.bytes += string.len(),
+            Value::List(items) => self.cells(items.iter()),
+            Value::Dict(dict) if self.visit(dict) => {
+                if let Ok(dict) = dict.try_borrow() {
+                    self.bytes += dict.len() * size_of::<(usize, usize)>();
+                    self.cells(dict.iter().map(|(_, cell)| cell));
+                }
+            }
+            Value::Instance { svt, .. } => self.table(svt),
+            Value::Function { captures, .. } if self.visit(captures) => {
+                self.cells(captures.values());
+            }
+            _ => (),
+        }
+    }
+}
+
+impl<Out: Write, In: Read> Interpreter<Out, In> {
+    /// Spends one operation, failing if there is no fuel left or the deadline has passed.
+    #[inline]
+    pub(crate) fn tick(&mut self) -> Result<()> {
+        self.limits.tick()
+    }
+
+    /// Counts a newly created value towards memory usage, failing if memory can't hold it.
+    pub(crate) fn allocate(&mut self, value: Value) -> Result<Value> {
+        if self.limits.memory.is_some() {
+            let mut usage = Usage::default();
+            usage.value(&value);
+            self.reserve(usage.bytes)?;
+        }
+        Ok(value)
+    }
+
+    /// Counts a copy of a value that is being stored towards memory usage, failing if memory
+    /// can't hold it.
+    pub(crate) fn charge(&mut self, variable: &Variable) -> Result<()> {
+        match (self.limits.memory, variable) {
+            (Some(_), Variable::Owned(value)) => self.reserve(copy_size(value)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts the arguments of an external function that changes its first argument towards
+    /// memory usage, since it may store copies of them. They're counted before the call, while
+    /// nothing it changes is borrowed, so that memory is measured in full.
+    pub(crate) fn charge_args(&mut self, args: &[Value]) -> Result<()> {
+        match self.limits.memory {
+            Some(_) => self.reserve(args.iter().map(copy_size).sum()),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails if memory can't hold about `bytes` more bytes. Memory usage is measured again each
+    /// time about half of the limit has been reserved or allocated since the last measurement.
+    pub(crate) fn reserve(&mut self, bytes: usize) -> Result<()> {
+        let Some(limit) = self.limits.memory else {
+            return Ok(());
+        };
+        if bytes > limit {
+            raise!(
+                ResourceExhausted,
+                "memory limit of {limit} bytes exceeded by a value of about {bytes} bytes"
+            );
+        }
+        self.limits.allocated = self.limits.allocated.saturating_add(bytes);
+        if self.limits.allocated > limit / 2 {
+            let usage = self.usage();
+            self.limits.allocated = 0;
+            if usage.bytes.saturating_add(bytes) > limit {
+                raise!(
+                    ResourceExhausted,
+                    "memory limit of {limit} bytes exceeded, holding about {} bytes and needing \
+                     about {bytes} more",
+                    usage.bytes
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Measures the values held by memory, by the current memory context, and by the local slots
+    /// of running chunks.
+    fn usage(&self) -> Usage {
+        let mut usage = Usage::default();
+        usage.table(&self.memory);
+        if let Some(context) = &self.context {
+            usage.table(context);
+        }
+        for slots in &self.locals {
+            if let Ok(slots) = slots.try_borrow() {
+                for variable in slots.iter() {
+                    usage.variable(variable);
+                }
+            }
+        }
+        usage
+    }
+
+    /// Sets the number of operations left before execution stops with a `ResourceExhausted`
+    /// error, or `None` to remove the budget. Operations are counted per evaluated node with the
+    /// AST backend, and per instruction with the bytecode backend.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.limits.fuel = fuel;
+    }
+
+    /// Returns the number of operations left, or `None` if there is no budget.
+    pub fn fuel(&self) -> Option<u64> {
+        self.limits.fuel
+    }
+
+    /// Adds to the operations left, so that buffers can be evaluated again after the fuel ran
+    /// out. Does nothing if there is no budget. Use `set_refuel` to let a buffer continue where
+    /// it ran out instead.
+    pub fn add_fuel(&mut self, fuel: u64) {
+        if let Some(left) = &mut self.limits.fuel {
+            *left = left.saturating_add(fuel);
+        }
+    }
+
+    /// Sets the function asked for more fuel whenever the operations left run out, or `None` to
+    /// stop right away. Fuel it gives lets execution continue from the operation it ran out at,
+    /// with everything it was doing intact. Does nothing if there is no budget.
+    pub fn set_refuel(&mut self, refuel: Option<Rc<Refuel>>) {
+        self.limits.refuel = refuel;
+    }
+
+    /// Sets the time each call to `execute` or `eval` may take before stopping with a
+    /// `ResourceExhausted` error, or `None` to let it run for any amount of time.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.limits.timeout = timeout;
+    }
+
+    /// Sets the approximate number of bytes that lists, strings, dictionaries & instances held
+    /// in memory may use, or `None` to let them use any amount. Once exceeded, execution stops
+    /// with a `ResourceExhausted` error. Memory is measured as values are created & stored, so
+    /// it may briefly hold somewhat more than this.
+    pub fn set_memory_limit(&mut self, bytes: Option<usize>) {
+        self.limits.memory = bytes;
+        self.limits.allocated = 0;
+    }
+
+    /// Returns the approximate number of bytes used by the values held in memory.
+    pub fn memory_usage(&self) -> usize {
+        self.usage().bytes
+    }
+}
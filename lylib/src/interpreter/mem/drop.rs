@@ -5,8 +5,12 @@ use super::*;
 impl<Out: Write, In: Read> Interpreter<Out, In> {
     /// Drops all out-of-scope variables and drops down a scope.
     pub(crate) fn drop_scope(&mut self) {
-        // decrease scope level
+        // decrease scope level, leaving the loops whose bodies were above it
         self.scope_id -= 1;
+        let live = self
+            .loops
+            .partition_point(|(scope, _)| *scope <= self.scope_id);
+        self.loops.truncate(live);
 
         // remove out of scope variables
         if let Some(mod_pointer) = &self.context {
@@ -12,100 +12,104 @@ pub mod variable;
 pub(crate) trait MemoryInterface {
     fn get_owned(&self, id: usize) -> Result<Variable>;
     fn get_ref(&self, id: usize) -> Result<Rc<RefCell<Variable>>>;
-    fn get_module(&self, id: usize) -> Result<Rc<RefCell<SVTable>>>;
     fn declare(&mut self, id: usize, value: Variable, scope: usize) -> Result<()>;
     fn assign(&mut self, id: usize, value: Variable, scope: usize) -> Result<()>;
 }
 
+/// The variable an `ID` names, found by following its path.
+enum Target {
+    /// A variable of a module, or other memory table, by symbol.
+    Name(Rc<RefCell<SVTable>>, usize),
+    /// An item of a list or dictionary, by key.
+    Item(Rc<RefCell<Variable>>, Key),
+}
+
 impl<Out: Write, In: Read> Interpreter<Out, In> {
     /// Helper function to get the target and variable name from an ID.
     ///
     /// Some identifiers reference variables within stacks of modules, and this function resolves
     /// these long chains of reference into the relevant target and variable name.
-    fn resolve_access_target(&self, id: &ID) -> Result<(Rc<RefCell<dyn MemoryInterface>>, usize)> {
+    fn resolve_access_target(&self, id: &ID) -> Result<Target> {
         // get current context (module)
-        let mut module: Rc<RefCell<dyn MemoryInterface>> = match &self.context {
+        let mut module = match &self.context {
             Some(context) => context.clone(),
             None => self.memory.clone(),
         };
 
-        // get variable id, stepping down if required
-        let id = match id.get_kind() {
-            IDKind::Symbol(sym) => sym,
-            IDKind::Literal(val) => val,
-            IDKind::Member {
-                parent: _,
-                member: _,
-            } => {
-                let path = id.to_path();
-                for &item in &path[0..(path.len() - 1)] {
+        // step down to the last component of the path, keeping the list or dictionary it indexes
+        let path = id.to_path_kinds();
+        let (last, steps) = path.split_last().context("empty identifier")?;
+        let mut item: Option<Rc<RefCell<Variable>>> = None;
+        for step in steps {
+            let cell = match (item.take(), step) {
+                (None, IDKind::Symbol(sym)) => {
                     // try to get module first, then check if it's a struct/list access
-                    let module_result = module.borrow().get_module(item);
+                    let module_result = module.borrow().get_module(*sym);
 
                     // if this is a simple module, use that and continue
                     if let Ok(v) = module_result {
                         module = v;
                         continue;
                     }
-
-                    // otherwise, this is a structure or list deref, so we have to find its SVT
-                    let item_ref = module.borrow().get_ref(item)?;
-
-                    match &*item_ref.borrow() {
-                        // expose inner scope for instances
-                        Variable::Owned(ASTNode::Instance { kind: _, svt }) => module = svt.clone(),
-
-                        // all other literals return their parent variable
-                        Variable::Owned(_) => {
-                            module = item_ref.clone();
-                        }
-
-                        _ => {}
-                    };
+                    module.borrow().get_ref(*sym)?
                 }
-                *path.last().unwrap()
-            }
-        };
-
-        Ok((module, id))
+                (Some(cell), IDKind::Literal(key)) => cell.borrow().get_ref(key)?,
+                (_, step) => bail!("cannot access {step:?}"),
+            };
+
+            match &*cell.borrow() {
+                // expose inner scope for instances
+                Variable::Owned(Value::Instance { svt, .. }) => module = svt.clone(),
+
+                // all other values are indexed by the next component
+                Variable::Owned(_) => item = Some(cell.clone()),
+
+                _ => {}
+            };
+        }
+
+        match (item, last) {
+            (None, IDKind::Symbol(sym)) => Ok(Target::Name(module, *sym)),
+            (Some(cell), IDKind::Literal(key)) => Ok(Target::Item(cell, key.clone())),
+            (_, last) => bail!("cannot access {last:?}"),
+        }
     }
 
     /// Gets the value of a variable, and clones it in the process.
     #[inline]
     pub(crate) fn get(&self, id: &ID) -> Result<Variable> {
-        // get absolute module and ID
-        let (module, id) = self.resolve_access_target(id)?;
-
-        // borrow statically to read value
-        let handle = module.borrow();
+        match self.resolve_access_target(id)? {
+            Target::Name(module, sym) => module.borrow().get_owned(sym),
+            Target::Item(cell, key) => cell.borrow().get_owned(&key),
+        }
+    }
 
-        // return value
-        handle.get_owned(id)
+    /// Gets the cell holding a variable, to change it in place.
+    #[inline]
+    pub(crate) fn get_ref(&self, id: &ID) -> Result<Rc<RefCell<Variable>>> {
+        match self.resolve_access_target(id)? {
+            Target::Name(module, sym) => module.borrow().get_ref(sym),
+            Target::Item(cell, key) => cell.borrow().get_ref(&key),
+        }
     }
 
-    /// Declares a new variable.
+    /// Declares a new variable, counting its value towards memory usage.
     #[inline]
     pub(crate) fn declare(&mut self, id: &ID, value: Variable) -> Result<()> {
-        // get absolute module and ID
-        let (module, id) = self.resolve_access_target(id)?;
-
-        // borrow module mutably to make changes
-        let mut module = module.borrow_mut();
-
-        // declare value
-        module.declare(id, value, self.scope_id)
+        self.charge(&value)?;
+        match self.resolve_access_target(id)? {
+            Target::Name(module, sym) => module.borrow_mut().declare(sym, value, self.scope_id),
+            Target::Item(cell, key) => cell.borrow_mut().declare(key, value),
+        }
     }
 
-    /// Assigns to an existing variable.
+    /// Assigns to an existing variable, counting its new value towards memory usage.
     #[inline]
     pub(crate) fn assign(&mut self, id: &ID, value: Variable) -> Result<()> {
-        // get absolute module and ID
-        let (module, id) = self.resolve_access_target(id)?;
-
-        // borrow module mutably to make changes
-        let mut module = module.borrow_mut();
-
-        // assign value
-        module.assign(id, value, self.scope_id)
+        self.charge(&value)?;
+        match self.resolve_access_target(id)? {
+            Target::Name(module, sym) => module.borrow_mut().assign(sym, value, self.scope_id),
+            Target::Item(cell, key) => cell.borrow_mut().assign(key, value),
+        }
     }
 }
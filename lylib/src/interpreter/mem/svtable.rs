@@ -13,10 +13,14 @@ pub struct SVTable {
     table: Vec<FxHashMap<usize, Rc<RefCell<Variable>>>>,
     /// Holds all the modules defined at this SVTable's scope.
     modules: FxHashMap<usize, Rc<RefCell<SVTable>>>,
+    /// Scope of the first frame of the innermost function being called. Scopes below it, other
+    /// than the base scope, belong to its callers, and are hidden from it.
+    frame: usize,
 }
 
 impl Clone for SVTable {
     /// Deep clone the SVTable, creating new Rc<RefCell<Variable>> instances for each variable.
+    /// Dictionaries are shared when copied, so each of them is copied into a new dictionary, too.
     fn clone(&self) -> Self {
         Self {
             // deep clone the table: for each scope, create new Rc<RefCell<Variable>> instances
@@ -26,7 +30,15 @@ impl Clone for SVTable {
                 .map(|scope| {
                     scope
                         .iter()
-                        .map(|(&id, var)| (id, Rc::new(RefCell::new(var.borrow().clone()))))
+                        .map(|(&id, var)| {
+                            let var = match &*var.borrow() {
+                                Variable::Owned(Value::Dict(dict)) => Variable::Owned(Value::Dict(
+                                    RefCell::new(dict.borrow().clone()).into(),
+                                )),
+                                other => other.clone(),
+                            };
+                            (id, Rc::new(RefCell::new(var)))
+                        })
                         .collect()
                 })
                 .collect(),
@@ -36,6 +48,7 @@ impl Clone for SVTable {
                 .iter()
                 .map(|(&id, module)| (id, Rc::new(RefCell::new(module.borrow().clone()))))
                 .collect(),
+            frame: self.frame,
         }
     }
 }
@@ -53,6 +66,7 @@ impl SVTable {
         Self {
             table: vec![FxHashMap::default()],
             modules: FxHashMap::default(),
+            frame: 0,
         }
     }
 
@@ -92,6 +106,12 @@ impl SVTable {
             .ok_or_else(|| anyhow::anyhow!("failed to find module '{}'", resolve!(name)))
     }
 
+    /// Returns the modules defined at this table's scope.
+    #[inline]
+    pub fn modules(&self) -> impl Iterator<Item = &Rc<RefCell<SVTable>>> {
+        self.modules.values()
+    }
+
     /// Adds a new scope.
     #[inline]
     pub fn add_scope(&mut self) {
@@ -107,6 +127,28 @@ impl SVTable {
         self.table.get_mut(index)
     }
 
+    /// Inserts shared variables into a scope, adding scopes if necessary. Unlike declarations,
+    /// the variables keep their cells, so assignments to them are seen by every holder.
+    pub fn share(&mut self, scope: usize, variables: &FxHashMap<usize, Rc<RefCell<Variable>>>) {
+        while self.scopes() <= scope {
+            self.add_scope();
+        }
+        self.table[scope].extend(variables.iter().map(|(&id, cell)| (id, cell.clone())));
+    }
+
+    /// Hides the scopes below `frame` from lookups, other than the base scope, returning the
+    /// previous frame so that it can be restored.
+    #[inline]
+    pub fn set_frame(&mut self, frame: usize) -> usize {
+        std::mem::replace(&mut self.frame, frame)
+    }
+
+    /// Returns true if a variable is visible in this table.
+    #[inline]
+    pub fn contains(&self, id: usize) -> bool {
+        self.find_variable(id).is_some()
+    }
+
     /// Returns the number of scopes in this table.
     #[inline]
     pub fn scopes(&self) -> usize {
@@ -115,26 +157,17 @@ impl SVTable {
 }
 
 impl SVTable {
-    /// Helper method to find a variable in any scope, returns the found variable reference.
+    /// Helper method to find a variable in any visible scope, returns the found variable reference.
     #[inline]
     fn find_variable(&self, id: usize) -> Option<&Rc<RefCell<Variable>>> {
-        for scope in self.iter().rev() {
-            if let Some(variable) = scope.get(&id) {
-                return Some(variable);
-            }
-        }
-        None
+        self.visible().find_map(|scope| scope.get(&id))
     }
 
-    /// Helper method to find a variable in any scope with mutable access, returns the found variable reference.
+    /// Returns the scopes visible from the current frame, innermost first.
     #[inline]
-    fn find_variable_mut(&mut self, id: usize) -> Option<&Rc<RefCell<Variable>>> {
-        for scope in self.inner_mut().iter_mut().rev() {
-            if let Some(variable) = scope.get(&id) {
-                return Some(variable);
-            }
-        }
-        None
+    fn visible(&self) -> impl Iterator<Item = &FxHashMap<usize, Rc<RefCell<Variable>>>> {
+        let frame = self.table.get(self.frame.max(1)..).unwrap_or_default();
+        frame.iter().rev().chain(self.table.first())
     }
 }
 
@@ -143,7 +176,7 @@ impl MemoryInterface for SVTable {
     fn get_owned(&self, id: usize) -> Result<Variable> {
         match self.find_variable(id) {
             Some(variable) => Ok(variable.borrow().clone()),
-            None => bail!("failed to get owned value {:#?}", resolve!(id)),
+            None => raise!(Name, "failed to get owned value {:#?}", resolve!(id)),
         }
     }
 
@@ -151,17 +184,7 @@ impl MemoryInterface for SVTable {
     fn get_ref(&self, id: usize) -> Result<Rc<RefCell<Variable>>> {
         match self.find_variable(id) {
             Some(variable) => Ok(variable.clone()),
-            None => bail!("failed to get ref value {:#?}", resolve!(id)),
-        }
-    }
-
-    #[inline]
-    fn get_module(&self, id: usize) -> Result<Rc<RefCell<SVTable>>> {
-        match self.modules.get(&id) {
-            Some(module) => Ok(module.clone()),
-            _ => {
-                bail!("could not find module '{:#?}'", resolve!(id))
-            }
+            None => raise!(Name, "failed to get ref value {:#?}", resolve!(id)),
         }
     }
 
@@ -185,7 +208,7 @@ impl MemoryInterface for SVTable {
     #[inline]
     fn assign(&mut self, id: usize, value: Variable, scope: usize) -> Result<()> {
         // replace the value of the top-most variable if possible
-        if let Some(variable) = self.find_variable_mut(id) {
+        if let Some(variable) = self.find_variable(id) {
             *variable.borrow_mut() = value;
             return Ok(());
         }
@@ -201,51 +224,6 @@ impl MemoryInterface for SVTable {
 
 impl Display for SVTable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        fn prettify(node: Rc<ASTNode>) -> String {
-            match &*node {
-                ASTNode::Literal(Token::Identifier(id)) => resolve!(*id),
-                ASTNode::Literal(token) => format!("{token:#?}"),
-                ASTNode::Op { lhs, op, rhs } => format!(
-                    "{} {:#?} {}",
-                    prettify(lhs.clone()),
-                    op,
-                    prettify(rhs.clone())
-                ),
-                ASTNode::Block(lines) => lines
-                    .iter()
-                    .map(|ln| prettify(ln.clone()))
-                    .collect::<Vec<String>>()
-                    .join(", ")
-                    .to_string(),
-                ASTNode::Return(value) => prettify(value.clone()),
-                ASTNode::Function {
-                    id,
-                    arguments,
-                    body,
-                } => format!(
-                    "{}({}) => {}",
-                    id.to_path_kinds()
-                        .iter()
-                        .map(|kind| match kind {
-                            IDKind::Symbol(sym) => resolve!(*sym),
-                            IDKind::Literal(val) => val.to_string(),
-                            IDKind::Member { .. } => {
-                                unreachable!("member should be flattened by to_path_kinds")
-                            }
-                        })
-                        .collect::<Vec<String>>()
-                        .join("."),
-                    arguments
-                        .iter()
-                        .map(|id| resolve!(*id))
-                        .collect::<Vec<String>>()
-                        .join(", "),
-                    prettify(body.clone())
-                ),
-                other => format!("{other:#?}"),
-            }
-        }
-
         // log scopes progressively
         for (scope_idx, scope) in self.table.iter().enumerate() {
             // log scope level
@@ -257,12 +235,7 @@ impl Display for SVTable {
             for &key in keys {
                 // obtain debug string respective to variable value
                 let value = scope.get(&key).unwrap();
-                let dbg_ln = match &*value.borrow() {
-                    Variable::Owned(node) => prettify(node.to_owned().into()).to_string(),
-                    Variable::Function(reference) => format!("&{}", prettify(reference.clone())),
-                    Variable::Extern(_) => "EXTERN".to_string(),
-                    Variable::Type(instance) => format!("struct {}", prettify(instance.clone())),
-                };
+                let dbg_ln = format!("{:?}", value.borrow());
 
                 // tab out endlines to keep indents, and print it
                 let dbg_ln = dbg_ln.replace("\n", "\n\t");
@@ -1,24 +1,13 @@
 use super::*;
-use std::{cmp::Ordering, fmt::Debug, mem::discriminant};
-
-/// External function signature.
-/// The first two arguments are the input and output handles. The third contains arguments.
-pub type ExFn = dyn for<'a> Fn(
-    &'a mut dyn Write,
-    &'a mut dyn Read,
-    &Vec<Rc<ASTNode>>,
-) -> Result<Option<Rc<ASTNode>>>;
+use std::fmt::Debug;
 
 /// Represents stored information.
+#[derive(Clone)]
 pub enum Variable {
-    /// For owned variables.
-    Owned(ASTNode),
-    /// For functions.
-    Function(Rc<ASTNode>),
-    /// For external functions.
-    Extern(Rc<ExFn>),
+    /// For values.
+    Owned(Value),
     /// For non-standard types, such as structures.
-    Type(Rc<ASTNode>),
+    Type(Rc<Structure>),
 }
 
 impl From<Variable> for Rc<RefCell<Variable>> {
@@ -27,35 +16,18 @@ impl From<Variable> for Rc<RefCell<Variable>> {
     }
 }
 
-impl From<ASTNode> for Variable {
-    fn from(value: ASTNode) -> Self {
+impl From<Value> for Variable {
+    fn from(value: Value) -> Self {
         Self::Owned(value)
     }
 }
 
-impl From<Rc<ASTNode>> for Variable {
-    fn from(value: Rc<ASTNode>) -> Self {
-        Self::Owned(ASTNode::inner_to_owned(&value))
-    }
-}
-
-impl Clone for Variable {
-    fn clone(&self) -> Self {
-        match self {
-            // lists deeply clone their items
-            Variable::Owned(ASTNode::List(items)) => {
-                let cloned_items: Vec<_> = items
-                    .iter()
-                    .map(|item| Rc::new(RefCell::new(item.borrow().clone())))
-                    .collect();
-                Variable::Owned(ASTNode::List(cloned_items))
-            }
-
-            // all other variables are cloned as is
-            Variable::Owned(node) => Variable::Owned(node.clone()),
-            Variable::Function(node) => Variable::Function(node.clone()),
-            Variable::Extern(func) => Variable::Extern(func.clone()),
-            Variable::Type(node) => Variable::Type(node.clone()),
+/// Structure types become type values.
+impl From<Variable> for Value {
+    fn from(variable: Variable) -> Self {
+        match variable {
+            Variable::Owned(value) => value,
+            Variable::Type(structure) => Value::Type(structure),
         }
     }
 }
@@ -63,93 +35,79 @@ impl Clone for Variable {
 impl Debug for Variable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Variable::Owned(node) => write!(f, "{node:#?}"),
-            Variable::Function(node) | Variable::Type(node) => write!(f, "&{node:#?}"),
-            Variable::Extern(_) => write!(f, "EXTERN"),
+            Variable::Owned(value) => write!(f, "{value:?}"),
+            Variable::Type(structure) => write!(f, "<struct {}>", resolve!(structure.name)),
         }
     }
 }
 
 impl PartialEq for Variable {
     fn eq(&self, other: &Self) -> bool {
-        // if variables are not the same variant, false
-        if !(discriminant(self) == discriminant(other)) {
-            return false;
-        }
-
-        // otherwise, all variants follow regular comparison rules except externals
         match (self, other) {
             (Variable::Owned(a), Variable::Owned(b)) => a == b,
-            (Variable::Function(a), Variable::Function(b))
-            | (Variable::Type(a), Variable::Type(b)) => a == b,
-            _ => panic!("cannot compare external variables ({self:?}, {other:?})"),
-        }
-    }
-}
-
-impl Eq for Variable {}
-
-impl PartialOrd for Variable {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (
-                Variable::Owned(ASTNode::Literal(Token::Number(a))),
-                Variable::Owned(ASTNode::Literal(Token::Number(b))),
-            ) => a.partial_cmp(b),
-            (
-                Variable::Owned(ASTNode::Literal(Token::Str(a))),
-                Variable::Owned(ASTNode::Literal(Token::Str(b))),
-            ) => a.partial_cmp(b),
-            _ => panic!("cannot order variables ({self:?}, {other:?})"),
+            (Variable::Type(a), Variable::Type(b)) => Rc::ptr_eq(a, b),
+            _ => false,
         }
     }
 }
 
-impl Ord for Variable {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap_or(Ordering::Less)
+/// Lists and dictionaries are accessed by key, as created by `Value::as_key`.
+impl Variable {
+    /// Gets a copy of the item at `key`.
+    pub(crate) fn get_owned(&self, key: &Key) -> Result<Variable> {
+        Ok(self.get_ref(key)?.borrow().clone())
     }
-}
 
-impl MemoryInterface for Variable {
-    fn get_owned(&self, id: usize) -> Result<Variable> {
-        if let Variable::Owned(ASTNode::List(items)) = self {
-            let item = items.get(id).context("index out of bounds")?;
-            let inner = item.borrow().clone();
-            Ok(inner)
-        } else {
-            bail!("invalid access to variable '{self:#?}'");
-        }
-    }
-
-    fn get_ref(&self, id: usize) -> Result<Rc<RefCell<Variable>>> {
-        if let Variable::Owned(ASTNode::List(items)) = self {
-            let item = items.get(id).context("index out of bounds")?;
-            Ok(item.clone())
-        } else {
-            bail!("invalid access to variable '{self:#?}'");
+    /// Gets the cell holding the item at `key`.
+    pub(crate) fn get_ref(&self, key: &Key) -> Result<Rc<RefCell<Variable>>> {
+        match self {
+            Variable::Owned(Value::List(items)) => match items.get(key.as_index()?) {
+                Some(item) => Ok(item.clone()),
+                None => raise!(Index, "index out of bounds"),
+            },
+            Variable::Owned(Value::Dict(dict)) => match dict.borrow().get(key) {
+                Some(item) => Ok(item.clone()),
+                None => raise!(Key, "key {:?} does not exist", Value::from(key)),
+            },
+            _ => bail!("invalid access to variable '{self:#?}'"),
         }
     }
 
-    fn get_module(&self, _: usize) -> Result<Rc<RefCell<SVTable>>> {
-        bail!("variables cannot contain modules");
-    }
-
-    fn declare(&mut self, id: usize, value: Variable, _: usize) -> Result<()> {
-        if let Variable::Owned(ASTNode::List(items)) = self {
-            items.insert(id, value.into());
-            Ok(())
-        } else {
-            bail!("invalid declaration to variable '{self:#?}'");
+    /// Adds an item at `key`. Lists shift the items after it, while dictionaries fail if the key
+    /// already exists.
+    pub(crate) fn declare(&mut self, key: Key, value: Variable) -> Result<()> {
+        match self {
+            Variable::Owned(Value::List(items)) => {
+                items.insert(key.as_index()?, value.into());
+                Ok(())
+            }
+            Variable::Owned(Value::Dict(dict)) => {
+                if dict.borrow().get(&key).is_some() {
+                    bail!("key {:?} already exists", Value::from(&key));
+                }
+                dict.borrow_mut().insert(key, value);
+                Ok(())
+            }
+            _ => bail!("invalid declaration to variable '{self:#?}'"),
         }
     }
 
-    fn assign(&mut self, id: usize, value: Variable, _: usize) -> Result<()> {
-        if let Variable::Owned(ASTNode::List(items)) = self {
-            *items.get_mut(id).context("index out of bounds")? = value.into();
-            Ok(())
-        } else {
-            bail!("invalid assignment to variable '{self:#?}'");
+    /// Replaces the item at `key`.
+    pub(crate) fn assign(&mut self, key: Key, value: Variable) -> Result<()> {
+        match self {
+            Variable::Owned(Value::List(items)) => {
+                match items.get_mut(key.as_index()?) {
+                    Some(item) => *item = value.into(),
+                    None => raise!(Index, "index out of bounds"),
+                }
+                Ok(())
+            }
+            // assigning to a new key of a dictionary inserts it
+            Variable::Owned(Value::Dict(dict)) => {
+                dict.borrow_mut().insert(key, value);
+                Ok(())
+            }
+            _ => bail!("invalid assignment to variable '{self:#?}'"),
         }
     }
 }
@@ -1,24 +1,90 @@
 //! The interpreter executes an abstract syntax tree.
 
 mod builtins;
+mod error;
 mod execute_function;
 mod id;
+mod limits;
 mod mem;
 mod node_to_id;
-mod resolve_refs;
+mod ops;
 mod tests;
-
-use crate::{lexer::Token, parser::ASTNode, *};
-use anyhow::{Context, Result, bail};
+mod value;
+mod vm;
+
+use crate::{
+    diagnostics::{Diagnostic, Stage},
+    execute::SourceOptions,
+    lexer::{Span, Token, WithSpan},
+    parser::{ASTNode, Pattern},
+    *,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use rustc_hash::FxHashMap;
 use std::{
     cell::RefCell,
     io::{Read, Write},
+    ops::ControlFlow,
     path::PathBuf,
     rc::Rc,
 };
 
+pub use error::{ErrorKind, Frame, RuntimeError, StackTrace};
 pub(crate) use id::*;
+pub use limits::Refuel;
 pub(crate) use mem::{MemoryInterface, svtable::SVTable, variable::*};
+pub(crate) use value::{Captures, Key};
+pub use value::{Dict, ExFn, ExFnMut, Host, Structure, Value};
+
+/// The strategy an interpreter uses to execute syntax trees. Both backends share memory and
+/// builtins, and produce the same results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Walks the syntax tree directly.
+    #[default]
+    Ast,
+    /// Compiles the syntax tree into bytecode, and runs it on a stack-based virtual machine.
+    Bytecode,
+}
+
+/// The number of function calls that may be active at once, unless configured otherwise. Each call
+/// uses the native stack, so this is kept low enough to fit within the stack of a main thread.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 256;
+
+/// The body of a matched arm, along with the names its pattern binds and their values.
+type MatchedArm<'a> = (&'a Rc<ASTNode>, Vec<(usize, Value)>);
+
+/// Control flow leaving a block early. Breaks & continues hold the label of the loop they apply
+/// to, or `None` for the innermost loop.
+enum Flow {
+    /// Returns a value from the enclosing function.
+    Return(Value),
+    /// Breaks out of a loop.
+    Break(Option<usize>),
+    /// Skips to the next iteration of a loop.
+    Continue(Option<usize>),
+}
+
+impl Flow {
+    /// Returns an error for this flow leaving a function, which only returns may do.
+    fn escaped(&self) -> anyhow::Error {
+        match self {
+            Flow::Return(_) => anyhow!("cannot return as base scope"),
+            Flow::Break(label) => anyhow!("cannot break outside of {}", describe_loop(*label)),
+            Flow::Continue(label) => {
+                anyhow!("cannot continue outside of {}", describe_loop(*label))
+            }
+        }
+    }
+}
+
+/// Describes the loop a break or continue applies to, for errors.
+fn describe_loop(label: Option<usize>) -> String {
+    match label {
+        Some(label) => format!("a loop labeled '{}'", resolve!(label)),
+        None => "a loop".into(),
+    }
+}
 
 /// The interpreter executes Abstract Syntax Trees (ASTs) and manages program state.
 #[derive(Debug)]
@@ -31,10 +97,37 @@ pub struct Interpreter<Out: Write, In: Read> {
     context: Option<Rc<RefCell<SVTable>>>,
     /// Scope level.
     scope_id: usize,
+    /// Scope level of the first frame of the innermost function being called. Zero outside of
+    /// functions.
+    frame: usize,
+    /// Loops being executed, innermost last, each as the scope level of its body & its label.
+    loops: Vec<(usize, Option<usize>)>,
     /// Output buffer. Typically `stdout`.
     output: Out,
     /// Input buffer. Typically `stdin`.
     input: In,
+    /// Backend used to execute syntax trees.
+    backend: Backend,
+    /// Compiled function bodies, keyed by the address of their function node. The node is kept
+    /// alongside its bytecode so that the address can't be reused.
+    chunks: FxHashMap<*const ASTNode, (Rc<ASTNode>, Rc<vm::Chunk>)>,
+    /// Local slots of the chunks running on the virtual machine, innermost last, kept here so
+    /// that they count towards memory usage.
+    locals: Vec<Rc<vm::Slots>>,
+    /// Options used to lex & parse buffers passed to `eval`.
+    pub(crate) source: SourceOptions,
+    /// Warnings found while parsing buffers passed to `eval`, not yet taken.
+    warnings: Vec<Diagnostic>,
+    /// The value thrown by the error being raised, if it was thrown by a Lily program.
+    thrown: Option<Value>,
+    /// The structure of the errors caught by try statements.
+    error: Rc<Structure>,
+    /// Function calls being executed, innermost last.
+    calls: Vec<Frame>,
+    /// The number of function calls that may be executed at once.
+    max_call_depth: usize,
+    /// Limits on the fuel & time used by programs.
+    limits: limits::Limits,
 }
 impl<Out: Write, In: Read> Interpreter<Out, In> {
     /// Creates a new interpreter with default builtins.
@@ -43,8 +136,20 @@ impl<Out: Write, In: Read> Interpreter<Out, In> {
             memory: Rc::new(RefCell::new(SVTable::default())),
             context: None,
             scope_id: 0,
+            frame: 0,
+            loops: vec![],
             output,
             input,
+            backend: Backend::default(),
+            chunks: FxHashMap::default(),
+            locals: vec![],
+            warnings: vec![],
+            source: SourceOptions::default(),
+            thrown: None,
+            error: Structure::error().into(),
+            calls: vec![],
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            limits: limits::Limits::default(),
         };
         i.inject_builtins()
             .context("failed to add builtins")
@@ -62,6 +167,25 @@ impl<Out: Write, In: Read> Interpreter<Out, In> {
         &mut self.output
     }
 
+    /// Gets the backend used to execute syntax trees.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Sets the backend used to execute syntax trees.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    /// Sets the number of function calls that may be active at once. Calls beyond it raise a
+    /// `recursion` error, which Lily programs can catch.
+    ///
+    /// Every call uses the native stack, so raising this far above `DEFAULT_MAX_CALL_DEPTH` may
+    /// require running the interpreter on a thread with a larger stack.
+    pub fn set_max_call_depth(&mut self, depth: usize) {
+        self.max_call_depth = depth;
+    }
+
     /// Executes a closure with a temporary memory context, restoring the previous context after
     /// execution has completed. Propagates all errors.
     #[inline]
@@ -76,460 +200,664 @@ impl<Out: Write, In: Read> Interpreter<Out, In> {
         result
     }
 
-    /// Executes an AST segment, typically the head. Returns `Some` when a return block is reached.
-    pub fn execute(&mut self, ast: Rc<ASTNode>) -> Result<Option<Rc<ASTNode>>> {
-        if let ASTNode::Block(statements) = &*ast {
+    /// Returns the current memory table, which is the memory context if there is one.
+    fn table(&self) -> Rc<RefCell<SVTable>> {
+        self.context.clone().unwrap_or(self.memory.clone())
+    }
+
+    /// Lexes, parses and executes a buffer against the memory of this interpreter, so that it can
+    /// use everything declared by previous buffers. Returns the value of the buffer's trailing
+    /// expression, or undefined if it doesn't end with one.
+    pub fn eval(&mut self, source: impl Into<String>) -> Result<Value> {
+        let (ast, warnings) = self.source.parse(source.into())?;
+        self.warnings.extend(warnings);
+        self.execute(ast).context(Stage::Execute)
+    }
+
+    /// Takes the warnings found while parsing the buffers passed to `eval`, such as matches that
+    /// may not be exhaustive.
+    pub fn take_warnings(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Executes an AST segment, typically the head, with the selected backend. If the segment
+    /// ends with an expression, returns its value. Otherwise, returns undefined.
+    pub fn execute(&mut self, ast: Rc<ASTNode>) -> Result<Value> {
+        self.limits.start();
+        match self.backend {
+            Backend::Ast => {
+                let result = self.walk(ast);
+
+                // errors leaving blocks at base scope leave their scopes behind, which are
+                // dropped so that later buffers start at base scope
+                if result.is_err() && self.scope_id > 0 {
+                    self.scope_id = 1;
+                    self.drop_scope();
+                }
+                result
+            }
+            Backend::Bytecode => self.execute_bytecode(&ast),
+        }
+    }
+
+    /// Walks an AST segment, evaluating to its trailing expression, or to undefined if it doesn't
+    /// end with one.
+    fn walk(&mut self, ast: Rc<ASTNode>) -> Result<Value> {
+        if let ASTNode::Block { statements, .. } = &*ast
+            && let Some((last, rest)) = statements.split_last()
+            && last.is_expression()
+        {
+            self.execute_block(
+                ASTNode::Block {
+                    statements: rest.to_vec(),
+                    span: Span::default(),
+                }
+                .into(),
+            )?;
+            return self
+                .execute_expr(last)
+                .context("failed to evaluate expression");
+        }
+        self.execute_block(ast).map(|_| Value::Undefined)
+    }
+
+    /// Walks an AST segment. Returns `Some` when control flow leaves the block early.
+    fn execute_block(&mut self, ast: Rc<ASTNode>) -> Result<Option<Flow>> {
+        if let ASTNode::Block { statements, .. } = &*ast {
             // if this segment is a block, execute all of its statements
             for statement in statements {
-                if let Some(ret_value) = self
-                    .execute_expr(statement)
-                    .context("failed to evaluate expression")?
-                {
+                let flow = self
+                    .execute_statement(statement)
+                    .context("failed to evaluate expression")?;
+                if let Some(flow) = flow {
                     if self.scope_id == 0 {
-                        bail!("cannot return as base scope");
+                        return Err(flow.escaped());
                     }
-                    return Ok(Some(ret_value));
+                    return Ok(Some(flow));
                 }
             }
+            Ok(None)
         } else {
             // otherwise, execute the segment by itself
-            self.execute_expr(&ast)
-                .context("failed to execute expression")?;
+            self.execute_statement(&ast)
+                .context("failed to execute expression")
         }
-        Ok(None)
     }
 
-    /// Executes an individual expression.
-    fn execute_expr(&mut self, statement: &Rc<ASTNode>) -> Result<Option<Rc<ASTNode>>> {
-        let statement = statement.clone();
+    /// Executes an individual statement. Returns `Some` when control flow leaves the enclosing
+    /// block. Errors are tagged with the location of the innermost node that failed.
+    fn execute_statement(&mut self, statement: &Rc<ASTNode>) -> Result<Option<Flow>> {
+        let result = self.tick().and_then(|_| self.execute_control(statement));
+        result.at(statement.span())
+    }
+
+    /// Executes an individual statement. Use `execute_statement` to keep location information on
+    /// errors.
+    fn execute_control(&mut self, statement: &Rc<ASTNode>) -> Result<Option<Flow>> {
         match statement.as_ref() {
-            ASTNode::Literal(Token::Identifier(sym)) => {
-                // resolve variable and return literal value
-                match self.get(&ID::new_sym(*sym))? {
-                    Variable::Owned(var) => Ok(Some(var.into())),
-                    Variable::Function(func) => Ok(Some(func.clone())),
-                    _ => Ok(None),
+            ASTNode::Return { value, .. } => {
+                let value = self
+                    .execute_expr(value)
+                    .context("failed to evaluate return expression")?;
+                Ok(Some(Flow::Return(value)))
+            }
+            ASTNode::Break { label, .. } => Ok(Some(Flow::Break(*label))),
+            ASTNode::Continue { label, .. } => Ok(Some(Flow::Continue(*label))),
+            ASTNode::Conditional {
+                condition,
+                if_body,
+                else_body,
+                ..
+            } => {
+                // evaluate condition
+                let condition = self
+                    .execute_expr(condition)
+                    .context("failed to evaluate condition")?;
+
+                // increase scope level
+                self.scope_id += 1;
+
+                // execute if-body if statement is true. otherwise, execute else body
+                let flow = self.execute_block(match condition.is_truthy() {
+                    true => if_body.clone(),
+                    false => else_body.clone(),
+                })?;
+
+                // after finishing, drop the scope
+                self.drop_scope();
+                Ok(flow)
+            }
+            ASTNode::Loop {
+                label,
+                condition,
+                body,
+                ..
+            } => {
+                // increase scope level and execute body
+                self.scope_id += 1;
+                self.loops.push((self.scope_id, *label));
+                let mut flow = None;
+                while self.execute_expr(condition)?.is_truthy() {
+                    // get cycle result. if the loop is left, stop
+                    if let ControlFlow::Break(leaving) = self.iterate(body, *label)? {
+                        flow = leaving;
+                        break;
+                    }
+                    // after each execution of the loop, clear values at this scope
+                    self.drop_here();
                 }
+
+                // loop finished, drop locals and continue
+                self.drop_scope();
+                Ok(flow)
+            }
+            ASTNode::For {
+                label,
+                index,
+                item,
+                iterable,
+                body,
+                ..
+            } => {
+                // count through ranges as the loop goes, and gather other entries up front, so
+                // that the body can't change what's visited
+                let entries: Box<dyn Iterator<Item = (Value, Value)>> = match &**iterable {
+                    ASTNode::Range { start, end, .. } => {
+                        let start = self
+                            .execute_expr(start)
+                            .context("failed to evaluate start of range")?;
+                        let end = self
+                            .execute_expr(end)
+                            .context("failed to evaluate end of range")?;
+                        let (start, end) = ops::bounds(&start, &end)?;
+                        Box::new(
+                            (start..end)
+                                .enumerate()
+                                .map(|(i, n)| (Value::Int(i as i64), Value::Int(n))),
+                        )
+                    }
+                    _ => {
+                        let iterable = self
+                            .execute_expr(iterable)
+                            .context("failed to evaluate iterated expression")?;
+                        Box::new(ops::entries(&iterable, index.is_some())?.into_iter())
+                    }
+                };
+
+                // bind each entry in a fresh scope and execute body
+                self.scope_id += 1;
+                self.loops.push((self.scope_id, *label));
+                let mut flow = None;
+                for (key, value) in entries {
+                    if let Some(index) = index {
+                        self.declare(&ID::new_sym(*index), Variable::Owned(key))?;
+                    }
+                    self.declare(&ID::new_sym(*item), Variable::Owned(value))?;
+                    if let ControlFlow::Break(leaving) = self.iterate(body, *label)? {
+                        flow = leaving;
+                        break;
+                    }
+                    self.drop_here();
+                }
+                self.drop_scope();
+                Ok(flow)
+            }
+            ASTNode::Match { value, arms, .. } => {
+                // bind the matched arm in a fresh scope, letting control flow leave it
+                let (body, bindings) = self.match_arm(value, arms)?;
+                self.scope_id += 1;
+                for (sym, value) in bindings {
+                    self.declare(&ID::new_sym(sym), Variable::Owned(value))?;
+                }
+                let flow = self.execute_block(body.clone())?;
+                self.drop_scope();
+                Ok(flow)
+            }
+            ASTNode::Throw { value, .. } => {
+                let value = self
+                    .execute_expr(value)
+                    .context("failed to evaluate thrown value")?;
+                Err(self.throw(value))
+            }
+            ASTNode::Try {
+                body,
+                name,
+                handler,
+                finally,
+                ..
+            } => {
+                // errors leaving the body are caught by the handler, if there is one, unless they
+                // can't be caught at all, in which case nothing else runs
+                let mut result = self.execute_scoped(body, None);
+                if let Err(err) = &result
+                    && !ErrorKind::of(err).is_catchable()
+                {
+                    return result;
+                }
+                if let (Err(err), Some(handler)) = (&result, handler) {
+                    let binding = name.map(|sym| (sym, self.catch(err)));
+                    result = self.execute_scoped(handler, binding);
+                }
+
+                // the finally body runs either way, and control flow leaving it replaces the
+                // outcome of the rest
+                if let Some(finally) = finally {
+                    let thrown = self.thrown.take();
+                    if let Some(flow) = self.execute_scoped(finally, None)? {
+                        return Ok(Some(flow));
+                    }
+                    self.thrown = thrown;
+                }
+                result
+            }
+            ASTNode::Block { .. } => self.execute_block(statement.clone()),
+            _ => {
+                // other statements are expressions, whose values are discarded
+                self.execute_expr(statement)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Runs the body of the loop labeled `label` once. Breaks with the flow leaving the loop,
+    /// which is `None` when the loop is broken out of.
+    fn iterate(
+        &mut self,
+        body: &Rc<ASTNode>,
+        label: Option<usize>,
+    ) -> Result<ControlFlow<Option<Flow>>> {
+        Ok(match self.execute_block(body.clone())? {
+            Some(Flow::Continue(target)) if target.is_none() || target == label => {
+                ControlFlow::Continue(())
+            }
+            Some(Flow::Break(target)) if target.is_none() || target == label => {
+                ControlFlow::Break(None)
+            }
+            Some(flow) => ControlFlow::Break(Some(flow)),
+            None => ControlFlow::Continue(()),
+        })
+    }
+
+    /// Executes a block in a new scope, declaring `binding` in it first if there is one. The scope
+    /// is dropped even if the block fails, along with any scopes the failure left behind, so that
+    /// execution can continue once the error is caught.
+    fn execute_scoped(
+        &mut self,
+        body: &Rc<ASTNode>,
+        binding: Option<(usize, Value)>,
+    ) -> Result<Option<Flow>> {
+        let scope_id = self.scope_id;
+        self.scope_id += 1;
+        let result = match binding {
+            Some((sym, value)) => self.declare(&ID::new_sym(sym), Variable::Owned(value)),
+            None => Ok(()),
+        }
+        .and_then(|_| self.execute_block(body.clone()));
+        self.scope_id = scope_id + 1;
+        self.drop_scope();
+        result
+    }
+
+    /// Returns an error for `flow` leaving an expression. Control flow that has nowhere to go
+    /// outside of it fails as it would have without the expression.
+    fn escaped_expression(&self, flow: &Flow) -> anyhow::Error {
+        let target = match flow {
+            Flow::Return(_) => self.frame > 0,
+            Flow::Break(label) | Flow::Continue(label) => self
+                .loops
+                .iter()
+                .any(|(scope, target)| *scope > self.frame && (label.is_none() || target == label)),
+        };
+        match target {
+            true => anyhow!("control flow cannot leave an expression"),
+            false => flow.escaped(),
+        }
+    }
+
+    /// Evaluates the value of a match, and finds the first arm whose pattern matches it. Returns
+    /// the body of the arm, along with the names its pattern binds and their values.
+    fn match_arm<'a>(
+        &mut self,
+        value: &Rc<ASTNode>,
+        arms: &'a [(Rc<Pattern>, Rc<ASTNode>)],
+    ) -> Result<MatchedArm<'a>> {
+        let value = self
+            .execute_expr(value)
+            .context("failed to evaluate matched expression")?;
+        for (pattern, body) in arms {
+            let mut values = vec![];
+            if ops::matches(pattern, &value, &mut values)? {
+                return Ok((body, pattern.bindings().into_iter().zip(values).collect()));
+            }
+        }
+        raise!(Match, "no match arm matches {value:?}")
+    }
+
+    /// Executes the body of a conditional expression, evaluating to its trailing expression, or to
+    /// undefined if it doesn't end with one.
+    fn execute_branch(&mut self, body: &Rc<ASTNode>) -> Result<Value> {
+        let (statements, last) = match body.as_ref() {
+            ASTNode::Block { statements, .. } => match statements.split_last() {
+                Some((last, rest)) if last.is_expression() => (rest, Some(last)),
+                _ => (statements.as_slice(), None),
+            },
+            _ => (std::slice::from_ref(body), None),
+        };
+        for statement in statements {
+            // control flow can't leave an expression
+            if let Some(flow) = self.execute_statement(statement)? {
+                return Err(self.escaped_expression(&flow));
+            }
+        }
+        match last {
+            Some(last) => self.execute_expr(last),
+            None => Ok(Value::Undefined),
+        }
+    }
+
+    /// Evaluates an individual expression. Errors are tagged with the location of the innermost
+    /// node that failed.
+    fn execute_expr(&mut self, statement: &Rc<ASTNode>) -> Result<Value> {
+        let result = self.tick().and_then(|_| self.execute_node(statement));
+        result.at(statement.span())
+    }
+
+    /// Evaluates an individual node. Statements evaluate to undefined. Use `execute_expr` to keep
+    /// location information on errors.
+    fn execute_node(&mut self, statement: &Rc<ASTNode>) -> Result<Value> {
+        let statement = statement.clone();
+        match statement.as_ref() {
+            ASTNode::Literal {
+                token: Token::Identifier(sym),
+                ..
+            } => {
+                // resolve variable and return its value. structures become type values
+                Ok(self.get(&ID::new_sym(*sym))?.into())
             }
-            ASTNode::Literal(_) | ASTNode::Instance { .. } => {
-                // return raw literal without resolving
-                Ok(Some(statement))
+            ASTNode::Literal { token, .. } => Value::try_from(token),
+            ASTNode::List { items, .. } => {
+                // evaluate every item into a fresh list
+                let mut values = Vec::with_capacity(items.len());
+                for item in items {
+                    let value = self
+                        .execute_expr(item)
+                        .context("failed to resolve list value")?;
+                    values.push(Variable::Owned(value).into());
+                }
+                self.allocate(Value::List(values))
             }
-            ASTNode::List(items) => {
-                // deeply-clone list
-                // this avoids mutation of the original AST
-                let cloned_items: Vec<_> = items
-                    .iter()
-                    .map(|item| Rc::new(RefCell::new(item.borrow().clone())))
-                    .collect();
-
-                // resolve all refs before returning
-                let resolved_list = self
-                    .resolve_refs(ASTNode::List(cloned_items))
-                    .context("failed to resolve list items")?;
-
-                Ok(Some(resolved_list))
+            ASTNode::Dict { entries, .. } => {
+                // evaluate every entry into a fresh dictionary, in order
+                let mut dict = Dict::default();
+                for (key, value) in entries {
+                    let key = self
+                        .execute_expr(key)
+                        .context("failed to resolve dictionary key")?
+                        .as_key()?;
+                    let value = self
+                        .execute_expr(value)
+                        .context("failed to resolve dictionary value")?;
+                    dict.insert(key, Variable::Owned(value));
+                }
+                self.allocate(Value::Dict(RefCell::new(dict).into()))
             }
-            ASTNode::Assign { target, value } => {
+            ASTNode::Assign { target, value, .. } => {
                 // resolve target & expression
                 let resolved_target = &self
                     .node_to_id(target.clone())
                     .context("failed to evaluate assignment target")?;
                 let resolved_expr = self
                     .execute_expr(value)
-                    .context("failed to evaluate assignment value")?
-                    .context("assignment value must be defined")?;
+                    .context("failed to evaluate assignment value")?;
 
                 // assign variable
-                self.assign(
-                    resolved_target,
-                    Variable::Owned(ASTNode::inner_to_owned(&resolved_expr)),
-                )?;
-                Ok(None)
+                self.assign(resolved_target, Variable::Owned(resolved_expr))?;
+                Ok(Value::Undefined)
             }
-            ASTNode::Declare { target, value } => {
+            ASTNode::Declare { target, value, .. } => {
                 // resolve target & expression
                 let resolved_target = &self
                     .node_to_id(target.clone())
                     .context("failed to evaluate declaration target")?;
                 let resolved_expr = self
                     .execute_expr(value)
-                    .context("failed to evaluate declaration value")?
-                    .context("declaration value must be defined")?;
+                    .context("failed to evaluate declaration value")?;
 
                 // declare variable
-                self.declare(
-                    resolved_target,
-                    Variable::Owned(ASTNode::inner_to_owned(&resolved_expr)),
-                )?;
-                Ok(None)
+                self.declare(resolved_target, Variable::Owned(resolved_expr))?;
+                Ok(Value::Undefined)
             }
-            ASTNode::Op { lhs, op, rhs } => {
-                use Token::*;
-                macro_rules! opmatch {
-                    (match $op:expr, $lhs:expr, $rhs:expr => $locallhs:pat, $localrhs:pat if $($pat:pat => $res:expr),*) => {
-                        match ($op, $lhs, $rhs) {
-                            $(($pat, ASTNode::Literal($locallhs), ASTNode::Literal($localrhs)) => {
-                                return Ok(Some(Rc::new(ASTNode::Literal($res))))
-                            })*
-                            _ => {},
-                        }
-                    };
-                }
-
+            ASTNode::Op { lhs, op, rhs, .. } => {
                 // evaluate operands
                 let a = self
                     .execute_expr(lhs)
-                    .context("failed to evaluate left operand")?
-                    .context("left operand is undefined")?;
+                    .context("failed to evaluate left operand")?;
                 let b = self
                     .execute_expr(rhs)
-                    .context("failed to evaluate right operand")?
-                    .context("right operand is undefined")?;
-                let (a, b) = (a.as_ref(), b.as_ref());
-
-                // math & numeric equality
-                opmatch!(
-                    match op, a, b => Number(l), Number(r) if
-                    Add => Number(l + r),
-                    Sub => Number(l - r),
-                    Mul => Number(l * r),
-                    Div => Number(l / r),
-                    Floor => Number((l / r).floor()),
-                    Pow => Number(l.powf(*r)),
-                    LogicalG => Bool(l > r),
-                    LogicalGe => Bool(l >= r),
-                    LogicalL => Bool(l < r),
-                    LogicalLe => Bool(l <= r)
-                );
-
-                // bi-directional string concatenation
-                opmatch!(
-                    match op, a, b => Str(l), r if
-                    Add => Str(format!("{l}{r}"))
-                );
-                opmatch!(
-                    match op, a, b => l, Str(r) if
-                    Add => Str(format!("{l}{r}"))
-                );
-
-                // and & or
-                opmatch!(
-                    match op, a, b => Bool(l), Bool(r) if
-                    LogicalAnd => Bool(*l && *r),
-                    LogicalOr => Bool(*l || *r)
-                );
-
-                // equality
-                opmatch!(
-                    match op, a, b => l, r if
-                    LogicalEq => Bool(l == r),
-                    LogicalNeq => Bool(l != r)
-                );
-
-                // list concatenation
-                // TODO: use macro
-                if let (Add, ASTNode::List(l), ASTNode::List(r)) = (op, a, b) {
-                    let mut combined = l.clone();
-                    combined.extend(r.clone());
-                    return Ok(Some(Rc::new(ASTNode::List(combined))));
-                }
-
-                // no match, fail
-                bail!("operator not implemented ({a} {op:#?} {b})")
+                    .context("failed to evaluate right operand")?;
+                self.allocate(ops::binary(op, &a, &b)?)
             }
-            ASTNode::UnaryOp { target, op } => match op {
+            ASTNode::UnaryOp { target, op, .. } => match op {
                 // increment/decrement operations need special handling
                 Token::Increment | Token::Decrement => {
                     // TODO: i'm pretty sure this doesn't work with dot notation or anything
                     // like that. that's a later fix, though
-                    if let ASTNode::Literal(Token::Identifier(sym)) = target.as_ref() {
+                    if let ASTNode::Literal {
+                        token: Token::Identifier(sym),
+                        ..
+                    } = target.as_ref()
+                    {
                         // get variable
                         let id = ID::new_sym(*sym);
-                        if let Variable::Owned(ASTNode::Literal(Token::Number(n))) =
+                        if let Variable::Owned(n @ (Value::Int(_) | Value::Float(_))) =
                             self.get(&id)?
                         {
                             // get new assignment value
-                            let new_value = match op {
-                                Token::Increment => Token::Number(n + 1.0),
-                                Token::Decrement => Token::Number(n - 1.0),
-                                _ => unreachable!(),
+                            let step = match op {
+                                Token::Increment => Token::Add,
+                                _ => Token::Sub,
                             };
-                            self.assign(&id, Variable::Owned(ASTNode::Literal(new_value)))?;
+                            let new_value = ops::binary(&step, &n, &Value::Int(1))?;
+                            self.assign(&id, Variable::Owned(new_value))?;
                         }
                     } else {
                         bail!("invalid increment/decrement target: {target:?}");
                     }
-                    Ok(None)
+                    Ok(Value::Undefined)
                 }
 
                 // other unary operations need the target to be evaluated first
                 _ => {
-                    let Ok(Some(target_result)) = self.execute_expr(target) else {
-                        bail!("failed to evaluate unary operand");
-                    };
-                    match (op, target_result.as_ref()) {
-                        // negative numbers
-                        (Token::Sub, ASTNode::Literal(Token::Number(n))) => {
-                            Ok(Some(Rc::new(ASTNode::Literal(Token::Number(-n)))))
-                        }
-                        // logical not
-                        (Token::LogicalNot, ASTNode::Literal(Token::Bool(b))) => {
-                            Ok(Some(Rc::new(ASTNode::Literal(Token::Bool(!b)))))
-                        }
-                        // bail for others
-                        _ => {
-                            bail!("unsupported unary operation: {op:?} on {target_result:?}");
-                        }
-                    }
+                    let target = self
+                        .execute_expr(target)
+                        .context("failed to evaluate unary operand")?;
+                    ops::unary(op, &target)
                 }
             },
             ASTNode::Function { id, .. } => {
-                self.declare(id, Variable::Function(statement.clone()))?;
-                Ok(None)
-            }
-            ASTNode::FunctionCall { target, arguments } => {
-                // get target variable and check if we need to set instance context
-                let (variable, instance_context) = match target.as_ref() {
-                    ASTNode::Literal(Token::Identifier(sym)) => {
-                        (self.get(&ID::new_sym(*sym))?, None)
+                let function = Value::Function {
+                    node: statement.clone(),
+                    captures: self.capture().into(),
+                };
+
+                // named functions are declared, while anonymous functions are values
+                match id {
+                    Some(id) => {
+                        self.declare(id, Variable::Owned(function))?;
+                        Ok(Value::Undefined)
                     }
-                    ASTNode::Deref { parent, child } => {
+                    None => Ok(function),
+                }
+            }
+            ASTNode::FunctionCall {
+                target, arguments, ..
+            } => {
+                // get target variable, along with the instance it is called on, if any
+                let (variable, receiver) = match target.as_ref() {
+                    ASTNode::Literal {
+                        token: Token::Identifier(sym),
+                        ..
+                    } => (self.get(&ID::new_sym(*sym))?, None),
+                    ASTNode::Deref { parent, child, .. } => {
                         // try to convert to ID for simple derefs (`a.b`)
                         if let Ok(id) = self.node_to_id(target.clone()) {
                             let variable = self.get(&id)?;
 
-                            // check if this is an instance method call
-                            let instance_context = match &**parent {
-                                ASTNode::Literal(Token::Identifier(parent_sym)) => {
-                                    // try to get the parent variable, but don't fail if it doesn't exist
-                                    // this is because we only need to expose contexts for some
-                                    // nodes, others apply to global context
-                                    if let Ok(parent_var) = self.get(&ID::new_sym(*parent_sym)) {
-                                        match (&parent_var, &variable) {
-                                            (
-                                                Variable::Owned(ASTNode::Instance { .. }),
-                                                Variable::Function(_),
-                                            ) => Some(parent_var),
-                                            _ => None,
-                                        }
-                                    } else {
-                                        None
-                                    }
+                            // methods of instances run with the instance as their memory context.
+                            // the parent doesn't have to exist, as it may be a module
+                            let receiver = match self
+                                .node_to_id(parent.clone())
+                                .and_then(|id| self.get(&id))
+                            {
+                                Ok(Variable::Owned(Value::Instance { svt, .. }))
+                                    if matches!(
+                                        variable,
+                                        Variable::Owned(Value::Function { .. })
+                                    ) =>
+                                {
+                                    Some(svt)
                                 }
                                 _ => None,
                             };
 
-                            (variable, instance_context)
+                            (variable, receiver)
                         } else {
                             // for complex derefs (like `parent().child`), evaluate the parent in-place
-                            let parent_value = self
-                                .execute_expr(parent)?
-                                .context("deref parent cannot be undefined")?;
+                            let parent = self.execute_expr(parent)?;
 
                             // get the child identifier
-                            let ASTNode::Literal(Token::Identifier(member_id)) = child.as_ref()
+                            let ASTNode::Literal {
+                                token: Token::Identifier(member_id),
+                                ..
+                            } = child.as_ref()
                             else {
                                 bail!("deref child must be an identifier")
                             };
 
-                            // get the variable from the parent value
-                            let variable = match parent_value.as_ref() {
-                                ASTNode::Instance { svt, .. } => {
-                                    svt.borrow().get_owned(*member_id)?
+                            // get the variable from the parent value, which becomes the receiver
+                            match parent {
+                                Value::Instance { svt, .. } => {
+                                    let variable = svt.borrow().get_owned(*member_id)?;
+                                    (variable, Some(svt))
                                 }
-                                _ => bail!("cannot dereference member of {parent_value:#?}"),
-                            };
-
-                            // set instance context to the parent value (the instance we're calling the method on)
-                            let instance_context = match parent_value.as_ref() {
-                                ASTNode::Instance { .. } => {
-                                    Some(Variable::Owned(ASTNode::inner_to_owned(&parent_value)))
-                                }
-                                _ => None,
-                            };
-
-                            (variable, instance_context)
+                                _ => bail!("cannot dereference member of {parent:?}"),
+                            }
                         }
                     }
-                    other => bail!("cannot call {other:#?}"),
+                    // other targets are called by value, like `list[0]()`
+                    _ => (Variable::Owned(self.execute_expr(target)?), None),
+                };
+
+                // external functions that change their first argument get the variable itself, if
+                // it is one. its path is resolved before the other arguments are evaluated, but
+                // the variable is only changed once they are
+                let (place, arguments) = match (&variable, arguments.split_first()) {
+                    (Variable::Owned(Value::NativeMut(_)), Some((first, rest)))
+                        if first.is_place() =>
+                    {
+                        (Some(self.node_to_id(first.clone())?), rest)
+                    }
+                    _ => (None, &arguments[..]),
                 };
 
                 // Resolve values before passing them as arguments. We do this so that the
                 // arguments are already in their most basic form-- math expressions become single
                 // numbers, variables become owned values, etc.
-                let mut resolved_args = Vec::with_capacity(arguments.len());
+                let mut args = Vec::with_capacity(arguments.len());
                 for arg in arguments {
-                    resolved_args.push(
+                    args.push(
                         self.execute_expr(arg)
-                            .context("failed to evaluate argument in extern")?
-                            .unwrap_or(lit!(Token::Undefined))
-                            .clone(),
+                            .context("failed to evaluate argument")?,
                     );
                 }
 
+                // calls are traced with the path of the module they were made through, if any
+                let module = match (target.as_ref(), &receiver) {
+                    (ASTNode::Deref { parent, .. }, None) => parent.path(),
+                    _ => None,
+                };
+                let site = statement.span().clone();
+
                 match variable {
                     // this branch should trigger on external functions
-                    Variable::Extern(closure) => {
-                        // call closure with i/o handles
-                        closure(&mut self.output, &mut self.input, &resolved_args)
+                    Variable::Owned(Value::Native(closure)) => {
+                        // call closure with this interpreter as its host
+                        let result = closure(self, &args)?;
+                        self.allocate(result)
                     }
-
-                    // this branch should trigger on raw, local functions
-                    Variable::Function(_) | Variable::Owned(_) => {
-                        // get the function node
-                        let function = match variable {
-                            Variable::Function(function) => function,
-                            Variable::Owned(var) => {
-                                let id = self.node_to_id(var.into())?;
-                                let Variable::Function(function) = self.get(&id)? else {
-                                    bail!("cannot execute variable");
+                    Variable::Owned(Value::NativeMut(closure)) => {
+                        self.charge_args(&args)?;
+                        let result = match place {
+                            Some(id) => {
+                                let cell = self.get_ref(&id)?;
+                                let mut cell = cell.borrow_mut();
+                                let Variable::Owned(value) = &mut *cell else {
+                                    raise!(Type, "cannot change {cell:?}");
                                 };
-                                function
+                                closure(value, &args)?
                             }
-                            _ => unreachable!(),
+                            None => execute_function::mutate_copy(&*closure, &args)?,
                         };
+                        self.allocate(result)
+                    }
 
-                        // execute it in context
-                        if let Some(Variable::Owned(ASTNode::Instance { svt, .. })) =
-                            instance_context
-                        {
+                    // this branch should trigger on local functions
+                    Variable::Owned(Value::Function { node, captures }) => {
+                        let frame = Frame::new(&node, module, site);
+                        self.traced(frame, |interpreter| match receiver {
                             // if we found a valid instance context, use it as memory space
-                            self.with_context(Some(svt), |interpreter| {
-                                interpreter.execute_function(&resolved_args, function)
-                            })
-                        } else {
+                            Some(svt) => interpreter.with_context(Some(svt), |interpreter| {
+                                interpreter.execute_function(&args, node, &captures)
+                            }),
                             // otherwise, use previously set memory space
-                            self.execute_function(&resolved_args, function)
-                        }
+                            None => interpreter.execute_function(&args, node, &captures),
+                        })
                     }
 
                     // this branch should trigger when constructors are called
-                    Variable::Type(ref structure) => {
-                        // get template as refcell
-                        let svt = Rc::new(RefCell::new(
-                            structure
-                                .template()
-                                .context("failed to create structure template")?,
-                        ));
-
-                        // if there is a defined constructor, run it
-                        if let Some(v) = structure.constructor() {
-                            self.with_context(Some(svt.clone()), |interpreter| {
-                                interpreter.execute_function(&resolved_args, v)
+                    Variable::Type(structure) | Variable::Owned(Value::Type(structure)) => self
+                        .instantiate(structure, |interpreter, constructor| {
+                            let frame = Frame::new(&constructor, module, site);
+                            interpreter.traced(frame, |interpreter| {
+                                interpreter.execute_function(&args, constructor, &Rc::default())
                             })?;
-                        }
+                            Ok(())
+                        }),
 
-                        Ok(Some(
-                            ASTNode::Instance {
-                                kind: variable.into(),
-                                svt,
-                            }
-                            .into(),
-                        ))
-                    }
+                    Variable::Owned(other) => raise!(Type, "cannot call {other:?}"),
                 }
             }
             ASTNode::Struct { id, .. } => {
-                self.declare(id, Variable::Type(statement.clone()))
-                    .context("failed to declare type for structure")?;
-                Ok(None)
-            }
-            ASTNode::Conditional {
-                condition,
-                if_body,
-                else_body,
-            } => {
-                // evaluate condition
-                let condition = self
-                    .execute_expr(condition)?
-                    .context("failed to evaluate condition")?;
-
-                // increase scope level
-                self.scope_id += 1;
-
-                // execute if-body if statement is true. otherwise, execute else body
-                if let Some(result) = self.execute(match condition.is_truthy() {
-                    true => if_body.clone(),
-                    false => else_body.clone(),
-                })? {
-                    self.drop_scope();
-                    return Ok(Some(result));
-                }
-
-                // after finishing, drop the scope
-                self.drop_scope();
-                Ok(None)
-            }
-            ASTNode::Loop { condition, body } => {
-                // increase scope level and execute body
-                self.scope_id += 1;
-                while let Some(condition) = self.execute_expr(condition)? {
-                    // if the condition is not true, break
-                    if !condition.is_truthy() {
-                        break;
-                    }
-
-                    // get cycle result. if return reached, stop loop
-                    let result = self.execute(body.clone())?;
-                    if let Some(node) = result {
-                        self.drop_scope();
-                        if ASTNode::Break == *node {
-                            return Ok(None);
-                        } else {
-                            return Ok(Some(node));
-                        }
-                    }
-                    // after each execution of the loop, clear values at this scope
-                    self.drop_here();
+                // evaluate default fields in order of declaration
+                let mut fields = vec![];
+                for field in statement.fields() {
+                    fields.push(
+                        self.execute_expr(&field)
+                            .context("failed to evaluate default field")?,
+                    );
                 }
 
-                // loop finished, drop locals and continue
-                self.drop_scope();
-                Ok(None)
+                let structure = Structure::new(&statement, fields)?;
+                self.declare(id, Variable::Type(structure.into()))
+                    .context("failed to declare type for structure")?;
+                Ok(Value::Undefined)
             }
-            ASTNode::Break => Ok(Some(statement)),
-            ASTNode::Index { target, index } => {
-                // get index as a usize
-                let usize_idx = self
+            ASTNode::Index { target, index, .. } => {
+                // get index as a key
+                let key = self
                     .execute_expr(index)
                     .context(format!("failed to evaluate index value ({index})"))?
-                    .context("index cannot be undefined")?
-                    .as_index()?;
+                    .as_key()?;
 
                 // get the target of this index
                 let target = self
                     .execute_expr(target)
-                    .context("failed to evaluate index target")?
-                    .unwrap();
+                    .context("failed to evaluate index target")?;
 
                 // find item if applicable, bail otherwise
-                match target.as_ref() {
-                    ASTNode::List(items) => {
-                        if let Variable::Owned(value) = &*items
-                            .get(usize_idx)
-                            .context("list item does not exist")?
-                            .borrow()
-                        {
-                            return Ok(Some(value.clone().into()));
-                        }
-                        bail!("expected list item to be an owned value");
-                    }
-                    ASTNode::Literal(Token::Str(string)) => {
-                        // get the char at the provided index, bail if it is not found
-                        let ch = string.chars().nth(usize_idx).context(format!(
-                            "no character exists at {usize_idx} in string '{string}'"
-                        ))?;
-
-                        // return the cloned character
-                        Ok(Some(lit!(Token::Char(ch))))
-                    }
-                    _ => {
-                        bail!("expected list as index target");
-                    }
-                }
+                ops::index(&target, &key)
             }
-            ASTNode::Deref { parent, child } => {
+            ASTNode::Deref { parent, child, .. } => {
                 // NOTE: we should really just figure out how to `self.get` values with IDs that
                 // represent a function call, but that might get a bit messy
 
@@ -539,42 +867,28 @@ impl<Out: Write, In: Read> Interpreter<Out, In> {
                     self.get(&deref_id)?
                 } else {
                     // for complex derefs (like `parent().child`), evaluate parts
-                    let parent = self
-                        .execute_expr(parent)?
-                        .context("deref parent cannot be undefined")?;
+                    let parent = self.execute_expr(parent)?;
 
                     // deref child & pull value from svt
-                    let ASTNode::Literal(Token::Identifier(member_id)) = child.as_ref() else {
+                    let ASTNode::Literal {
+                        token: Token::Identifier(member_id),
+                        ..
+                    } = child.as_ref()
+                    else {
                         bail!("deref child must be an identifier")
                     };
-                    match parent.as_ref() {
-                        ASTNode::Instance { svt, .. } => svt.borrow().get_owned(*member_id)?,
-                        _ => bail!("cannot dereference member of {parent:#?}"),
+                    match parent {
+                        Value::Instance { svt, .. } => svt.borrow().get_owned(*member_id)?,
+                        _ => bail!("cannot dereference member of {parent:?}"),
                     }
                 };
 
-                // convert variable back to AST node
-                match variable {
-                    Variable::Owned(node) => Ok(Some(Rc::new(node))),
-                    Variable::Function(func) => Ok(Some(func)),
-                    _ => bail!(format!("cannot convert {variable:#?} to valid node")),
-                }
+                // get value of variable
+                Ok(variable.into())
             }
-            ASTNode::Return(expr) => {
-                // resolve expression
-                let expr = self
-                    .execute_expr(expr)
-                    .context("failed to evaluate return expression")?
-                    .context("expected return value")?;
-
-                // if there are references, flatten them
-                let expr = self
-                    .resolve_refs(ASTNode::inner_to_owned(&expr))
-                    .context("could not flatten references")?;
-
-                Ok(Some(expr))
-            }
-            ASTNode::Module { path, alias, body } => {
+            ASTNode::Module {
+                path, alias, body, ..
+            } => {
                 let ctx = match alias {
                     // if alias exists, create named module and execute in its context
                     Some(sym) => {
@@ -588,16 +902,59 @@ impl<Out: Write, In: Read> Interpreter<Out, In> {
                 };
 
                 self.with_context(ctx, |interpreter| {
-                    interpreter.execute(body.clone()).context(format!(
+                    interpreter.execute_block(body.clone()).context(format!(
                         "failed to evaluate module '{}' ({:?})",
-                        (*alias).unwrap_or(intern!("anonymous")),
+                        alias.map_or("anonymous".to_string(), |sym| resolve!(sym)),
                         path.clone().unwrap_or(PathBuf::default()),
                     ))
                 })?;
-                Ok(None)
+                Ok(Value::Undefined)
             }
-            _ => {
-                todo!()
+            ASTNode::Conditional {
+                condition,
+                if_body,
+                else_body,
+                ..
+            } => {
+                // evaluate to the value of the branch taken
+                let condition = self
+                    .execute_expr(condition)
+                    .context("failed to evaluate condition")?;
+                self.scope_id += 1;
+                let value = self.execute_branch(match condition.is_truthy() {
+                    true => if_body,
+                    false => else_body,
+                });
+                self.drop_scope();
+                value
+            }
+            ASTNode::Match { value, arms, .. } => {
+                // evaluate to the value of the matched arm
+                let (body, bindings) = self.match_arm(value, arms)?;
+                self.scope_id += 1;
+                let value = bindings
+                    .into_iter()
+                    .try_for_each(|(sym, value)| {
+                        self.declare(&ID::new_sym(sym), Variable::Owned(value))
+                    })
+                    .and_then(|_| self.execute_branch(body));
+                self.drop_scope();
+                value
+            }
+            ASTNode::Range { .. } => bail!("ranges can only be looped over"),
+            ASTNode::Return { .. }
+            | ASTNode::Break { .. }
+            | ASTNode::Continue { .. }
+            | ASTNode::Loop { .. }
+            | ASTNode::For { .. }
+            | ASTNode::Throw { .. }
+            | ASTNode::Try { .. }
+            | ASTNode::Block { .. } => {
+                // control flow can't leave an expression
+                if let Some(flow) = self.execute_statement(&statement)? {
+                    return Err(self.escaped_expression(&flow));
+                }
+                Ok(Value::Undefined)
             }
         }
     }
@@ -0,0 +1,323 @@
+//! Operators and indexing on runtime values. Both backends evaluate operations through these
+//! functions, so they always agree on semantics.
+
+use super::*;
+
+/// Applies a binary operator to two values.
+pub(crate) fn binary(op: &Token, a: &Value, b: &Value) -> Result<Value> {
+    use Token::*;
+    macro_rules! opmatch {
+        (match $op:expr, $lhs:expr, $rhs:expr => $locallhs:pat, $localrhs:pat if $($pat:pat => $res:expr),*) => {
+            match ($op, $lhs, $rhs) {
+                $(($pat, $locallhs, $localrhs) => {
+                    return Ok($res)
+                })*
+                _ => {},
+            }
+        };
+    }
+
+    // integer math, which never wraps
+    if let (Value::Int(l), Value::Int(r)) = (a, b)
+        && let Some(result) = integer(op, *l, *r)?
+    {
+        return Ok(result);
+    }
+
+    // float math, where integers are promoted to floats
+    if let (Some(l), Some(r)) = (a.as_float(), b.as_float()) {
+        opmatch!(
+            match op, l, r => l, r if
+            Add => Value::Float(l + r),
+            Sub => Value::Float(l - r),
+            Mul => Value::Float(l * r),
+            Div => Value::Float(l / r),
+            Floor => Value::Float((l / r).floor()),
+            Pow => Value::Float(l.powf(r)),
+            LogicalG => Value::Bool(l > r),
+            LogicalGe => Value::Bool(l >= r),
+            LogicalL => Value::Bool(l < r),
+            LogicalLe => Value::Bool(l <= r)
+        );
+    }
+    if matches!(op, Mod | BitAnd | BitOr | BitXor | ShiftL | ShiftR) {
+        raise!(
+            Type,
+            "{op:?} requires integer operands, found {a:?} and {b:?}"
+        );
+    }
+
+    // bi-directional string concatenation
+    opmatch!(
+        match op, a, b => Value::Str(l), r if
+        Add => Value::Str(format!("{l}{r}"))
+    );
+    opmatch!(
+        match op, a, b => l, Value::Str(r) if
+        Add => Value::Str(format!("{l}{r}"))
+    );
+
+    // and & or
+    opmatch!(
+        match op, a, b => Value::Bool(l), Value::Bool(r) if
+        LogicalAnd => Value::Bool(*l && *r),
+        LogicalOr => Value::Bool(*l || *r)
+    );
+
+    // equality
+    opmatch!(
+        match op, a, b => l, r if
+        LogicalEq => Value::Bool(equals(l, r)),
+        LogicalNeq => Value::Bool(!equals(l, r))
+    );
+
+    // list concatenation
+    if let (Add, Value::List(l), Value::List(r)) = (op, a, b) {
+        let mut combined = l.clone();
+        combined.extend(r.clone());
+        return Ok(Value::List(combined));
+    }
+
+    // no match, fail
+    raise!(Type, "operator not implemented ({a:?} {op:#?} {b:?})")
+}
+
+/// Applies a binary operator to two integers. Returns `None` if the operator does not produce an
+/// integer result, such as true division, and `Err` if the result overflows.
+fn integer(op: &Token, l: i64, r: i64) -> Result<Option<Value>> {
+    use Token::*;
+    let result = match op {
+        Add => l.checked_add(r),
+        Sub => l.checked_sub(r),
+        Mul => l.checked_mul(r),
+        Floor | Mod => {
+            if r == 0 {
+                raise!(Arithmetic, "division by zero");
+            }
+            // round towards negative infinity, so that the remainder takes the sign of `r`
+            // (the remainder only fails to compute for `i64::MIN % -1`, which is zero)
+            let remainder = l.checked_rem(r).unwrap_or(0);
+            let adjust = remainder != 0 && (remainder < 0) != (r < 0);
+            match (op, adjust) {
+                (Floor, true) => l.checked_div(r).and_then(|q| q.checked_sub(1)),
+                (Floor, false) => l.checked_div(r),
+                (_, true) => remainder.checked_add(r),
+                (_, false) => Some(remainder),
+            }
+        }
+        // negative exponents produce fractions, so they are left to float math
+        Pow if r >= 0 => u32::try_from(r).ok().and_then(|r| l.checked_pow(r)),
+        BitAnd => Some(l & r),
+        BitOr => Some(l | r),
+        BitXor => Some(l ^ r),
+        // shifting left errors if any bits are shifted out
+        ShiftL => u32::try_from(r)
+            .ok()
+            .and_then(|r| l.checked_shl(r))
+            .filter(|shifted| shifted >> r == l),
+        ShiftR => u32::try_from(r).ok().and_then(|r| l.checked_shr(r)),
+        LogicalG => return Ok(Some(Value::Bool(l > r))),
+        LogicalGe => return Ok(Some(Value::Bool(l >= r))),
+        LogicalL => return Ok(Some(Value::Bool(l < r))),
+        LogicalLe => return Ok(Some(Value::Bool(l <= r))),
+        _ => return Ok(None),
+    };
+    match result {
+        Some(n) => Ok(Some(Value::Int(n))),
+        None => raise!(Arithmetic, "integer overflow ({l} {op:?} {r})"),
+    }
+}
+
+/// Compares two values with Lily's `==` semantics. Unlike `PartialEq`, integers and floats are
+/// equal if they hold the same number, including inside of lists and dictionaries.
+pub(crate) fn equals(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Int(l), Value::Float(r)) | (Value::Float(r), Value::Int(l)) => *l as f64 == *r,
+        (Value::Dict(l), Value::Dict(r)) => l.borrow().eq_by(&r.borrow(), |l, r| match (l, r) {
+            (Variable::Owned(l), Variable::Owned(r)) => equals(l, r),
+            (l, r) => l == r,
+        }),
+        (Value::List(l), Value::List(r)) => {
+            l.len() == r.len()
+                && l.iter()
+                    .zip(r)
+                    .all(|(l, r)| match (&*l.borrow(), &*r.borrow()) {
+                        (Variable::Owned(l), Variable::Owned(r)) => equals(l, r),
+                        (l, r) => l == r,
+                    })
+        }
+        _ => a == b,
+    }
+}
+
+/// Applies a unary operator, other than increments and decrements, to a value.
+pub(crate) fn unary(op: &Token, target: &Value) -> Result<Value> {
+    match (op, target) {
+        // negative numbers
+        (Token::Sub, Value::Int(n)) => match n.checked_neg() {
+            Some(n) => Ok(Value::Int(n)),
+            None => raise!(Arithmetic, "integer overflow (negation)"),
+        },
+        (Token::Sub, Value::Float(n)) => Ok(Value::Float(-n)),
+        // logical not
+        (Token::LogicalNot, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        // bail for others
+        _ => raise!(Type, "unsupported unary operation: {op:?} on {target:?}"),
+    }
+}
+
+/// Gets the item at `key` in a list or dictionary, or the character at `key` in a string. Keys
+/// are created with `Value::as_key`.
+pub(crate) fn index(target: &Value, key: &Key) -> Result<Value> {
+    match target {
+        Value::Dict(dict) => match dict.borrow().get(key) {
+            Some(variable) => match &*variable.borrow() {
+                Variable::Owned(value) => Ok(value.clone()),
+                _ => bail!("expected dictionary value to be an owned value"),
+            },
+            None => raise!(Key, "key {:?} does not exist", Value::from(key)),
+        },
+        Value::List(items) => {
+            let index = key.as_index()?;
+            let Some(item) = items.get(index) else {
+                raise!(Index, "index out of bounds");
+            };
+            if let Variable::Owned(value) = &*item.borrow() {
+                return Ok(value.clone());
+            }
+            bail!("expected list item to be an owned value");
+        }
+        Value::Str(string) => {
+            let index = key.as_index()?;
+
+            // get the char at the provided index, bail if it is not found
+            match string.chars().nth(index) {
+                Some(ch) => Ok(Value::Char(ch)),
+                None => raise!(Index, "no character exists at {index} in string '{string}'"),
+            }
+        }
+        _ => raise!(Type, "expected list, string or dictionary as index target"),
+    }
+}
+
+/// Gets the entries that a for loop visits in a list, string or dictionary, in order. Each entry
+/// pairs an item with its index, or a dictionary value with its key. Loops that only bind an item
+/// visit the keys of dictionaries, so unless `pairs` is set, dictionary entries hold their key in
+/// place of their value.
+pub(crate) fn entries(target: &Value, pairs: bool) -> Result<Vec<(Value, Value)>> {
+    let owned = |variable: &Rc<RefCell<Variable>>| match &*variable.borrow() {
+        Variable::Owned(value) => Ok(value.clone()),
+        _ => bail!("expected loop item to be an owned value"),
+    };
+    match target {
+        Value::List(items) => items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| Ok((Value::Int(i as i64), owned(item)?)))
+            .collect(),
+        Value::Str(string) => Ok(string
+            .chars()
+            .enumerate()
+            .map(|(i, ch)| (Value::Int(i as i64), Value::Char(ch)))
+            .collect()),
+        Value::Dict(dict) => dict
+            .borrow()
+            .iter()
+            .map(|(key, value)| match pairs {
+                true => Ok((key, owned(value)?)),
+                false => Ok((key.clone(), key)),
+            })
+            .collect(),
+        _ => raise!(
+            Type,
+            "expected list, string or dictionary to loop over, found {target:?}"
+        ),
+    }
+}
+
+/// Gets the bounds of a range that a for loop counts through.
+pub(crate) fn bounds(start: &Value, end: &Value) -> Result<(i64, i64)> {
+    match (start, end) {
+        (Value::Int(start), Value::Int(end)) => Ok((*start, *end)),
+        _ => raise!(
+            Type,
+            "expected integers as the bounds of a range, found {start:?} & {end:?}"
+        ),
+    }
+}
+
+/// Tests a value against the pattern of a match arm. If it matches, the values of the names that
+/// the pattern binds are pushed to `bindings`, in the order of `Pattern::bindings`.
+pub(crate) fn matches(pattern: &Pattern, value: &Value, bindings: &mut Vec<Value>) -> Result<bool> {
+    match pattern {
+        Pattern::Wildcard => Ok(true),
+        Pattern::Bind(_) => {
+            bindings.push(value.clone());
+            Ok(true)
+        }
+        Pattern::Literal(token) => Ok(equals(&Value::try_from(token)?, value)),
+        Pattern::Range(start, end) => {
+            let (start, end) = (Value::try_from(start)?, Value::try_from(end)?);
+            Ok(match (value, &start, &end) {
+                (Value::Char(c), Value::Char(start), Value::Char(end)) => start <= c && c < end,
+                (Value::Int(n), Value::Int(start), Value::Int(end)) => start <= n && n < end,
+                _ => match (number(value), number(&start), number(&end)) {
+                    (Some(n), Some(start), Some(end)) => start <= n && n < end,
+                    _ => false,
+                },
+            })
+        }
+        Pattern::List(items, rest) => {
+            let Value::List(list) = value else {
+                return Ok(false);
+            };
+            if list.len() < items.len() || rest.is_none() && list.len() != items.len() {
+                return Ok(false);
+            }
+            for (pattern, item) in items.iter().zip(list) {
+                let Variable::Owned(item) = &*item.borrow() else {
+                    bail!("expected list item to be an owned value");
+                };
+                if !matches(pattern, item, bindings)? {
+                    return Ok(false);
+                }
+            }
+            match rest {
+                Some(rest) => {
+                    let rest_items = list[items.len()..]
+                        .iter()
+                        .map(|item| item.borrow().clone().into())
+                        .collect();
+                    matches(rest, &Value::List(rest_items), bindings)
+                }
+                None => Ok(true),
+            }
+        }
+        Pattern::Struct(name, fields) => {
+            let Value::Instance { kind, svt } = value else {
+                return Ok(false);
+            };
+            if kind.name != *name {
+                return Ok(false);
+            }
+            for (field, pattern) in fields {
+                let Ok(Variable::Owned(member)) = svt.borrow().get_owned(*field) else {
+                    return Ok(false);
+                };
+                if !matches(pattern, &member, bindings)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+    }
+}
+
+/// Gets a number as a float, for comparisons between integers and floats.
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(n) => Some(*n as f64),
+        Value::Float(n) => Some(*n),
+        _ => None,
+    }
+}
@@ -5,14 +5,43 @@ test!(assert_fails => panic);
 test!(assert_passes => "");
 
 test!(chars => (
-    letters == node!([
-        lit!('a'),
-        lit!('b'),
-        lit!('c'),
-        lit!('1'),
-        lit!('2'),
-        lit!('3')
-    ])
+    letters == list![
+        'a',
+        'b',
+        'c',
+        '1',
+        '2',
+        '3'
+    ]
+));
+
+test!(dicts => (
+    keys_of == list!["a", "b", 3],
+    values_of == list![1, 2, "c"],
+    has_a := true,
+    has_z := false,
+    removed := 1,
+    missing == Value::Undefined,
+    after == list!["b", 3],
+    size := 2,
+    reinserted == list!["b", 3, "a"]
+));
+
+test!(input <= "ann\nfirst\r\nsecond\n\nfourth" => (
+    name := "ann",
+    first := "first",
+    rest == list!["second", "", "fourth"],
+    line_at_end == Value::Undefined,
+    lines_at_end == Value::Undefined,
+    all_at_end == Value::Undefined,
+    input_at_end == Value::Undefined
+));
+
+test!(input_prompt <= "ann\n" => "name? hello ann\n");
+
+test!(read_all <= "\nsome\ntext\n" => (
+    blank := "",
+    text := "some\ntext\n"
 ));
 
 test!(len => (
@@ -20,19 +49,162 @@ test!(len => (
     empty_string := 0,
     string := 11,
     list := 5,
-    unicode := 4
+    unicode := 1
 ));
 
 test!(print => "str\nc\n1\ntrue\n\n");
 
+test!(print_dicts => "{\"a\": 1, 2: ['c', \"d\"]}\n{}\n{\"nested\": {\"x\": 1.5}}\n");
+
+test!(print_values => "[1, \"a\", 'c']\n[[true], []]\n");
+
+test!(strings => (
+    words == list!["a", "b", "", "c"],
+    letters == list!["h", "é", "l", "l", "o"],
+    joined := "a-1-c",
+    rejoined := "1+2+3",
+    trimmed := "padded",
+    starts := true,
+    ends := true,
+    contained := true,
+    found := 6,
+    not_found == Value::Undefined,
+    replaced := "a+b+c",
+    upper_case := "STRASSE",
+    lower_case := "àbc",
+    repeated := "ababab",
+    sliced := "lily",
+    clamped := "bc",
+    empty := "",
+    code := 97,
+    emoji_code := 0x1F525,
+    from_code := 'é',
+    unicode_len := 7
+));
+
+test!(strings_invalid_code => panic);
+
+test!(strings_repeat_overflow => panic);
+
+test!(lists => (
+    last := 40,
+    removed := 0,
+    pushed == list![-1, 10, 20, 30, 99],
+    empty_pop == Value::Undefined,
+    reversed == list![3, 2, 1],
+    cleared == list![],
+    at := 1,
+    not_at == Value::Undefined,
+    has_item := true,
+    lacks_item := false,
+    middle == list![2, 3],
+    clamped == list![3],
+    nested == list![list![1], list![2, 3]],
+    in_dict == list!["a"],
+    in_struct == list![1, 2],
+    filled == list![0, 1, 2],
+    filled_globals == list![0, 1, 2],
+    ordered == list![1, 2, 3],
+    copy_pop := 3
+));
+
+test!(lists_out_of_bounds => panic);
+
+test!(types => (
+    int_type := "number",
+    float_type := "number",
+    string_type := "string",
+    char_type := "char",
+    bool_type := "bool",
+    list_type := "list",
+    dict_type := "dict",
+    function_type := "function",
+    instance_type := "Point",
+    structure_type := "type",
+    is_point := true,
+    is_line := false,
+    number_is_point := false,
+    also_point := true,
+    parsed_int := 42,
+    parsed_float := 2.5,
+    not_number == Value::Undefined,
+    from_bool := 1,
+    from_number := 7,
+    as_string := "1.0",
+    list_string := "[1, \"a\"]",
+    truthy := true,
+    falsy := false,
+    round_trip := -10
+));
+
+test!(types_invalid_structure => panic);
+
 test!(sort => (
-    sorted_numbers == node!([lit!(1), lit!(2), lit!(3), lit!(4), lit!(5)]),
-    sorted_words == node!([lit!("apple"), lit!("banana"), lit!("mango")]),
-    empty == node!([]),
-    single == node!([lit!(42)]),
-    reverse_sorted == node!([lit!(1), lit!(2), lit!(3), lit!(4), lit!(5)]),
-    duplicates == node!([lit!(1), lit!(1), lit!(2), lit!(2), lit!(3), lit!(3)]),
-    negatives == node!([lit!(-3), lit!(-2), lit!(-1), lit!(0), lit!(1)])
+    sorted_numbers == list![1, 2, 3, 4, 5],
+    sorted_words == list!["apple", "banana", "mango"],
+    empty == list![],
+    single == list![42],
+    reverse_sorted == list![1, 2, 3, 4, 5],
+    duplicates == list![1, 1, 2, 2, 3, 3],
+    negatives == list![-3, -2, -1, 0, 1],
+    numbers_first == list![-8, -2.5, -1, 0.5, 1, 2.25, 3.5, 4, 5.5, 6, 7, 8, 9, 11, 12, 13, 20],
+    nans_last := true
 ));
 
 test!(sort_mixed_types => panic);
+
+test!(type_errors => (
+    keys_kind := "type",
+    values_kind := "type",
+    has_kind := "type",
+    chars_kind := "type",
+    range_kind := "type",
+    map_kind := "type"
+));
+
+test!(map => (
+    squares == list![1, 4, 9],
+    labels == list!["a!", "b!"],
+    empty == list![],
+    lengths == list![3, 2]
+));
+
+test!(filter => (
+    evens == list![2, 4, 6],
+    none == list![],
+    truthy == list![1, "a"]
+));
+
+test!(reduce => (
+    sum := 10,
+    joined := "abc",
+    initial := 42
+));
+
+test!(any_all => (
+    some_positive := true,
+    none_positive := false,
+    any_empty := false,
+    all_positive := true,
+    not_all_positive := false,
+    all_empty := true
+));
+
+test!(find => (
+    found := 9,
+    missing == Value::Undefined
+));
+
+test!(sort_by => (
+    by_length == list!["a", "bb", "ccc"],
+    descending == list![3, 2, 1],
+    stable == list!["b1", "a1", "b2", "a2"],
+    nan_keys == list![1, 2, 0]
+));
+
+test!(callback_closures => (
+    scaled == list![10, 20, 30],
+    total := 6
+));
+
+test!(callback_errors => panic);
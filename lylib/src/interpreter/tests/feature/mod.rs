@@ -10,14 +10,14 @@ test!(global_variables => (
 test!(math => (
     complex_a := 1,
     complex_b := 2.5,
-    complex_c := 6,
+    complex_c := 6.0,
     floor_basic := 3,
     floor_exact := 3,
     floor_negative := -4,
     power_basic := 8,
     power_zero := 1,
     power_one := 5,
-    power_fraction := 2,
+    power_fraction := 2.0,
     zero_addition := 5,
     zero_subtraction := 10,
     zero_multiplication := 0,
@@ -25,25 +25,51 @@ test!(math => (
     negative_addition := -2,
     negative_subtraction := -6,
     negative_multiplication := -12,
-    negative_division := -4,
+    negative_division := -4.0,
     large_number := 1000000,
-    decimal_precision := 0.3,
+    decimal_precision := 0.1 + 0.2,
     mixed_operations := 9.5,
     nested_power := 16,
-    floor_with_decimals := 3,
+    floor_with_decimals := 3.0,
     power_negative_base := 4,
     multiple_negatives := 6,
-    large_overflow := 1e36
+    large_float := 1e36
 ));
 
+test!(math_integers => (
+    int_add := 3,
+    mixed_add := 3.5,
+    true_division := 3.5,
+    exact_division := 2.0,
+    floor_division := 3,
+    negative_power := 0.25,
+    mod_basic := 1,
+    mod_negative := 2,
+    mod_negative_divisor := -2,
+    bit_and := 8,
+    bit_or := 14,
+    bit_xor := 6,
+    shift_left := 40,
+    shift_right := 2,
+    precedence := 7,
+    large_int := 9007199254740993,
+    int_float_eq := true,
+    list_eq := true,
+    max_int := 9223372036854775807
+));
+
+test!(math_overflow => panic);
+
+test!(math_integer_only => panic);
+
 test!(operators => (
     n_eq := true,
     n_neq := true,
     n_add := 2,
     n_sub := 0,
     n_mul := 8,
-    n_div := 8,
-    n_neg := -8,
+    n_div := 8.0,
+    n_neg := -8.0,
     n_inc := 1,
     n_dec := -1,
     b_eq := true,
@@ -73,6 +99,60 @@ test!(ifelse => (
     a := 0
 ));
 
+test!(ifelse_chains => (
+    a := "a",
+    b := "b",
+    c := "c",
+    f := "f",
+    touched := false
+));
+
+test!(conditional_expressions => (
+    parity := "odd",
+    sign := 1,
+    missing == Value::Undefined,
+    nested == list![2, 3],
+    called := 3,
+    looped := 3
+));
+
+test!(conditional_expressions_flow => panic);
+
+test!(match_patterns => (
+    zero := "zero",
+    negative := "negative",
+    small := "small",
+    lowercase := "lowercase",
+    yes := "yes",
+    other := "other",
+    empty := "empty",
+    only := 7,
+    shape := 5,
+    on_axis := 4,
+    area := 12,
+    x := 1,
+    block_arm := 22,
+    kept := 7,
+    even := 8
+));
+
+test!(match_no_arm => panic);
+
+test!(exceptions => (
+    index_kind := "index",
+    name_kind := "name",
+    type_kind := "type",
+    thrown := 42,
+    from_callback := 2,
+    rethrown := "key",
+    order := "acde",
+    passed := "inner error",
+    returned := "returned",
+    visited := 42
+));
+
+test!(exceptions_uncaught => panic);
+
 test!(comments => (
     x := 42,
     y := 24,
@@ -97,10 +177,36 @@ test!(functions => (
     c := true
 ));
 
+test!(functions_nested_recursion => (
+    sum := 10,
+    counted == list![3, 2, 1]
+));
+
+test!(functions_caller_locals => panic);
+
 test!(functions_by_ref => (
     res := 2
 ));
 
+test!(functions_as_values => (
+    res := 3,
+    first := 2,
+    second := 3
+));
+
+test!(closures => (
+    count := 3,
+    fresh := 1,
+    added := 7,
+    composed := 6,
+    from_list := 12,
+    from_field := 11,
+    nested := 6,
+    by_value := 10,
+    live := 5,
+    recursive := 120
+));
+
 test!(functions_truthy => (
     is_truthy := true
 ));
@@ -117,6 +223,41 @@ test!(loops_nested => (
     x := 25
 ));
 
+test!(loops_for => (
+    sum := 10,
+    letters == list!['h', 'é', 'y'],
+    count := 3,
+    dict_keys := "ab",
+    weighted := 80,
+    dict_values := 3,
+    total := 10,
+    evens := 5,
+    countdown := 321,
+    counted := 1234,
+    offsets := 35,
+    empty := 0,
+    last := 4,
+    stopped := 2,
+    found := 5,
+    fresh := 6,
+    captured := 21
+));
+
+test!(loops_for_invalid => panic);
+test!(loops_range_invalid => panic);
+
+test!(loops_continue => (
+    odd_sum := 25,
+    skipped := 12,
+    pairs := 10,
+    x := 10,
+    inner := 3
+));
+
+test!(loops_unknown_label => panic);
+
+test!(loops_continue_outside => panic);
+
 test!(indices_dangling => (
     dangling := 10
 ));
@@ -127,11 +268,11 @@ test!(indices_indirect => (
 ));
 
 test!(indices_assignment => (
-    result == node!([
-        lit!(999),
-        lit!(777),
-        lit!(300)
-    ])
+    result == list![
+        999,
+        777,
+        300
+    ]
 ));
 
 test!(indices_negative_access => panic);
@@ -139,7 +280,7 @@ test!(indices_negative_access => panic);
 test!(indices_negative_assign => panic);
 
 test!(indices_nested => (
-    nest_0 == node!([lit!(123), lit!(321)]),
+    nest_0 == list![123, 321],
     nest_1 := 123,
     nest_2 := 321,
     nest_3 := true
@@ -148,28 +289,58 @@ test!(indices_nested => (
 test!(indices_out_of_range => panic);
 
 test!(lists => (
-    empty == node!([]),
-    nested_empty == node!([node!([node!([node!([])])])]),
-    spread == node!([lit!(1), lit!(2), lit!(3)]),
-    spread_empty == node!([node!([]), node!([]), node!([])]),
-    spread_nested == node!([node!([lit!(1), lit!(2), lit!(3)])])
+    empty == list![],
+    nested_empty == list![list![list![list![]]]],
+    spread == list![1, 2, 3],
+    spread_empty == list![list![], list![], list![]],
+    spread_nested == list![list![1, 2, 3]]
 ));
 
 test!(lists_concat => (
-    simple == node!([lit!(1), lit!(2), lit!(3), lit!(4)]),
-    empty_left == node!([lit!(1), lit!(2)]),
-    empty_right == node!([lit!(1), lit!(2)]),
-    both_empty == node!([]),
-    nested == node!([node!([lit!(1), lit!(2)]), node!([lit!(3), lit!(4)])]),
-    mixed_types == node!([lit!(1), lit!("a"), lit!(true), lit!('c')]),
-    chained == node!([lit!(1), lit!(2), lit!(3)])
+    simple == list![1, 2, 3, 4],
+    empty_left == list![1, 2],
+    empty_right == list![1, 2],
+    both_empty == list![],
+    nested == list![list![1, 2], list![3, 4]],
+    mixed_types == list![1, "a", true, 'c'],
+    chained == list![1, 2, 3]
 ));
 
 test!(lists_with_indices => (
-    source == node!([lit!(10), lit!(20), lit!(30)]),
-    with_index == node!([lit!(20)]),
-    with_many_index == node!([lit!(10), lit!(30)]),
-    with_nested_index == node!([node!([node!([lit!(20)])])])
+    source == list![10, 20, 30],
+    with_index == list![20],
+    with_many_index == list![10, 30],
+    with_nested_index == list![list![list![20]]]
+));
+
+test!(dicts => (
+    ann := 31,
+    by_variable := 1,
+    by_expression := 2,
+    by_int := "ten",
+    negative := "before",
+    negative_inserted := "far",
+    negative_keys == list![-1, 0, -2],
+    inserted := 40,
+    replaced := 32,
+    size := 3,
+    nested_item := 3,
+    nested_new := true,
+    shared := 4,
+    same := true,
+    different := false,
+    empty := 0,
+    count_a := 2,
+    count_b := 1
+));
+
+test!(dicts_missing_key => panic);
+
+test!(dicts_invalid_key => panic);
+
+test!(dicts_struct_fields => (
+    a_len := 1,
+    b_len := 0
 ));
 
 test!(imports => (
@@ -190,7 +361,7 @@ test!(string_concat => (
     char_concat := "xyz",
     char_concat_reverse := "xyz",
     unicode_str := "🔥",
-    unicode_len := 4
+    unicode_len := 1
 ));
 
 test!(nested_imports => (
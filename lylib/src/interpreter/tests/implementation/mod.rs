@@ -24,14 +24,14 @@ test!(factorial => (
 ));
 
 test!(matrix_rotation => (
-    matrix_a == node!([
-        node!([lit!(1), lit!(4), lit!(7)]),
-        node!([lit!(2), lit!(5), lit!(8)]),
-        node!([lit!(3), lit!(6), lit!(9)])
-    ]),
-    matrix_b == node!([
-        node!([lit!(1), lit!(1), lit!(1)]),
-        node!([lit!(1), lit!(1), lit!(1)]),
-        node!([lit!(1), lit!(1), lit!(1)])
-    ])
+    matrix_a == list![
+        list![1, 4, 7],
+        list![2, 5, 8],
+        list![3, 6, 9]
+    ],
+    matrix_b == list![
+        list![1, 1, 1],
+        list![1, 1, 1],
+        list![1, 1, 1]
+    ]
 ));
@@ -8,7 +8,7 @@ use crate::{
     *,
 };
 
-/// Shorthand for comparing a variable with any owned value.
+/// Shorthand for comparing a variable with any value.
 macro_rules! var_eq {
     ($interpreter:expr, $id:tt, $node:expr) => {
         let (got, expected) = (
@@ -24,23 +24,26 @@ macro_rules! var_eq {
     };
 }
 
-/// Shorthand for comparing a variable with an owned literal.
+/// Shorthand for comparing a variable with a literal, converted with `Value::from`.
 macro_rules! var_eq_literal {
-    ($interpreter:expr, $id:tt, $token:expr) => {
-        var_eq!($interpreter, $id, ASTNode::inner_to_owned(&lit!($token)));
+    ($interpreter:expr, $id:tt, $literal:expr) => {
+        var_eq!($interpreter, $id, Value::from($literal));
     };
 }
 
 /// Expands into entire test cases. The name of the function provided is expected to be the name
-/// of the test file, given the file extension is omitted.
+/// of the test file, given the file extension is omitted. Each file is tested under every
+/// backend, as `file_name::ast` and `file_name::bytecode`. Files read from empty input, unless
+/// input is given with `<=`.
 /// # Example
 /// ```ignore
 /// test!(file_name => ( // will read `file_name.ly`
 ///     // use `:=` to implicitly create a literal
 ///     literal_string := "expected value",
-///     // use `==` for custom nodes
-///     other_node == node!([lit!(1), lit!(2)]),
+///     // use `==` for other values
+///     other_value == list![1, 2],
 /// ));
+/// test!(file_name <= "input\n" => "expected output\n");
 /// ```
 macro_rules! test {
     // Helpers for progressively munching equality test statements.
@@ -60,10 +63,11 @@ macro_rules! test {
     };
 
     // Helper for running the file.
-    (@interpret $path:expr) => {{
+    (@interpret $path:expr, $backend:expr, $input:expr) => {{
         // interpret file
         use std::io::Cursor;
-        let mut i = Interpreter::new(Cursor::new(vec![]), Cursor::new(vec![]));
+        let mut i = Interpreter::new(Cursor::new($input.as_bytes().to_vec()), Cursor::new(vec![]));
+        i.set_backend($backend);
         let mut p = Parser::new(Lexer::default().lex(include_str!($path).to_string()).unwrap());
         p.set_pwd(std::path::PathBuf::from("src/interpreter/tests/feature/"));
         let ast = p.parse().unwrap();
@@ -77,34 +81,54 @@ macro_rules! test {
         (i, buf)
     }};
 
+    // Helper for expanding a test case once for each backend.
+    (@backends $file:tt, $input:expr, $($case:tt)*) => {
+        mod $file {
+            use super::*;
+            test!(@case ast, Backend::Ast, $input, $file, $($case)*);
+            test!(@case bytecode, Backend::Bytecode, $input, $file, $($case)*);
+        }
+    };
+
     // Test for variable equality
-    ($file:tt => ($($rest:tt)*)) => {
+    (@case $name:ident, $backend:expr, $input:expr, $file:tt, ($($rest:tt)*)) => {
         #[test]
-        fn $file() {
-            let (i, _) = test!(@interpret concat!(stringify!($file), ".ly"));
+        fn $name() {
+            let (i, _) = test!(@interpret concat!(stringify!($file), ".ly"), $backend, $input);
             test!(@munch i; $($rest)*);
         }
     };
 
     // Test & assure panic
-    ($file:tt => panic) => {
+    (@case $name:ident, $backend:expr, $input:expr, $file:tt, panic) => {
         #[test]
         #[should_panic]
-        fn $file() {
-            let (_, _) = test!(@interpret concat!(stringify!($file), ".ly"));
+        fn $name() {
+            let (_, _) = test!(@interpret concat!(stringify!($file), ".ly"), $backend, $input);
         }
     };
 
     // Test against `stdout`
-    ($file:tt => $expected:expr) => {
+    (@case $name:ident, $backend:expr, $input:expr, $file:tt, $expected:expr) => {
         #[test]
-        fn $file() {
-            let (_, out) = test!(@interpret concat!(stringify!($file), ".ly"));
+        fn $name() {
+            let (_, out) = test!(@interpret concat!(stringify!($file), ".ly"), $backend, $input);
             assert_eq!(out, $expected);
         }
     };
+
+    ($file:tt <= $input:literal => $($case:tt)*) => {
+        test!(@backends $file, $input, $($case)*);
+    };
+    ($file:tt => $($case:tt)*) => {
+        test!(@backends $file, "", $($case)*);
+    };
 }
 
+/// Every backend, for tests that run under each of them.
+#[cfg(test)]
+const BACKENDS: [Backend; 2] = [Backend::Ast, Backend::Bytecode];
+
 #[cfg(test)]
 mod builtins;
 
@@ -156,37 +180,496 @@ fn syntax() {
 
     // run each code block through the interpreter
     for (i, block) in blocks.iter().enumerate() {
+        for backend in BACKENDS {
+            let mut interpreter = Interpreter::new(Cursor::new(vec![]), Cursor::new(vec![]));
+            interpreter.set_backend(backend);
+            let lexer_result = Lexer::default().lex(block.clone());
+
+            let tokens = match lexer_result {
+                Ok(t) => t,
+                Err(e) => panic!(
+                    "SYNTAX.md block {} failed to lex:\n{}\nError: {}",
+                    i + 1,
+                    block,
+                    e
+                ),
+            };
+
+            let mut parser = Parser::new(tokens);
+            let ast = match parser.parse() {
+                Ok(a) => a,
+                Err(e) => panic!(
+                    "SYNTAX.md block {} failed to parse:\n{}\nError: {}",
+                    i + 1,
+                    block,
+                    e
+                ),
+            };
+
+            if let Err(e) = interpreter.execute(ast) {
+                panic!(
+                    "SYNTAX.md block {} failed to execute with {backend:?}:\n{}\nError: {}",
+                    i + 1,
+                    block,
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Tests that runtime errors point at the innermost failing node.
+#[test]
+fn error_location() {
+    use std::io::Cursor;
+
+    let source = "func fail x do\n  return x + undefined_value\nend\nlet a = fail(1)";
+    for backend in BACKENDS {
+        let ast = Parser::new(Lexer::default().lex(source.into()).unwrap())
+            .parse()
+            .unwrap();
         let mut interpreter = Interpreter::new(Cursor::new(vec![]), Cursor::new(vec![]));
-        let lexer_result = Lexer::default().lex(block.clone());
-
-        let tokens = match lexer_result {
-            Ok(t) => t,
-            Err(e) => panic!(
-                "SYNTAX.md block {} failed to lex:\n{}\nError: {}",
-                i + 1,
-                block,
-                e
-            ),
+        interpreter.set_backend(backend);
+        let err = interpreter.execute(ast).unwrap_err();
+        let span = err
+            .downcast_ref::<Span>()
+            .expect("error should carry a span");
+        assert_eq!(
+            (span.line, span.column),
+            (2, 14),
+            "wrong location with {backend:?}"
+        );
+    }
+}
+
+/// Tests that buffers evaluated one after another share memory, and return their values.
+#[test]
+fn eval() {
+    use std::io::Cursor;
+
+    for backend in BACKENDS {
+        let mut interpreter = LyConfig::default()
+            .include_as("math", "func double n do return n * 2 end;")
+            .backend(backend)
+            .build(Cursor::new(vec![]), Cursor::new(vec![]))
+            .unwrap();
+
+        // declarations & statements evaluate to undefined
+        assert_eq!(interpreter.eval("let a = 1").unwrap(), Value::Undefined);
+        assert_eq!(
+            interpreter
+                .eval("func inc n do\n  return n + 1\nend")
+                .unwrap(),
+            Value::Undefined
+        );
+
+        // trailing expressions evaluate to their values, using previous buffers & includes
+        assert_eq!(interpreter.eval("a = inc(a)\na").unwrap(), Value::Int(2));
+        assert_eq!(interpreter.eval("math.double(a)").unwrap(), Value::Int(4));
+
+        // errors leave memory intact
+        assert!(interpreter.eval("let b = undefined_value").is_err());
+        assert!(interpreter.eval("let b = (").is_err());
+        assert_eq!(interpreter.eval("a").unwrap(), Value::Int(2), "{backend:?}");
+
+        // warnings are collected per buffer, and taken by the embedder
+        assert_eq!(
+            interpreter.eval("match a do\n  2 => 1\nend").unwrap(),
+            Value::Int(1)
+        );
+        assert_eq!(interpreter.take_warnings().len(), 1);
+        assert!(interpreter.take_warnings().is_empty());
+    }
+}
+
+/// Tests that uncaught errors expose their kind to embedders.
+#[test]
+fn error_kinds() {
+    use std::io::Cursor;
+
+    for backend in BACKENDS {
+        let mut interpreter = LyConfig::default()
+            .backend(backend)
+            .build(Cursor::new(vec![]), Cursor::new(vec![]))
+            .unwrap();
+        let kind = |interpreter: &mut Interpreter<_, _>, source| {
+            ErrorKind::of(&interpreter.eval(source).unwrap_err())
         };
+        assert_eq!(kind(&mut interpreter, "[1][2]"), ErrorKind::Index);
+        assert_eq!(kind(&mut interpreter, "missing"), ErrorKind::Name);
+        assert_eq!(kind(&mut interpreter, "throw 1"), ErrorKind::Thrown);
+        assert_eq!(kind(&mut interpreter, "let a = ("), ErrorKind::Runtime);
+    }
+}
+
+/// Tests that both backends run the statements before control flow with nowhere to go, then
+/// fail the same way.
+#[test]
+fn escaped_flow() {
+    use std::io::Cursor;
+
+    let sources = [
+        ("break", "cannot break outside of a loop"),
+        (
+            "continue outer",
+            "cannot continue outside of a loop labeled 'outer'",
+        ),
+        ("return 1", "cannot return as base scope"),
+        ("if true do\n  break\nend", "cannot break outside of a loop"),
+        (
+            "while true do\n  let b = if true do\n    break\n  else\n    1\n  end\nend",
+            "control flow cannot leave an expression",
+        ),
+    ];
+    for backend in BACKENDS {
+        for (source, message) in sources {
+            let mut interpreter = LyConfig::default()
+                .backend(backend)
+                .build(Cursor::new(vec![]), Cursor::new(vec![]))
+                .unwrap();
+            let err = interpreter
+                .eval(format!("let a = 1\n{source}"))
+                .unwrap_err();
+            assert_eq!(
+                err.root_cause().to_string(),
+                message,
+                "{backend:?}: {source}"
+            );
+            assert_eq!(interpreter.eval("a").unwrap(), Value::Int(1), "{backend:?}");
+        }
+    }
+}
+
+/// Tests that both backends report indices out of bounds the same way, however the list is
+/// reached.
+#[test]
+fn index_errors() {
+    use std::io::Cursor;
+
+    let sources = [
+        "[1][2]",
+        "let a = [1]\na[2]",
+        "let b = [[1]]\nb[0][2]",
+        "func f do\n  let c = [1]\n  return c[2]\nend\nf()",
+    ];
+    for backend in BACKENDS {
+        for source in sources {
+            let mut interpreter = LyConfig::default()
+                .backend(backend)
+                .build(Cursor::new(vec![]), Cursor::new(vec![]))
+                .unwrap();
+            let err = interpreter.eval(source).unwrap_err();
+            assert_eq!(
+                err.root_cause().to_string(),
+                "index out of bounds",
+                "{backend:?}: {source}"
+            );
+        }
+    }
+}
+
+/// Tests that errors leaving function calls carry the calls that were active, innermost first.
+#[test]
+fn stack_trace() {
+    use std::io::Cursor;
 
-        let mut parser = Parser::new(tokens);
-        let ast = match parser.parse() {
-            Ok(a) => a,
-            Err(e) => panic!(
-                "SYNTAX.md block {} failed to parse:\n{}\nError: {}",
-                i + 1,
-                block,
-                e
-            ),
+    for backend in BACKENDS {
+        let mut interpreter = LyConfig::default()
+            .include_as("math", "func fail n do\n  return n + missing\nend")
+            .backend(backend)
+            .build(Cursor::new(vec![]), Cursor::new(vec![]))
+            .unwrap();
+        let mut trace = |source: &str| {
+            let err = interpreter.eval(source).unwrap_err();
+            let StackTrace(frames) = err
+                .downcast_ref::<StackTrace>()
+                .expect("error should carry a stack trace");
+            frames
+                .iter()
+                .map(|frame| (frame.to_string(), frame.call_site.line))
+                .collect::<Vec<_>>()
         };
+        assert_eq!(
+            trace("func check x do\n  return math.fail(x)\nend\nlet a = check(1)"),
+            [("math.fail".to_string(), 2), ("check".to_string(), 4)],
+            "wrong trace with {backend:?}"
+        );
+        assert_eq!(
+            trace("struct Point\n  func Point do\n    a = missing\n  end\nend\nnew Point()"),
+            [("Point".to_string(), 6)],
+            "wrong trace with {backend:?}"
+        );
+    }
+}
 
-        if let Err(e) = interpreter.execute(ast) {
-            panic!(
-                "SYNTAX.md block {} failed to execute:\n{}\nError: {}",
-                i + 1,
-                block,
-                e
+/// Tests that runaway recursion raises a catchable error once it reaches the maximum call depth.
+#[test]
+fn max_call_depth() {
+    use std::io::Cursor;
+
+    for backend in BACKENDS {
+        let mut interpreter = LyConfig::default()
+            .backend(backend)
+            .max_call_depth(32)
+            .build(Cursor::new(vec![]), Cursor::new(vec![]))
+            .unwrap();
+        interpreter
+            .eval("func depth n do\n  return depth(n + 1)\nend")
+            .unwrap();
+
+        // uncaught, the error carries every active call
+        let err = interpreter.eval("depth(0)").unwrap_err();
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Recursion, "{backend:?}");
+        let StackTrace(frames) = err.downcast_ref::<StackTrace>().unwrap();
+        assert_eq!(frames.len(), 32);
+
+        // caught, the call stack unwinds & calls work again
+        interpreter
+            .eval("let kind = \"\"\ntry do\n  depth(0)\ncatch err do\n  kind = err.kind\nend")
+            .unwrap();
+        assert_eq!(interpreter.eval("kind").unwrap(), Value::from("recursion"));
+        assert_eq!(
+            interpreter.eval("func one do return 1 end\none()").unwrap(),
+            Value::Int(1)
+        );
+    }
+}
+
+/// Tests that running out of fuel stops execution with an error that can't be caught, and that
+/// buffers can be evaluated again once more fuel is given.
+#[test]
+fn fuel() {
+    use std::io::Cursor;
+
+    for backend in BACKENDS {
+        let mut interpreter = LyConfig::default()
+            .backend(backend)
+            .fuel(1000)
+            .build(Cursor::new(vec![]), Cursor::new(vec![]))
+            .unwrap();
+        let source = "let caught = false\nlet i = 0\ntry do\n  while true do\n    let j = i\n    \
+                      i = i + 1\n  end\ncatch do\n  caught = true\nfinally\n  caught = true\nend";
+        let err = interpreter.eval(source).unwrap_err();
+        assert_eq!(
+            ErrorKind::of(&err),
+            ErrorKind::ResourceExhausted,
+            "{backend:?}"
+        );
+        assert_eq!(interpreter.fuel(), Some(0));
+
+        // neither the handler nor the finally body ran
+        interpreter.add_fuel(100);
+        assert_eq!(interpreter.eval("caught").unwrap(), Value::Bool(false));
+        assert!(matches!(interpreter.eval("i").unwrap(), Value::Int(i) if i > 10));
+        assert!(interpreter.eval("j").is_err());
+        assert!(interpreter.fuel().unwrap() < 100);
+
+        // running out within a loop at base scope doesn't leave its scope behind
+        assert!(interpreter.eval("while true do\n  let k = 1\nend").is_err());
+        assert_eq!(interpreter.scope_id, 0, "{backend:?}");
+    }
+}
+
+/// Tests that executions running out of fuel continue where they were when more is given, even
+/// within function calls, and stop once no more is.
+#[test]
+fn refuel() {
+    use std::{cell::Cell, io::Cursor};
+
+    for backend in BACKENDS {
+        let refuels = Rc::new(Cell::new(0));
+        let counter = refuels.clone();
+        let mut interpreter = LyConfig::default()
+            .backend(backend)
+            .fuel(100)
+            .refuel(move || {
+                counter.set(counter.get() + 1);
+                (counter.get() <= 50).then_some(100)
+            })
+            .build(Cursor::new(vec![]), Cursor::new(vec![]))
+            .unwrap();
+        let source = "func sum n do\n  let total = 0\n  for i in 0..n do\n    \
+                      total = total + i\n  end\n  return total\nend\nsum(200)";
+        assert_eq!(interpreter.eval(source).unwrap(), Value::Int(19900));
+        assert!(refuels.get() > 1, "{backend:?}");
+
+        // once no more fuel is given, execution stops
+        let err = interpreter.eval("while true do\nend").unwrap_err();
+        assert_eq!(
+            ErrorKind::of(&err),
+            ErrorKind::ResourceExhausted,
+            "{backend:?}"
+        );
+        assert_eq!(refuels.get(), 51, "{backend:?}");
+    }
+}
+
+/// Tests that executions taking longer than their timeout are stopped.
+#[test]
+fn timeout() {
+    use std::{io::Cursor, time::Duration};
+
+    for backend in BACKENDS {
+        let mut interpreter = LyConfig::default()
+            .backend(backend)
+            .timeout(Duration::from_millis(20))
+            .build(Cursor::new(vec![]), Cursor::new(vec![]))
+            .unwrap();
+        let err = interpreter.eval("while true do\nend").unwrap_err();
+        assert_eq!(
+            ErrorKind::of(&err),
+            ErrorKind::ResourceExhausted,
+            "{backend:?}"
+        );
+
+        // each execution gets its own timeout
+        assert_eq!(interpreter.eval("1 + 1").unwrap(), Value::Int(2));
+    }
+}
+
+/// Tests that programs holding more memory than they may are stopped, whether their values grow
+/// by concatenation, by insertion or by being copied, and that the error can't be caught.
+#[test]
+fn memory_limit() {
+    use std::io::Cursor;
+
+    let sources = [
+        "let s = \"a\"\nwhile true do\n  s = s + s\nend",
+        "let l = []\nwhile true do\n  l = l + [\"item\"]\nend",
+        "struct Point\n  let x = 0\nend\nlet d = {}\nlet i = 0\nwhile true do\n  \
+         d[i] = new Point()\n  i = i + 1\nend",
+        "let s = \"a\"\ntry do\n  while true do\n    s = s + s\n  end\ncatch do\nend",
+        "let s = repeat(\"a\", 60000)\nlet d = {}\nlet n = 0\nwhile true do\n  d[n] = s\n  \
+         n = n + 1\nend",
+        "let s = repeat(\"a\", 60000)\nlet t = s",
+        "let s = repeat(\"a\", 60000)\nlet l = []\nwhile true do\n  push(l, s)\nend",
+        "let s = repeat(\"a\", 60000)\nlet l = []\nwhile true do\n  insert(l, 0, s)\nend",
+        "let s = repeat(\"a\", 60000)\nlet d = {\"l\": []}\nwhile true do\n  push(d[\"l\"], s)\nend",
+    ];
+    for backend in BACKENDS {
+        for source in sources {
+            let mut interpreter = LyConfig::default()
+                .backend(backend)
+                .memory_limit(64 * 1024)
+                .build(Cursor::new(vec![]), Cursor::new(vec![]))
+                .unwrap();
+            let err = interpreter.eval(source).unwrap_err();
+            assert_eq!(
+                ErrorKind::of(&err),
+                ErrorKind::ResourceExhausted,
+                "{backend:?}: {source}"
+            );
+
+            // memory is measured approximately, but never grows far past the limit
+            let usage = interpreter.memory_usage();
+            assert!(
+                usage > 32 * 1024 && usage < 128 * 1024,
+                "{backend:?}: {usage}"
+            );
+        }
+    }
+}
+
+/// Tests that values held by the variables of a function count towards the memory limit, which
+/// live in local slots rather than memory with the bytecode backend.
+#[test]
+fn memory_limit_locals() {
+    use std::io::Cursor;
+
+    let sources = [
+        "func f do\n  let s = repeat(\"a\", 60000)\n  let l = []\n  while true do\n    \
+         push(l, s)\n  end\nend\nf()",
+        "func f do\n  let s = \"a\"\n  while true do\n    s = s + s\n  end\nend\nf()",
+    ];
+    for backend in BACKENDS {
+        for source in sources {
+            let mut interpreter = LyConfig::default()
+                .backend(backend)
+                .memory_limit(64 * 1024)
+                .build(Cursor::new(vec![]), Cursor::new(vec![]))
+                .unwrap();
+            let err = interpreter.eval(source).unwrap_err();
+            assert_eq!(
+                ErrorKind::of(&err),
+                ErrorKind::ResourceExhausted,
+                "{backend:?}: {source}"
+            );
+        }
+    }
+}
+
+/// Tests that external functions building large values fail before building them, rather than
+/// running out of memory on the host.
+#[test]
+fn memory_limit_natives() {
+    use std::io::Cursor;
+
+    let sources = [
+        "let l = range(500000000)",
+        "let l = range(0, 9000000000000000000, 2)",
+        "let s = repeat(\"abcdefgh\", 1000000000)",
+    ];
+    for backend in BACKENDS {
+        for source in sources {
+            let mut interpreter = LyConfig::default()
+                .backend(backend)
+                .memory_limit(64 * 1024)
+                .build(Cursor::new(vec![]), Cursor::new(vec![]))
+                .unwrap();
+            let err = interpreter.eval(source).unwrap_err();
+            assert_eq!(
+                ErrorKind::of(&err),
+                ErrorKind::ResourceExhausted,
+                "{backend:?}: {source}"
+            );
+
+            // small values can still be built afterwards
+            interpreter.eval("let small = range(10)").unwrap();
+        }
+    }
+}
+
+/// Tests that native math functions share their module with files included under its alias, and
+/// that seeding the generator makes its numbers reproducible.
+#[test]
+fn math_module() {
+    use std::io::Cursor;
+
+    for backend in BACKENDS {
+        let mut interpreter = LyConfig::default()
+            .backend(backend)
+            .math_as("math")
+            .include_as("math", "let PI = 3.141592653589793")
+            .build(Cursor::new(vec![]), Cursor::new(vec![]))
+            .unwrap();
+        assert_eq!(
+            interpreter
+                .eval("math.round(math.PI * math.sqrt(4))")
+                .unwrap(),
+            Value::Int(6)
+        );
+        assert!(interpreter.eval("round(1.5)").is_err());
+
+        // rounding gives integers, while integers given to abs, max & min stay integers
+        for (source, expected) in [
+            ("math.floor(2.5)", 2),
+            ("math.ceil(2.5)", 3),
+            ("math.trunc(-2.5)", -2),
+            ("math.abs(-4)", 4),
+            ("math.max(1, 2)", 2),
+            ("math.min(1, 2)", 1),
+        ] {
+            assert_eq!(
+                interpreter.eval(source).unwrap(),
+                Value::Int(expected),
+                "{source}"
             );
         }
+
+        // the same seed gives the same numbers
+        let source = "math.seed(7)\n[math.random(), math.random_int(0, 1000)]";
+        let first = interpreter.eval(source).unwrap();
+        assert_eq!(interpreter.eval(source).unwrap(), first, "{backend:?}");
+        assert!(interpreter.eval("math.random_int(1, 1)").is_err());
     }
 }
@@ -0,0 +1,476 @@
+//! Runtime values. Everything a Lily program computes, stores, or passes to external functions is
+//! a `Value`, keeping syntax trees purely syntactic.
+
+use super::*;
+use std::fmt::{Debug, Display};
+
+/// External function signature.
+/// The first argument is the interpreter calling the function. The second contains arguments.
+pub type ExFn = dyn Fn(&mut dyn Host, &[Value]) -> Result<Value>;
+
+/// External function that changes its first argument in place, like `push`.
+/// The first argument is the variable passed to the function, rather than a copy of it. The second
+/// contains the other arguments.
+pub type ExFnMut = dyn Fn(&mut Value, &[Value]) -> Result<Value>;
+
+/// The interpreter, as seen by external functions.
+pub trait Host {
+    /// Gets the output handle of the interpreter.
+    fn writer(&mut self) -> &mut dyn Write;
+    /// Gets the input handle of the interpreter.
+    fn reader(&mut self) -> &mut dyn Read;
+    /// Calls a function value with the given arguments, returning its result.
+    fn call(&mut self, function: &Value, args: &[Value]) -> Result<Value>;
+    /// Fails if memory can't hold about `bytes` more bytes. Functions building large values call
+    /// this before building them, since they're only counted towards memory usage afterwards.
+    fn reserve(&mut self, bytes: usize) -> Result<()>;
+}
+
+/// Variables captured by a function when it is created, by name.
+pub(crate) type Captures = FxHashMap<usize, Rc<RefCell<Variable>>>;
+
+/// A key of a dictionary entry, or an index into a list or string. See `Value::as_key`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Int(i64),
+    Str(Rc<str>),
+}
+
+impl Key {
+    /// Converts this key into a list index. Returns `Err` if the key was created from a string,
+    /// or is negative.
+    pub(crate) fn as_index(&self) -> Result<usize> {
+        Value::from(self).as_index()
+    }
+}
+
+impl From<&Key> for Value {
+    fn from(key: &Key) -> Self {
+        match key {
+            Key::Int(n) => Value::Int(*n),
+            Key::Str(s) => Value::Str(s.to_string()),
+        }
+    }
+}
+
+/// A runtime value.
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Char(char),
+    Bool(bool),
+    Undefined,
+    /// A list of values. Each item lives in its own cell, so that it can be assigned in place.
+    List(Vec<Rc<RefCell<Variable>>>),
+    /// A dictionary of values, keyed by strings or integers. Like instances, dictionaries share
+    /// their entries when copied.
+    Dict(Rc<RefCell<Dict>>),
+    /// An instance of a structure. Instances share their members when copied.
+    Instance {
+        kind: Rc<Structure>,
+        svt: Rc<RefCell<SVTable>>,
+    },
+    /// A function declared in Lily, along with the variables it captured from the scope it was
+    /// created in. Captures are shared between copies of the function, and between its calls.
+    Function {
+        node: Rc<ASTNode>,
+        captures: Rc<Captures>,
+    },
+    /// A function implemented by the host.
+    Native(Rc<ExFn>),
+    /// A function implemented by the host, which changes its first argument in place. Called with
+    /// anything other than a variable as its first argument, it changes a copy instead.
+    NativeMut(Rc<ExFnMut>),
+    /// A structure type, used as a value, like when passed to `is_instance`. Calling it creates
+    /// an instance, like calling the structure by name.
+    Type(Rc<Structure>),
+}
+
+/// A structure type, created when a structure declaration is executed.
+#[derive(Debug)]
+pub struct Structure {
+    /// Name of the structure.
+    pub name: usize,
+    /// Initial members of every instance, including methods.
+    pub(crate) template: SVTable,
+    /// Constructor of the structure, if it defines one.
+    pub(crate) constructor: Option<Rc<ASTNode>>,
+}
+
+impl Structure {
+    /// Creates a structure type from its declaration. `fields` holds the evaluated default
+    /// values of its fields, in order of declaration.
+    pub(crate) fn new(node: &ASTNode, fields: impl IntoIterator<Item = Value>) -> Result<Self> {
+        let ASTNode::Struct { id, body, .. } = node else {
+            bail!("cannot create structure from non-structure node {node:?}");
+        };
+        let IDKind::Symbol(name) = id.get_kind() else {
+            bail!("invalid structure name {id:?}");
+        };
+
+        // add default fields & methods to the template
+        let mut template = SVTable::default();
+        let mut fields = fields.into_iter();
+        let ASTNode::Block {
+            statements: members,
+            ..
+        } = &**body
+        else {
+            bail!("expected structure body to be a block");
+        };
+        for member in members {
+            let (sym, value) = match &**member {
+                ASTNode::Declare { target, .. } => {
+                    let ASTNode::Literal {
+                        token: Token::Identifier(sym),
+                        ..
+                    } = &**target
+                    else {
+                        bail!("invalid default field '{target:?}'");
+                    };
+                    let value = fields.next().context("missing default field value")?;
+                    (*sym, value)
+                }
+                ASTNode::Function { id: Some(id), .. } => {
+                    let IDKind::Symbol(sym) = id.get_kind() else {
+                        bail!("invalid method name {id:?}");
+                    };
+                    let method = Value::Function {
+                        node: member.clone(),
+                        captures: Rc::default(),
+                    };
+                    (sym, method)
+                }
+                other => bail!("unexpected structure field: {other:?}"),
+            };
+            template.declare(sym, Variable::Owned(value), 0)?;
+        }
+
+        Ok(Self {
+            name,
+            template,
+            constructor: node.constructor(),
+        })
+    }
+
+    /// Creates the `Error` structure, whose instances are the errors caught by try statements.
+    pub(crate) fn error() -> Self {
+        let mut template = SVTable::default();
+        for member in ["kind", "message"] {
+            template
+                .declare(
+                    intern!(member),
+                    Variable::Owned(Value::Str(String::new())),
+                    0,
+                )
+                .expect("members are distinct");
+        }
+        Self {
+            name: intern!("Error"),
+            template,
+            constructor: None,
+        }
+    }
+}
+
+/// A dictionary, holding entries in order of insertion.
+#[derive(Default)]
+pub struct Dict {
+    entries: Vec<(Key, Rc<RefCell<Variable>>)>,
+    /// Position of every key in `entries`.
+    positions: FxHashMap<Key, usize>,
+}
+
+impl Dict {
+    /// Returns the number of entries in this dictionary.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if this dictionary has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Gets the variable at `key`, if there is one.
+    pub(crate) fn get(&self, key: &Key) -> Option<&Rc<RefCell<Variable>>> {
+        self.positions.get(key).map(|&idx| &self.entries[idx].1)
+    }
+
+    /// Sets the variable at `key`. New keys are added after every other entry.
+    pub(crate) fn insert(&mut self, key: Key, value: Variable) {
+        match self.positions.get(&key) {
+            Some(&idx) => self.entries[idx].1 = value.into(),
+            None => {
+                self.positions.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value.into()));
+            }
+        }
+    }
+
+    /// Removes the entry at `key`, returning its variable if there was one.
+    pub(crate) fn remove(&mut self, key: &Key) -> Option<Variable> {
+        let idx = self.positions.remove(key)?;
+        let (_, variable) = self.entries.remove(idx);
+        for (key, _) in &self.entries[idx..] {
+            *self.positions.get_mut(key).unwrap() -= 1; // safety: every entry has a position
+        }
+        Some(variable.borrow().clone())
+    }
+
+    /// Returns the entries of this dictionary in order, with their keys as values.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Value, &Rc<RefCell<Variable>>)> {
+        self.entries
+            .iter()
+            .map(|(key, variable)| (Value::from(key), variable))
+    }
+}
+
+impl Clone for Dict {
+    /// Deep clone the dictionary, creating new cells for each entry.
+    fn clone(&self) -> Self {
+        Self {
+            entries: self
+                .entries
+                .iter()
+                .map(|(key, variable)| {
+                    let variable = variable.borrow().clone();
+                    (key.clone(), Rc::new(RefCell::new(variable)))
+                })
+                .collect(),
+            positions: self.positions.clone(),
+        }
+    }
+}
+
+impl Dict {
+    /// Returns true if both dictionaries have the same keys, in any order, and `eq` holds for
+    /// the variables at every key.
+    pub(crate) fn eq_by(&self, other: &Self, eq: impl Fn(&Variable, &Variable) -> bool) -> bool {
+        self.len() == other.len()
+            && self.entries.iter().all(|(key, variable)| {
+                other
+                    .get(key)
+                    .is_some_and(|other| eq(&variable.borrow(), &other.borrow()))
+            })
+    }
+}
+
+impl PartialEq for Dict {
+    fn eq(&self, other: &Self) -> bool {
+        self.eq_by(other, |a, b| a == b)
+    }
+}
+
+impl Value {
+    /// Returns the truthiness of this value.
+    /// True booleans, non-zero numbers, and all other defined values are truthy.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Bool(v) => *v,
+            Value::Int(n) => *n != 0,
+            Value::Float(n) => *n != 0.,
+            Value::Undefined => false,
+            _ => true,
+        }
+    }
+
+    /// Converts this value into a list index. Returns `Err` if this value is not an integer, or
+    /// the integer is negative.
+    pub(crate) fn as_index(&self) -> Result<usize> {
+        match self {
+            Value::Int(n) => match usize::try_from(*n) {
+                Ok(index) => Ok(index),
+                Err(_) => raise!(Index, "index values must be non-negative"),
+            },
+            _ => raise!(Type, "expected an integer as index, found {self:?}"),
+        }
+    }
+
+    /// Converts this value into a key, which indexes lists, strings and dictionaries. Returns
+    /// `Err` for values other than integers & strings.
+    pub(crate) fn as_key(&self) -> Result<Key> {
+        match self {
+            Value::Str(s) => Ok(Key::Str(s.as_str().into())),
+            Value::Int(n) => Ok(Key::Int(*n)),
+            _ => raise!(Type, "expected an integer or string as key, found {self:?}"),
+        }
+    }
+
+    /// Returns this value as a float if it is numeric, promoting integers.
+    pub(crate) fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Int(n) => Some(*n as f64),
+            Value::Float(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<&Token> for Value {
+    type Error = anyhow::Error;
+
+    /// Converts a literal token into its value.
+    fn try_from(token: &Token) -> Result<Self> {
+        Ok(match token {
+            Token::Int(n) => Value::Int(*n),
+            Token::Float(n) => Value::Float(*n),
+            Token::Str(s) => Value::Str(s.clone()),
+            Token::Char(c) => Value::Char(*c),
+            Token::Bool(b) => Value::Bool(*b),
+            Token::Undefined => Value::Undefined,
+            other => bail!("{other:?} is not a literal value"),
+        })
+    }
+}
+
+impl From<isize> for Value {
+    fn from(value: isize) -> Self {
+        Value::Int(value as i64)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Float(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<char> for Value {
+    fn from(value: char) -> Self {
+        Value::Char(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(String::from(value))
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Str(value)
+    }
+}
+
+impl Clone for Value {
+    fn clone(&self) -> Self {
+        match self {
+            // lists deeply clone their items
+            Value::List(items) => Value::List(
+                items
+                    .iter()
+                    .map(|item| Rc::new(RefCell::new(item.borrow().clone())))
+                    .collect(),
+            ),
+
+            // all other values are cloned as is
+            Value::Int(n) => Value::Int(*n),
+            Value::Float(n) => Value::Float(*n),
+            Value::Str(s) => Value::Str(s.clone()),
+            Value::Char(c) => Value::Char(*c),
+            Value::Bool(b) => Value::Bool(*b),
+            Value::Undefined => Value::Undefined,
+            Value::Dict(dict) => Value::Dict(dict.clone()),
+            Value::Instance { kind, svt } => Value::Instance {
+                kind: kind.clone(),
+                svt: svt.clone(),
+            },
+            Value::Function { node, captures } => Value::Function {
+                node: node.clone(),
+                captures: captures.clone(),
+            },
+            Value::Native(func) => Value::Native(func.clone()),
+            Value::NativeMut(func) => Value::NativeMut(func.clone()),
+            Value::Type(structure) => Value::Type(structure.clone()),
+        }
+    }
+}
+
+/// Structural equality. Integers and floats are never equal here, even if they hold the same
+/// number; Lily's `==` operator compares numbers by value instead.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Char(a), Value::Char(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Undefined, Value::Undefined) => true,
+            (Value::List(a), Value::List(b)) => a == b,
+            (Value::Dict(a), Value::Dict(b)) => Rc::ptr_eq(a, b) || a == b,
+            (Value::Instance { kind: ka, svt: a }, Value::Instance { kind: kb, svt: b }) => {
+                Rc::ptr_eq(ka, kb) && a == b
+            }
+            (
+                Value::Function {
+                    node: a,
+                    captures: ca,
+                },
+                Value::Function {
+                    node: b,
+                    captures: cb,
+                },
+            ) => a == b && Rc::ptr_eq(ca, cb),
+            (Value::Native(a), Value::Native(b)) => Rc::ptr_eq(a, b),
+            (Value::NativeMut(a), Value::NativeMut(b)) => Rc::ptr_eq(a, b),
+            (Value::Type(a), Value::Type(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Float(n) => write!(f, "{n:?}"),
+            Value::Str(s) => write!(f, "{s}"),
+            Value::Char(c) => write!(f, "{c}"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Float(n) => write!(f, "{n:?}"),
+            Value::Str(s) => write!(f, "{s:?}"),
+            Value::Char(c) => write!(f, "{c:?}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Undefined => write!(f, "undefined"),
+            Value::List(items) => f
+                .debug_list()
+                .entries(items.iter().map(|item| item.borrow()))
+                .finish(),
+            Value::Dict(dict) => f
+                .debug_map()
+                .entries(
+                    dict.borrow()
+                        .iter()
+                        .map(|(key, variable)| (key, variable.borrow())),
+                )
+                .finish(),
+            Value::Instance { kind, .. } => write!(f, "<instance of {}>", resolve!(kind.name)),
+            Value::Function { node, .. } => match &**node {
+                ASTNode::Function { id: Some(id), .. } => {
+                    write!(f, "<function {:?}>", id.get_kind_ref())
+                }
+                _ => write!(f, "<function>"),
+            },
+            Value::Native(_) | Value::NativeMut(_) => write!(f, "<native function>"),
+            Value::Type(structure) => write!(f, "<struct {}>", resolve!(structure.name)),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Bytecode representation.
+
+use super::*;
+use std::fmt::Debug;
+
+/// A single virtual machine instruction. Instructions operate on the value stack of the function
+/// being run, and on its local slots.
+#[derive(Debug, Clone)]
+pub(crate) enum Op {
+    /// Pushes a constant from the pool of the chunk.
+    Constant(usize),
+    /// Pushes an undefined value.
+    Undefined,
+    /// Discards the top value.
+    Pop,
+    /// Pushes a copy of a local slot.
+    GetLocal(usize),
+    /// Pops a value into a local slot.
+    SetLocal(usize),
+    /// Pushes a copy of a named variable from the current memory context.
+    GetName(usize),
+    /// Pops a value, and assigns it to a named variable.
+    AssignName(usize),
+    /// Pops a value, and declares it as a named variable.
+    DeclareName(usize),
+    /// Pushes the value at a path. Index keys are popped from the stack, last key on top.
+    GetPath(Root, Rc<[Key]>),
+    /// Pushes the first argument of a call from a path, like `GetPath`, unless the callee below
+    /// its index keys changes its first argument in place. Then the index keys are popped and
+    /// pushed as a list instead, for the call to follow the path once the other arguments are
+    /// evaluated.
+    Borrow(Root, Rc<[Key]>),
+    /// Pops a value, then the index keys of a path, and assigns the value at the path.
+    AssignPath(Root, Rc<[Key]>),
+    /// Pops a value, then the index keys of a path, and declares the value at the path. Paths
+    /// are always named, as locals can't be declared through paths.
+    DeclarePath(usize, Rc<[Key]>),
+    /// Pops an instance, and pushes one of its members.
+    GetMember(usize),
+    /// Pops an index, then a target, and pushes the item at that index of the target.
+    Index,
+    /// Pops a number of items, and pushes them as a list.
+    List(usize),
+    /// Pops a number of entries, each a key followed by its value, and pushes them as a
+    /// dictionary.
+    Dict(usize),
+    /// Pops the default values of the fields of a structure declaration, and pushes its type.
+    Struct(Rc<ASTNode>, usize),
+    /// Pushes a function created from a function node. Locals captured by the function are
+    /// listed by symbol & slot, outermost first.
+    Closure(Rc<ASTNode>, Rc<[(usize, usize)]>),
+    /// Pops two operands, and pushes the result of an operator.
+    Binary(Token),
+    /// Pops an operand, and pushes the result of an operator.
+    Unary(Token),
+    /// Pops a value, and pushes it incremented or decremented. Values other than numbers are
+    /// pushed back unchanged.
+    Step(Token),
+    /// Pops an instance, and pushes it as the receiver of one of its methods, followed by the
+    /// method itself.
+    Method(usize),
+    /// Pushes the receiver & callee of a method at a path. The receiver is undefined if the
+    /// parent of the path isn't an instance.
+    GetMethod(Root, Rc<[Key]>),
+    /// Pops a list, string or dictionary, and pushes the entries a for loop visits in it, as a
+    /// flat list of keys each followed by their item. Dictionaries have their values as items if
+    /// set.
+    Entries(bool),
+    /// Advances a for loop, whose entries are in a local slot, and whose position is in the slot
+    /// after it. Pushes the key & item of the next entry, or jumps to an instruction once there
+    /// are none left.
+    Next(usize, usize),
+    /// Pops the start & end of a range, and pushes them back once they're known to be integers.
+    Bounds,
+    /// Advances a for loop over a range, whose start is in a local slot, whose position is in the
+    /// slot after it, and whose end is in the slot after that. Pushes the position & integer
+    /// reached, or jumps to an instruction once the end is reached.
+    Count(usize, usize),
+    /// Tests the value in a local slot against the pattern of a match arm. Pushes the values of
+    /// the names it binds if it matches, or jumps to an instruction otherwise.
+    Match(Rc<Pattern>, usize, usize),
+    /// Fails a match, whose value in a local slot no arm matched.
+    NoMatch(usize),
+    /// Jumps to an instruction.
+    Jump(usize),
+    /// Pops a value, and jumps to an instruction if it is falsy.
+    JumpIfFalse(usize),
+    /// Pops a number of arguments, the callee, and its receiver, and pushes the result of the
+    /// call. Holds the path of the module the callee is looked up in, if it is called through
+    /// one, for stack traces, and the path of the first argument if it was pushed by `Borrow`.
+    Call(usize, Option<Rc<str>>, Option<(Root, Rc<[Key]>)>),
+    /// Pops a value, and returns it from the chunk.
+    Return,
+    /// Pops a value, and throws it.
+    Throw,
+    /// Fails with an error, for control flow that has nowhere to go.
+    Fail(Rc<str>),
+    /// Installs a handler for the instructions that follow. When one fails, the stack is restored,
+    /// and the error is pushed as a value before jumping to an instruction.
+    Catch(usize),
+    /// Installs a handler for the instructions that follow. When one fails, the stack is restored,
+    /// and the error is kept aside before jumping to an instruction, to be raised again by
+    /// `Resume`.
+    Finally(usize),
+    /// Removes the innermost handler.
+    EndTry,
+    /// Raises the error most recently kept aside by a handler again.
+    Resume,
+    /// Runs the body of a module.
+    Module(Rc<Module>),
+}
+
+/// Where a path starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Root {
+    Local(usize),
+    Name(usize),
+}
+
+/// A step along a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Key {
+    /// A member of an instance or module.
+    Member(usize),
+    /// An index into a list or string. The index itself is evaluated onto the stack.
+    Index,
+}
+
+/// A compiled module body.
+#[derive(Debug)]
+pub(crate) struct Module {
+    pub alias: Option<usize>,
+    pub path: Option<PathBuf>,
+    pub body: Chunk,
+}
+
+/// A compiled function body or program.
+#[derive(Default)]
+pub(crate) struct Chunk {
+    /// Instructions, in order of execution.
+    pub code: Vec<Op>,
+    /// Source location of every instruction, for errors.
+    pub spans: Vec<Span>,
+    /// Constant pool.
+    pub constants: Vec<Variable>,
+    /// Number of local slots, including parameters.
+    pub locals: usize,
+    /// Number of parameters. These occupy the first local slots.
+    pub params: usize,
+}
+
+impl Debug for Chunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "chunk ({} params, {} locals)", self.params, self.locals)?;
+        for (idx, op) in self.code.iter().enumerate() {
+            match op {
+                Op::Constant(constant) => {
+                    writeln!(f, "{idx:>4} Constant({:?})", self.constants[*constant])?
+                }
+                op => writeln!(f, "{idx:>4} {op:?}")?,
+            }
+        }
+        Ok(())
+    }
+}
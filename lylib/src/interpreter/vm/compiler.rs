@@ -0,0 +1,856 @@
+//! Lowers syntax trees into bytecode.
+//!
+//! Variables declared inside of functions and nested blocks are resolved to local slots at
+//! compile time. Declarations at the top level of a program or module stay named, so that they
+//! live in the memory table like they do when walking the tree, and other names are looked up in
+//! the current memory context at runtime.
+
+use super::*;
+
+/// A step of a path, along with the index expression it needs, if any.
+type PathStep = (Key, Option<Rc<ASTNode>>);
+
+/// A loop being compiled.
+struct LoopTarget {
+    label: Option<usize>,
+    /// Where continues jump to.
+    start: usize,
+    /// Unpatched break jumps, which jump past the end of the loop.
+    breaks: Vec<usize>,
+    /// Number of try statements outside of the loop, whose handlers stay installed when leaving
+    /// it.
+    tries: usize,
+}
+
+/// Compiles a single chunk.
+pub(crate) struct Compiler {
+    chunk: Chunk,
+    /// Lexical scopes of locals, innermost last. Each local maps a symbol to its slot.
+    /// Declarations made outside of any scope are named.
+    scopes: Vec<Vec<(usize, usize)>>,
+    /// Loops being compiled, innermost last.
+    loops: Vec<LoopTarget>,
+    /// Try statements whose handler is installed at the code being compiled, innermost last,
+    /// along with the finally body that runs when control flow leaves them.
+    tries: Vec<Option<Rc<ASTNode>>>,
+    /// Numbers of loops & of try statements outside of the innermost conditional expression
+    /// being compiled, if any. Control flow can't leave expressions, so only loops inside of it
+    /// can be broken out of.
+    expression: Option<(usize, usize)>,
+    /// True when compiling a function body, where returns are allowed.
+    function: bool,
+    /// Location of the innermost node being compiled.
+    span: Span,
+}
+
+impl Compiler {
+    /// Creates a new compiler for an empty chunk.
+    fn new(function: bool) -> Self {
+        Self {
+            chunk: Chunk::default(),
+            scopes: vec![],
+            loops: vec![],
+            tries: vec![],
+            expression: None,
+            function,
+            span: Span::default(),
+        }
+    }
+
+    /// Compiles a program or module body. Declarations at its top level are named. If the body
+    /// ends with an expression, the chunk returns its value.
+    pub fn program(ast: &Rc<ASTNode>) -> Result<Chunk> {
+        let mut compiler = Self::new(false);
+        if let ASTNode::Block { statements, .. } = &**ast
+            && let Some((last, rest)) = statements.split_last()
+            && last.is_expression()
+        {
+            rest.iter()
+                .try_for_each(|statement| compiler.statement(statement))?;
+            compiler.expr(last)?;
+            compiler.emit(Op::Return);
+        } else {
+            compiler.block(ast)?;
+        }
+        Ok(compiler.finish())
+    }
+
+    /// Compiles the body of a function node. Its parameters take up the first local slots.
+    pub fn function(function: &ASTNode) -> Result<Chunk> {
+        let ASTNode::Function {
+            arguments, body, ..
+        } = function
+        else {
+            bail!("cannot compile non-function value {function:?}");
+        };
+
+        // parameters share a scope with the top level of the body
+        let mut compiler = Self::new(true);
+        compiler.scopes.push(vec![]);
+        for argument in arguments {
+            compiler.local(*argument)?;
+        }
+        compiler.chunk.params = arguments.len();
+        compiler.block(body)?;
+        Ok(compiler.finish())
+    }
+
+    /// Ends the chunk with an implicit undefined return.
+    fn finish(mut self) -> Chunk {
+        self.emit(Op::Undefined);
+        self.emit(Op::Return);
+        self.chunk
+    }
+
+    /// Appends an instruction, returning its position.
+    fn emit(&mut self, op: Op) -> usize {
+        self.chunk.code.push(op);
+        self.chunk.spans.push(self.span.clone());
+        self.chunk.code.len() - 1
+    }
+
+    /// Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let target = self.chunk.code.len();
+        match &mut self.chunk.code[at] {
+            Op::Jump(to)
+            | Op::JumpIfFalse(to)
+            | Op::Next(_, to)
+            | Op::Count(_, to)
+            | Op::Match(_, _, to)
+            | Op::Catch(to)
+            | Op::Finally(to) => *to = target,
+            op => unreachable!("cannot patch non-jump instruction {op:?}"),
+        }
+    }
+
+    /// Adds a constant to the pool, and pushes it.
+    fn constant(&mut self, value: Variable) {
+        self.chunk.constants.push(value);
+        self.emit(Op::Constant(self.chunk.constants.len() - 1));
+    }
+
+    /// Declares a local in the innermost scope, returning its slot.
+    fn local(&mut self, sym: usize) -> Result<usize> {
+        let slot = self.chunk.locals;
+        let scope = self
+            .scopes
+            .last_mut()
+            .context("no scope to declare local in")?;
+        if scope.iter().any(|(local, _)| *local == sym) {
+            bail!("variable '{}' already exists", resolve!(sym));
+        }
+        scope.push((sym, slot));
+        self.chunk.locals += 1;
+        Ok(slot)
+    }
+
+    /// Reserves a local slot that no name refers to, returning it.
+    fn hidden(&mut self) -> usize {
+        self.chunk.locals += 1;
+        self.chunk.locals - 1
+    }
+
+    /// Compiles the body of a loop, which continues at `start`. Returns the break jumps to patch
+    /// once the end of the loop is known.
+    fn loop_body(
+        &mut self,
+        label: Option<usize>,
+        start: usize,
+        body: &Rc<ASTNode>,
+        scoped: bool,
+    ) -> Result<Vec<usize>> {
+        self.loops.push(LoopTarget {
+            label,
+            start,
+            breaks: vec![],
+            tries: self.tries.len(),
+        });
+        let result = match scoped {
+            true => self.scoped_block(body),
+            false => self.block(body),
+        };
+        let target = self.loops.pop().expect("loop was pushed");
+        result.map(|_| target.breaks)
+    }
+
+    /// Finds the index of the loop a break or continue named `verb` applies to. When there is
+    /// none it may leave to, the code that fails once control flow reaches it is emitted instead,
+    /// and `None` is returned.
+    fn loop_target(&mut self, label: Option<usize>, verb: &str) -> Result<Option<usize>> {
+        let found = self
+            .loops
+            .iter()
+            .rposition(|target| label.is_none() || target.label == label);
+        match (found, self.expression) {
+            (Some(at), Some((inner, _))) if at < inner => {
+                self.escape("control flow cannot leave an expression")?;
+                Ok(None)
+            }
+            (Some(at), _) => Ok(Some(at)),
+            (None, _) => {
+                self.escape(&format!(
+                    "cannot {verb} outside of {}",
+                    describe_loop(label)
+                ))?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Emits the code for control flow that has nowhere to go, which fails with `message` after
+    /// leaving the try statements it would leave on its way out, as it does with the AST
+    /// backend.
+    fn escape(&mut self, message: &str) -> Result<()> {
+        let (_, tries) = self.expression.unwrap_or_default();
+        self.leave(tries)?;
+        self.emit(Op::Fail(message.into()));
+        Ok(())
+    }
+
+    /// Emits the code that leaves every try statement but the outermost `depth`, before control
+    /// flow jumps out of them: each handler is removed, and each finally body runs, innermost
+    /// first.
+    fn leave(&mut self, depth: usize) -> Result<()> {
+        let tries = self.tries.clone();
+        let result = (|| {
+            while self.tries.len() > depth {
+                let finally = self.tries.pop().expect("more tries than depth");
+                self.emit(Op::EndTry);
+                if let Some(finally) = finally {
+                    self.scoped_block(&finally)?;
+                }
+            }
+            Ok(())
+        })();
+        self.tries = tries;
+        result
+    }
+
+    /// Declares locals for `names`, popping their values from the stack. The value of the last
+    /// name is on top.
+    fn bind(&mut self, names: &[usize]) -> Result<()> {
+        let slots = names
+            .iter()
+            .map(|name| self.local(*name))
+            .collect::<Result<Vec<_>>>()?;
+        for slot in slots.into_iter().rev() {
+            self.emit(Op::SetLocal(slot));
+        }
+        Ok(())
+    }
+
+    /// Compiles the body of a conditional expression or match arm in a new scope, pushing the
+    /// value of its trailing expression, or undefined if it doesn't end with one. `names` are
+    /// bound in the scope first, as with `bind`.
+    fn branch(&mut self, body: &Rc<ASTNode>, names: &[usize]) -> Result<()> {
+        let outer = self
+            .expression
+            .replace((self.loops.len(), self.tries.len()));
+        self.scopes.push(vec![]);
+        let result = (|| {
+            self.bind(names)?;
+            match &**body {
+                ASTNode::Block { statements, .. } => match statements.split_last() {
+                    Some((last, rest)) if last.is_expression() => {
+                        rest.iter()
+                            .try_for_each(|statement| self.statement(statement))?;
+                        self.expr(last)?;
+                    }
+                    _ => {
+                        self.block(body)?;
+                        self.emit(Op::Undefined);
+                    }
+                },
+                _ => self.expr(body)?,
+            }
+            Ok(())
+        })();
+        self.scopes.pop();
+        self.expression = outer;
+        result
+    }
+
+    /// Finds the slot of the innermost local named `sym`.
+    fn resolve(&self, sym: usize) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(local, _)| *local == sym)
+            .map(|(_, slot)| *slot)
+    }
+
+    /// Emits the declaration of `sym` for the value on top of the stack.
+    fn declare(&mut self, sym: usize) -> Result<()> {
+        if self.scopes.is_empty() {
+            self.emit(Op::DeclareName(sym));
+        } else {
+            let slot = self.local(sym)?;
+            self.emit(Op::SetLocal(slot));
+        }
+        Ok(())
+    }
+
+    /// Compiles the statements of a block in the current scope.
+    fn block(&mut self, node: &Rc<ASTNode>) -> Result<()> {
+        match &**node {
+            ASTNode::Block { statements, .. } => statements
+                .iter()
+                .try_for_each(|statement| self.statement(statement)),
+            _ => self.statement(node),
+        }
+    }
+
+    /// Compiles the statements of a block in a new scope.
+    fn scoped_block(&mut self, node: &Rc<ASTNode>) -> Result<()> {
+        self.scopes.push(vec![]);
+        let result = self.block(node);
+        self.scopes.pop();
+        result
+    }
+
+    /// Runs `f` with the location of `node` as the current location, if it is known.
+    fn spanned<F>(&mut self, node: &Rc<ASTNode>, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut Self, &Rc<ASTNode>) -> Result<()>,
+    {
+        let previous = self.span.clone();
+        if node.span().is_known() {
+            self.span = node.span().clone();
+        }
+        let result = f(self, node).at(&self.span);
+        self.span = previous;
+        result
+    }
+
+    /// Compiles a statement, which leaves the stack as it found it.
+    fn statement(&mut self, node: &Rc<ASTNode>) -> Result<()> {
+        self.spanned(node, Self::compile_statement)
+    }
+
+    /// Compiles an expression, which pushes exactly one value.
+    fn expr(&mut self, node: &Rc<ASTNode>) -> Result<()> {
+        self.spanned(node, Self::compile_expr)
+    }
+
+    fn compile_statement(&mut self, node: &Rc<ASTNode>) -> Result<()> {
+        match &**node {
+            ASTNode::Declare { target, value, .. } => {
+                if let ASTNode::Literal {
+                    token: Token::Identifier(sym),
+                    ..
+                } = &**target
+                {
+                    self.expr(value)?;
+                    return self.declare(*sym);
+                }
+                match self.path(target)? {
+                    Some((Root::Name(sym), keys)) => {
+                        self.expr(value)?;
+                        self.emit(Op::DeclarePath(sym, keys));
+                    }
+                    _ => bail!("invalid declaration target {target:?}"),
+                }
+            }
+            ASTNode::Assign { target, value, .. } => {
+                if let ASTNode::Literal {
+                    token: Token::Identifier(sym),
+                    ..
+                } = &**target
+                {
+                    self.expr(value)?;
+                    match self.resolve(*sym) {
+                        Some(slot) => self.emit(Op::SetLocal(slot)),
+                        None => self.emit(Op::AssignName(*sym)),
+                    };
+                    return Ok(());
+                }
+                let Some((root, keys)) = self.path(target)? else {
+                    bail!("invalid assignment target {target:?}");
+                };
+                self.expr(value)?;
+                self.emit(Op::AssignPath(root, keys));
+            }
+            ASTNode::Function { id: Some(id), .. } => {
+                let IDKind::Symbol(sym) = id.get_kind() else {
+                    bail!("invalid function name {id:?}");
+                };
+                self.closure(node);
+                self.declare(sym)?;
+            }
+            ASTNode::Struct { id, .. } => {
+                let IDKind::Symbol(sym) = id.get_kind() else {
+                    bail!("invalid structure name {id:?}");
+                };
+                let fields = node.fields();
+                for field in &fields {
+                    self.expr(field)?;
+                }
+                self.emit(Op::Struct(node.clone(), fields.len()));
+                self.declare(sym)?;
+            }
+            ASTNode::Conditional {
+                condition,
+                if_body,
+                else_body,
+                ..
+            } => {
+                self.expr(condition)?;
+                let to_else = self.emit(Op::JumpIfFalse(0));
+                self.scoped_block(if_body)?;
+                let to_end = self.emit(Op::Jump(0));
+                self.patch(to_else);
+                self.scoped_block(else_body)?;
+                self.patch(to_end);
+            }
+            ASTNode::Loop {
+                label,
+                condition,
+                body,
+                ..
+            } => {
+                let start = self.chunk.code.len();
+                self.expr(condition)?;
+                let to_end = self.emit(Op::JumpIfFalse(0));
+                let breaks = self.loop_body(*label, start, body, true)?;
+                self.emit(Op::Jump(start));
+                self.patch(to_end);
+                for at in breaks {
+                    self.patch(at);
+                }
+            }
+            ASTNode::For {
+                label,
+                index,
+                item,
+                iterable,
+                body,
+                ..
+            } => {
+                // keep entries & position in hidden slots, next to each other. ranges keep their
+                // bounds instead, around the position
+                let entries = self.hidden();
+                let position = self.hidden();
+                let range = match &**iterable {
+                    ASTNode::Range { start, end, .. } => {
+                        let end_slot = self.hidden();
+                        self.expr(start)?;
+                        self.expr(end)?;
+                        self.emit(Op::Bounds);
+                        self.emit(Op::SetLocal(end_slot));
+                        self.emit(Op::SetLocal(entries));
+                        true
+                    }
+                    _ => {
+                        self.expr(iterable)?;
+                        self.emit(Op::Entries(index.is_some()));
+                        self.emit(Op::SetLocal(entries));
+                        false
+                    }
+                };
+                self.constant(Variable::Owned(Value::Int(0)));
+                self.emit(Op::SetLocal(position));
+
+                // bind each entry, sharing a scope with the top level of the body
+                self.scopes.push(vec![]);
+                let start = self.chunk.code.len();
+                let to_end = match range {
+                    true => self.emit(Op::Count(entries, 0)),
+                    false => self.emit(Op::Next(entries, 0)),
+                };
+                let result = (|| {
+                    let item = self.local(*item)?;
+                    self.emit(Op::SetLocal(item));
+                    match index {
+                        Some(index) => {
+                            let index = self.local(*index)?;
+                            self.emit(Op::SetLocal(index));
+                        }
+                        None => {
+                            self.emit(Op::Pop);
+                        }
+                    }
+                    self.loop_body(*label, start, body, false)
+                })();
+                self.scopes.pop();
+                let breaks = result?;
+                self.emit(Op::Jump(start));
+                self.patch(to_end);
+                for at in breaks {
+                    self.patch(at);
+                }
+
+                // release entries once the loop is done
+                self.emit(Op::Undefined);
+                self.emit(Op::SetLocal(entries));
+            }
+            ASTNode::Break { label, .. } => {
+                if let Some(target) = self.loop_target(*label, "break")? {
+                    self.leave(self.loops[target].tries)?;
+                    let at = self.chunk.code.len();
+                    self.loops[target].breaks.push(at);
+                    self.emit(Op::Jump(0));
+                }
+            }
+            ASTNode::Continue { label, .. } => {
+                if let Some(target) = self.loop_target(*label, "continue")? {
+                    let LoopTarget { start, tries, .. } = self.loops[target];
+                    self.leave(tries)?;
+                    self.emit(Op::Jump(start));
+                }
+            }
+            ASTNode::Return { value, .. } => {
+                if !self.function {
+                    return self.escape("cannot return as base scope");
+                }
+                if self.expression.is_some() {
+                    return self.escape("control flow cannot leave an expression");
+                }
+                self.expr(value)?;
+                self.leave(0)?;
+                self.emit(Op::Return);
+            }
+            ASTNode::Throw { value, .. } => {
+                self.expr(value)?;
+                self.emit(Op::Throw);
+            }
+            ASTNode::Try {
+                body,
+                name,
+                handler,
+                finally,
+                ..
+            } => self.compile_try(body, *name, handler.as_ref(), finally.as_ref())?,
+            ASTNode::Module {
+                path, alias, body, ..
+            } => {
+                let module = Module {
+                    alias: *alias,
+                    path: path.clone(),
+                    body: Self::program(body)?,
+                };
+                self.emit(Op::Module(module.into()));
+            }
+            ASTNode::UnaryOp {
+                target,
+                op: op @ (Token::Increment | Token::Decrement),
+                ..
+            } => {
+                let ASTNode::Literal {
+                    token: Token::Identifier(sym),
+                    ..
+                } = &**target
+                else {
+                    bail!("invalid increment/decrement target: {target:?}");
+                };
+                match self.resolve(*sym) {
+                    Some(slot) => {
+                        self.emit(Op::GetLocal(slot));
+                        self.emit(Op::Step(op.clone()));
+                        self.emit(Op::SetLocal(slot));
+                    }
+                    None => {
+                        self.emit(Op::GetName(*sym));
+                        self.emit(Op::Step(op.clone()));
+                        self.emit(Op::AssignName(*sym));
+                    }
+                }
+            }
+            ASTNode::Match { value, arms, .. } => self.compile_match(value, arms, false)?,
+            ASTNode::Block { .. } => self.scoped_block(node)?,
+            _ => {
+                // expression statements discard their value
+                self.expr(node)?;
+                self.emit(Op::Pop);
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_expr(&mut self, node: &Rc<ASTNode>) -> Result<()> {
+        match &**node {
+            ASTNode::Literal {
+                token: Token::Identifier(sym),
+                ..
+            } => {
+                match self.resolve(*sym) {
+                    Some(slot) => self.emit(Op::GetLocal(slot)),
+                    None => self.emit(Op::GetName(*sym)),
+                };
+            }
+            ASTNode::Literal { token, .. } => {
+                self.constant(Variable::Owned(Value::try_from(token)?));
+            }
+            ASTNode::List { items, .. } => {
+                for item in items {
+                    self.expr(item)?;
+                }
+                self.emit(Op::List(items.len()));
+            }
+            ASTNode::Dict { entries, .. } => {
+                for (key, value) in entries {
+                    self.expr(key)?;
+                    self.expr(value)?;
+                }
+                self.emit(Op::Dict(entries.len()));
+            }
+            ASTNode::Op { lhs, op, rhs, .. } => {
+                self.expr(lhs)?;
+                self.expr(rhs)?;
+                self.emit(Op::Binary(op.clone()));
+            }
+            ASTNode::UnaryOp {
+                op: Token::Increment | Token::Decrement,
+                ..
+            } => {
+                self.compile_statement(node)?;
+                self.emit(Op::Undefined);
+            }
+            ASTNode::UnaryOp { target, op, .. } => {
+                self.expr(target)?;
+                self.emit(Op::Unary(op.clone()));
+            }
+            ASTNode::Index { target, index, .. } => {
+                if let Some((root, keys)) = self.path(node)? {
+                    self.emit(Op::GetPath(root, keys));
+                } else {
+                    self.expr(target)?;
+                    self.expr(index)?;
+                    self.emit(Op::Index);
+                }
+            }
+            ASTNode::Deref { parent, child, .. } => {
+                if let Some((root, keys)) = self.path(node)? {
+                    self.emit(Op::GetPath(root, keys));
+                } else {
+                    self.expr(parent)?;
+                    self.emit(Op::GetMember(member(child)?));
+                }
+            }
+            ASTNode::FunctionCall {
+                target, arguments, ..
+            } => {
+                self.callee(target)?;
+
+                // first arguments naming a variable are borrowed, in case the callee changes them
+                let borrowed = match arguments.first() {
+                    Some(first) => self.path(first)?,
+                    None => None,
+                };
+                if let Some((root, keys)) = &borrowed {
+                    self.emit(Op::Borrow(*root, keys.clone()));
+                }
+                for argument in arguments.iter().skip(borrowed.is_some().into()) {
+                    self.expr(argument)?;
+                }
+                let module = match &**target {
+                    ASTNode::Deref { parent, .. } => parent.path().map(Into::into),
+                    _ => None,
+                };
+                self.emit(Op::Call(arguments.len(), module, borrowed));
+            }
+            ASTNode::Function { id: None, .. } => self.closure(node),
+            ASTNode::Conditional {
+                condition,
+                if_body,
+                else_body,
+                ..
+            } => {
+                self.expr(condition)?;
+                let to_else = self.emit(Op::JumpIfFalse(0));
+                self.branch(if_body, &[])?;
+                let to_end = self.emit(Op::Jump(0));
+                self.patch(to_else);
+                self.branch(else_body, &[])?;
+                self.patch(to_end);
+            }
+            ASTNode::Match { value, arms, .. } => self.compile_match(value, arms, true)?,
+            ASTNode::Range { .. } => bail!("ranges can only be looped over"),
+            _ => {
+                // statements evaluate to undefined
+                self.compile_statement(node)?;
+                self.emit(Op::Undefined);
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles a match, keeping its value in a hidden slot while arms test it. Matches used as
+    /// expressions push the value of the matched arm.
+    fn compile_match(
+        &mut self,
+        value: &Rc<ASTNode>,
+        arms: &[(Rc<Pattern>, Rc<ASTNode>)],
+        expression: bool,
+    ) -> Result<()> {
+        self.expr(value)?;
+        let slot = self.hidden();
+        self.emit(Op::SetLocal(slot));
+        let mut ends = vec![];
+        for (pattern, body) in arms {
+            let next = self.emit(Op::Match(pattern.clone(), slot, 0));
+            let names = pattern.bindings();
+            if expression {
+                self.branch(body, &names)?;
+            } else {
+                self.scopes.push(vec![]);
+                let result = self.bind(&names).and_then(|_| self.block(body));
+                self.scopes.pop();
+                result?;
+            }
+            ends.push(self.emit(Op::Jump(0)));
+            self.patch(next);
+        }
+        self.emit(Op::NoMatch(slot));
+        for at in ends {
+            self.patch(at);
+        }
+
+        // release the value once the match is done
+        self.emit(Op::Undefined);
+        self.emit(Op::SetLocal(slot));
+        Ok(())
+    }
+
+    /// Compiles a try statement. The finally body is compiled twice: once for when the rest
+    /// completes, and once for when it fails, which raises the error again afterwards.
+    fn compile_try(
+        &mut self,
+        body: &Rc<ASTNode>,
+        name: Option<usize>,
+        handler: Option<&Rc<ASTNode>>,
+        finally: Option<&Rc<ASTNode>>,
+    ) -> Result<()> {
+        let install = self.emit(match handler {
+            Some(_) => Op::Catch(0),
+            None => Op::Finally(0),
+        });
+        self.tries.push(finally.cloned());
+        let result = self.scoped_block(body);
+        self.tries.pop();
+        result?;
+        self.emit(Op::EndTry);
+
+        // the handler runs with the caught error on the stack, protected by the finally body
+        let mut unwind = install;
+        if let Some(handler) = handler {
+            let to_finally = self.emit(Op::Jump(0));
+            self.patch(install);
+            if finally.is_some() {
+                unwind = self.emit(Op::Finally(0));
+                self.tries.push(finally.cloned());
+            }
+            self.scopes.push(vec![]);
+            let result = match name {
+                Some(name) => self.bind(&[name]),
+                None => {
+                    self.emit(Op::Pop);
+                    Ok(())
+                }
+            }
+            .and_then(|_| self.block(handler));
+            self.scopes.pop();
+            if finally.is_some() {
+                self.tries.pop();
+                self.emit(Op::EndTry);
+            }
+            result?;
+            self.patch(to_finally);
+        }
+
+        if let Some(finally) = finally {
+            self.scoped_block(finally)?;
+            let to_end = self.emit(Op::Jump(0));
+            self.patch(unwind);
+            self.scoped_block(finally)?;
+            self.emit(Op::Resume);
+            self.patch(to_end);
+        }
+        Ok(())
+    }
+
+    /// Pushes a function created from `node`, capturing every local visible here.
+    fn closure(&mut self, node: &Rc<ASTNode>) {
+        let locals = self.scopes.iter().flatten().copied().collect();
+        self.emit(Op::Closure(node.clone(), locals));
+    }
+
+    /// Pushes the receiver & callee of a call.
+    fn callee(&mut self, target: &Rc<ASTNode>) -> Result<()> {
+        match &**target {
+            ASTNode::Deref { parent, child, .. } => {
+                if let Some((root, keys)) = self.path(target)? {
+                    self.emit(Op::GetMethod(root, keys));
+                } else {
+                    // for complex derefs (like `parent().child`), evaluate the parent in-place
+                    self.expr(parent)?;
+                    self.emit(Op::Method(member(child)?));
+                }
+            }
+            _ => {
+                self.emit(Op::Undefined);
+                self.expr(target)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles a chain of indices & derefs rooted at an identifier, pushing its index values.
+    /// Returns `None` without emitting anything for chains rooted at other expressions.
+    fn path(&mut self, node: &Rc<ASTNode>) -> Result<Option<(Root, Rc<[Key]>)>> {
+        let Some((sym, steps)) = flatten(node) else {
+            return Ok(None);
+        };
+        for index in steps.iter().filter_map(|(_, index)| index.as_ref()) {
+            self.expr(index)?;
+        }
+        let root = match self.resolve(sym) {
+            Some(slot) => Root::Local(slot),
+            None => Root::Name(sym),
+        };
+        Ok(Some((
+            root,
+            steps.into_iter().map(|(key, _)| key).collect(),
+        )))
+    }
+}
+
+/// Splits a chain of indices & derefs into its root identifier and steps.
+fn flatten(node: &ASTNode) -> Option<(usize, Vec<PathStep>)> {
+    match node {
+        ASTNode::Literal {
+            token: Token::Identifier(sym),
+            ..
+        } => Some((*sym, vec![])),
+        ASTNode::Index { target, index, .. } => {
+            let (root, mut steps) = flatten(target)?;
+            steps.push((Key::Index, Some(index.clone())));
+            Some((root, steps))
+        }
+        ASTNode::Deref { parent, child, .. } => {
+            let ASTNode::Literal {
+                token: Token::Identifier(child),
+                ..
+            } = &**child
+            else {
+                return None;
+            };
+            let (root, mut steps) = flatten(parent)?;
+            steps.push((Key::Member(*child), None));
+            Some((root, steps))
+        }
+        _ => None,
+    }
+}
+
+/// Gets the symbol of the child of a deref.
+fn member(child: &ASTNode) -> Result<usize> {
+    match child {
+        ASTNode::Literal {
+            token: Token::Identifier(sym),
+            ..
+        } => Ok(*sym),
+        _ => bail!("deref child must be an identifier"),
+    }
+}
@@ -0,0 +1,699 @@
+//! The bytecode backend. Syntax trees are compiled into chunks of instructions, which run on a
+//! stack-based virtual machine sharing its memory & builtins with the tree-walking backend.
+//!
+//! Function bodies are compiled the first time they are called, and cached for later calls.
+
+mod chunk;
+mod compiler;
+
+use super::{
+    execute_function::{check_arity, mutate_copy},
+    *,
+};
+use crate::lexer::Span;
+use chunk::Key;
+pub(crate) use chunk::*;
+use compiler::Compiler;
+
+/// The local slots of a running chunk. They're only borrowed while an instruction uses them, so
+/// that memory usage can be measured in between.
+pub(crate) type Slots = RefCell<Vec<Variable>>;
+
+/// A location values can be read from & written to while following a path.
+enum Place {
+    /// A local slot of the running chunk.
+    Slot(usize),
+    /// A module, or other memory table.
+    Module(Rc<RefCell<SVTable>>),
+    /// A variable, such as a list item or instance member.
+    Cell(Rc<RefCell<Variable>>),
+}
+
+/// A step of a path being followed, with its index value converted into a key.
+enum At {
+    /// A member of an instance or module.
+    Member(usize),
+    /// An item of a list or dictionary, or a character of a string.
+    Index(value::Key),
+}
+
+/// A handler installed by a try statement.
+struct Handler {
+    /// Where execution continues once an error is caught.
+    to: usize,
+    /// Height of the stack when the handler was installed.
+    depth: usize,
+    /// Number of errors kept aside when the handler was installed.
+    kept: usize,
+    /// True if the error is pushed as a value, rather than kept aside.
+    catches: bool,
+}
+
+/// The handlers installed by a running chunk, innermost last.
+#[derive(Default)]
+struct Handlers {
+    installed: Vec<Handler>,
+    /// Errors kept aside while finally bodies run, along with the values they threw.
+    kept: Vec<(anyhow::Error, Option<Value>)>,
+}
+
+/// Returns an undefined value.
+fn undefined() -> Variable {
+    Variable::Owned(Value::Undefined)
+}
+
+/// Returns the truthiness of a value. Types are always truthy.
+fn is_truthy(value: &Variable) -> bool {
+    match value {
+        Variable::Owned(value) => value.is_truthy(),
+        Variable::Type(_) => true,
+    }
+}
+
+/// Gets the value of a variable, for use as an operand.
+fn operand(value: &Variable) -> Result<&Value> {
+    match value {
+        Variable::Owned(value) => Ok(value),
+        Variable::Type(_) => bail!("cannot use {value:?} as a value"),
+    }
+}
+
+/// Converts an index value into a key.
+fn as_key(value: &Variable) -> Result<value::Key> {
+    operand(value)?.as_key()
+}
+
+/// Gets the variable at a step of a list, dictionary, or instance.
+fn child(value: &Variable, at: &At) -> Result<Rc<RefCell<Variable>>> {
+    match (at, value) {
+        (At::Member(sym), Variable::Owned(Value::Instance { svt, .. })) => {
+            svt.borrow().get_ref(*sym)
+        }
+        (At::Index(key), Variable::Owned(Value::List(_) | Value::Dict(_))) => value.get_ref(key),
+        _ => bail!("cannot access {} of {value:?}", describe(at)),
+    }
+}
+
+/// Reads the value at a step of a list, dictionary, string, or instance.
+fn read(value: &Variable, at: &At) -> Result<Variable> {
+    match (at, value) {
+        (At::Member(sym), Variable::Owned(Value::Instance { svt, .. })) => {
+            svt.borrow().get_owned(*sym)
+        }
+        (At::Member(_), _) => bail!("cannot dereference member of {value:#?}"),
+        (At::Index(key), Variable::Owned(Value::List(_) | Value::Dict(_))) => value.get_owned(key),
+        (At::Index(key), Variable::Owned(value)) => Ok(Variable::Owned(ops::index(value, key)?)),
+        (At::Index(_), _) => bail!("expected list as index target"),
+    }
+}
+
+/// Assigns the value at a step of a list, dictionary, or instance.
+fn write(target: &mut Variable, at: At, value: Variable) -> Result<()> {
+    match (at, &*target) {
+        (At::Member(sym), Variable::Owned(Value::Instance { svt, .. })) => {
+            svt.borrow_mut().assign(sym, value, 0)
+        }
+        (At::Index(key), Variable::Owned(Value::List(_) | Value::Dict(_))) => {
+            target.assign(key, value)
+        }
+        (at, _) => bail!("cannot assign {} of {target:?}", describe(&at)),
+    }
+}
+
+/// Gets the symbol of a step into a module, which can't be indexed.
+fn member(at: &At) -> Result<usize> {
+    match at {
+        At::Member(sym) => Ok(*sym),
+        At::Index(_) => bail!("cannot access {} of a module", describe(at)),
+    }
+}
+
+/// Describes a step of a path for errors.
+fn describe(at: &At) -> String {
+    match at {
+        At::Member(sym) => format!("member '{}'", resolve!(*sym)),
+        At::Index(key) => format!("index {:?}", Value::from(key)),
+    }
+}
+
+impl<Out: Write, In: Read> Interpreter<Out, In> {
+    /// Compiles a program, and runs it on the virtual machine. Returns the value of its trailing
+    /// expression, if it has one.
+    pub(crate) fn execute_bytecode(&mut self, ast: &Rc<ASTNode>) -> Result<Value> {
+        let chunk = Compiler::program(ast).context("failed to compile program")?;
+        Ok(self.run(&chunk, vec![])?.into())
+    }
+
+    /// Calls a function value on the virtual machine, for external functions calling back into
+    /// Lily.
+    pub(crate) fn call_bytecode(&mut self, function: &Value, args: &[Value]) -> Result<Value> {
+        let args = args.iter().cloned().map(Variable::Owned).collect();
+        let function = Variable::Owned(function.clone());
+        Ok(self
+            .call(undefined(), function, args, Span::default(), None)?
+            .into())
+    }
+
+    /// Gets the compiled body of a function node, compiling it if it hasn't been yet.
+    fn chunk(&mut self, function: &Rc<ASTNode>) -> Result<Rc<Chunk>> {
+        let key = Rc::as_ptr(function);
+        if let Some((_, chunk)) = self.chunks.get(&key) {
+            return Ok(chunk.clone());
+        }
+        let chunk = Rc::new(Compiler::function(function).context("failed to compile function")?);
+        self.chunks.insert(key, (function.clone(), chunk.clone()));
+        Ok(chunk)
+    }
+
+    /// Runs a chunk with `args` in its first local slots, returning its result.
+    fn run(&mut self, chunk: &Chunk, mut args: Vec<Variable>) -> Result<Variable> {
+        args.resize_with(chunk.locals, undefined);
+        let slots = Rc::new(RefCell::new(args));
+        self.locals.push(slots.clone());
+        let result = self.dispatch(chunk, &slots);
+        self.locals.pop();
+        result
+    }
+
+    /// Executes the instructions of a chunk until it returns.
+    fn dispatch(&mut self, chunk: &Chunk, slots: &Slots) -> Result<Variable> {
+        let mut stack = Vec::with_capacity(8);
+        let mut handlers = Handlers::default();
+        let mut pc = 0;
+        loop {
+            let at = pc;
+            pc += 1;
+            let op = &chunk.code[at];
+            match self
+                .tick()
+                .and_then(|_| self.step(chunk, op, &mut pc, &mut stack, slots, &mut handlers))
+                .at(&chunk.spans[at])
+            {
+                Ok(None) => (),
+                Ok(Some(value)) => return Ok(value),
+                Err(e) => {
+                    // errors are caught by the innermost handler, if there is one and they can
+                    // be caught at all
+                    if !ErrorKind::of(&e).is_catchable() {
+                        return Err(e);
+                    }
+                    let Some(handler) = handlers.installed.pop() else {
+                        return Err(e);
+                    };
+                    stack.truncate(handler.depth);
+                    handlers.kept.truncate(handler.kept);
+                    match handler.catches {
+                        true => stack.push(Variable::Owned(self.catch(&e))),
+                        false => handlers.kept.push((e, self.thrown.take())),
+                    }
+                    pc = handler.to;
+                }
+            }
+        }
+    }
+
+    /// Executes a single instruction. Returns `Some` when the chunk returns.
+    #[inline]
+    fn step(
+        &mut self,
+        chunk: &Chunk,
+        op: &Op,
+        pc: &mut usize,
+        stack: &mut Vec<Variable>,
+        slots: &Slots,
+        handlers: &mut Handlers,
+    ) -> Result<Option<Variable>> {
+        /// Pops the top value. The compiler never emits code that underflows the stack.
+        macro_rules! pop {
+            () => {
+                stack.pop().expect("stack underflow")
+            };
+        }
+
+        match op {
+            Op::Constant(idx) => stack.push(chunk.constants[*idx].clone()),
+            Op::Undefined => stack.push(undefined()),
+            Op::Pop => {
+                pop!();
+            }
+            Op::GetLocal(slot) => stack.push(slots.borrow()[*slot].clone()),
+            Op::SetLocal(slot) => {
+                let value = pop!();
+                self.charge(&value)?;
+                slots.borrow_mut()[*slot] = value;
+            }
+            Op::GetName(sym) => stack.push(self.get(&ID::new_sym(*sym))?),
+            Op::AssignName(sym) => self.assign(&ID::new_sym(*sym), pop!())?,
+            Op::DeclareName(sym) => self.declare(&ID::new_sym(*sym), pop!())?,
+            Op::GetPath(root, keys) => {
+                let value = self.fetch(*root, keys, stack, &slots.borrow())?;
+                stack.push(value);
+            }
+            Op::Borrow(root, keys) => {
+                let indices = keys.iter().filter(|key| **key == Key::Index).count();
+                let indices = stack.len() - indices;
+                match &stack[indices - 1] {
+                    Variable::Owned(Value::NativeMut(_)) => {
+                        let indices = stack.split_off(indices).into_iter().map(Into::into);
+                        stack.push(Variable::Owned(Value::List(indices.collect())));
+                    }
+                    _ => {
+                        let value = self.fetch(*root, keys, stack, &slots.borrow())?;
+                        stack.push(value);
+                    }
+                }
+            }
+            Op::AssignPath(root, keys) => {
+                let value = pop!();
+                self.charge(&value)?;
+                let (place, at) = self.follow(*root, keys, stack, &slots.borrow())?;
+                match place {
+                    Place::Slot(slot) => write(&mut slots.borrow_mut()[slot], at, value)?,
+                    Place::Module(module) => {
+                        module
+                            .borrow_mut()
+                            .assign(member(&at)?, value, self.scope_id)?
+                    }
+                    Place::Cell(cell) => write(&mut cell.borrow_mut(), at, value)?,
+                }
+            }
+            Op::DeclarePath(sym, keys) => {
+                let value = pop!();
+                self.charge(&value)?;
+                let (place, at) = self.follow(Root::Name(*sym), keys, stack, &slots.borrow())?;
+                match (place, at) {
+                    (Place::Module(module), at) => {
+                        module
+                            .borrow_mut()
+                            .declare(member(&at)?, value, self.scope_id)?
+                    }
+                    (Place::Cell(cell), At::Index(key)) => cell.borrow_mut().declare(key, value)?,
+                    (Place::Cell(cell), at) => {
+                        bail!("cannot declare {} of {:?}", describe(&at), cell.borrow())
+                    }
+                    (Place::Slot(_), _) => unreachable!("named paths never start at a slot"),
+                }
+            }
+            Op::GetMember(sym) => {
+                let value = pop!();
+                stack.push(read(&value, &At::Member(*sym))?);
+            }
+            Op::Index => {
+                let key = as_key(&pop!())?;
+                let target = pop!();
+                stack.push(read(&target, &At::Index(key))?);
+            }
+            Op::List(len) => {
+                let items = stack.split_off(stack.len() - len);
+                let items = items.into_iter().map(Into::into).collect();
+                stack.push(Variable::Owned(self.allocate(Value::List(items))?));
+            }
+            Op::Dict(len) => {
+                let mut dict = Dict::default();
+                let entries = stack.split_off(stack.len() - len * 2);
+                for entry in entries.chunks_exact(2) {
+                    dict.insert(as_key(&entry[0])?, entry[1].clone());
+                }
+                let dict = Value::Dict(RefCell::new(dict).into());
+                stack.push(Variable::Owned(self.allocate(dict)?));
+            }
+            Op::Struct(node, fields) => {
+                let fields = stack
+                    .split_off(stack.len() - fields)
+                    .into_iter()
+                    .map(Value::from)
+                    .collect::<Vec<_>>();
+                stack.push(Variable::Type(Structure::new(node, fields)?.into()));
+            }
+            Op::Closure(node, locals) => {
+                // locals shadow the variables captured from the memory table
+                let mut captures = self.capture();
+                for &(sym, slot) in locals.iter() {
+                    captures.insert(sym, Rc::new(RefCell::new(slots.borrow()[slot].clone())));
+                }
+                stack.push(Variable::Owned(Value::Function {
+                    node: node.clone(),
+                    captures: captures.into(),
+                }));
+            }
+            Op::Binary(op) => {
+                let rhs = pop!();
+                let lhs = pop!();
+                let result = ops::binary(op, operand(&lhs)?, operand(&rhs)?)?;
+                stack.push(Variable::Owned(self.allocate(result)?));
+            }
+            Op::Unary(op) => {
+                let target = pop!();
+                stack.push(Variable::Owned(ops::unary(op, operand(&target)?)?));
+            }
+            Op::Step(op) => {
+                let value = match (op, pop!()) {
+                    (Token::Increment, Variable::Owned(n @ (Value::Int(_) | Value::Float(_)))) => {
+                        Variable::Owned(ops::binary(&Token::Add, &n, &Value::Int(1))?)
+                    }
+                    (Token::Decrement, Variable::Owned(n @ (Value::Int(_) | Value::Float(_)))) => {
+                        Variable::Owned(ops::binary(&Token::Sub, &n, &Value::Int(1))?)
+                    }
+                    (_, other) => other,
+                };
+                stack.push(value);
+            }
+            Op::Method(sym) => {
+                let receiver = pop!();
+                let method = read(&receiver, &At::Member(*sym))?;
+                stack.push(receiver);
+                stack.push(method);
+            }
+            Op::GetMethod(root, keys) => {
+                let (place, at) = self.follow(*root, keys, stack, &slots.borrow())?;
+
+                // methods of instances run with the instance as their memory context
+                let (receiver, method) = match place {
+                    Place::Slot(slot) => {
+                        let slots = slots.borrow();
+                        (slots[slot].clone(), read(&slots[slot], &at)?)
+                    }
+                    Place::Module(module) => {
+                        (undefined(), module.borrow().get_owned(member(&at)?)?)
+                    }
+                    Place::Cell(cell) => {
+                        let cell = cell.borrow();
+                        let receiver = match &*cell {
+                            Variable::Owned(Value::Instance { .. }) => cell.clone(),
+                            _ => undefined(),
+                        };
+                        (receiver, read(&cell, &at)?)
+                    }
+                };
+                stack.push(receiver);
+                stack.push(method);
+            }
+            Op::Entries(pairs) => {
+                let target = pop!();
+                let entries = ops::entries(operand(&target)?, *pairs)?
+                    .into_iter()
+                    .flat_map(|(key, item)| [key, item])
+                    .map(|value| Variable::Owned(value).into())
+                    .collect();
+                stack.push(Variable::Owned(Value::List(entries)));
+            }
+            Op::Next(slot, to) => {
+                let mut slots = slots.borrow_mut();
+                let (Variable::Owned(Value::List(entries)), Variable::Owned(Value::Int(position))) =
+                    (&slots[*slot], &slots[*slot + 1])
+                else {
+                    bail!("invalid loop state");
+                };
+                let at = *position as usize * 2;
+                if at >= entries.len() {
+                    *pc = *to;
+                } else {
+                    stack.push(entries[at].borrow().clone());
+                    stack.push(entries[at + 1].borrow().clone());
+                    slots[*slot + 1] = Variable::Owned(Value::Int(*position + 1));
+                }
+            }
+            Op::Bounds => {
+                let end = pop!();
+                let start = pop!();
+                let (start, end) = ops::bounds(operand(&start)?, operand(&end)?)?;
+                stack.push(Variable::Owned(Value::Int(start)));
+                stack.push(Variable::Owned(Value::Int(end)));
+            }
+            Op::Count(slot, to) => {
+                let mut slots = slots.borrow_mut();
+                let (
+                    Variable::Owned(Value::Int(start)),
+                    Variable::Owned(Value::Int(position)),
+                    Variable::Owned(Value::Int(end)),
+                ) = (&slots[*slot], &slots[*slot + 1], &slots[*slot + 2])
+                else {
+                    bail!("invalid loop state");
+                };
+                let (position, reached) = (*position, start + position);
+                if reached >= *end {
+                    *pc = *to;
+                } else {
+                    stack.push(Variable::Owned(Value::Int(position)));
+                    stack.push(Variable::Owned(Value::Int(reached)));
+                    slots[*slot + 1] = Variable::Owned(Value::Int(position + 1));
+                }
+            }
+            Op::Match(pattern, slot, to) => {
+                let mut bindings = vec![];
+                if ops::matches(pattern, operand(&slots.borrow()[*slot])?, &mut bindings)? {
+                    stack.extend(bindings.into_iter().map(Variable::Owned));
+                } else {
+                    *pc = *to;
+                }
+            }
+            Op::NoMatch(slot) => {
+                raise!(
+                    Match,
+                    "no match arm matches {:?}",
+                    operand(&slots.borrow()[*slot])?
+                )
+            }
+            Op::Jump(to) => *pc = *to,
+            Op::JumpIfFalse(to) => {
+                if !is_truthy(&pop!()) {
+                    *pc = *to;
+                }
+            }
+            Op::Call(argc, module, borrowed) => {
+                let mut args = stack.split_off(stack.len() - argc);
+                let callee = pop!();
+                let receiver = pop!();
+
+                // external functions changing a borrowed argument follow its path now, with its
+                // index keys in place of the argument
+                if let (Variable::Owned(Value::NativeMut(closure)), Some((root, keys))) =
+                    (&callee, borrowed)
+                {
+                    let Variable::Owned(Value::List(indices)) = args.remove(0) else {
+                        bail!("expected the index keys of a borrowed argument");
+                    };
+                    stack.extend(indices.iter().map(|index| index.borrow().clone()));
+                    let args = args.into_iter().map(Value::from).collect::<Vec<_>>();
+                    self.charge_args(&args)?;
+                    let result =
+                        self.mutate(*root, keys, stack, &mut slots.borrow_mut(), |value| {
+                            closure(value, &args)
+                        })?;
+                    stack.push(Variable::Owned(self.allocate(result)?));
+                    return Ok(None);
+                }
+
+                let site = chunk.spans[*pc - 1].clone();
+                let result = self.call(receiver, callee, args, site, module.as_deref())?;
+                stack.push(result);
+            }
+            Op::Return => return Ok(Some(pop!())),
+            Op::Throw => {
+                let value = pop!().into();
+                return Err(self.throw(value));
+            }
+            Op::Fail(message) => bail!("{message}"),
+            Op::Catch(to) | Op::Finally(to) => handlers.installed.push(Handler {
+                to: *to,
+                depth: stack.len(),
+                kept: handlers.kept.len(),
+                catches: matches!(op, Op::Catch(_)),
+            }),
+            Op::EndTry => {
+                handlers.installed.pop();
+            }
+            Op::Resume => {
+                let (err, thrown) = handlers.kept.pop().expect("an error was kept aside");
+                self.thrown = thrown;
+                return Err(err);
+            }
+            Op::Module(module) => {
+                let ctx = module.alias.map(|sym| {
+                    // if alias exists, create named module and execute in its context
+                    let context = self.context.clone().unwrap_or(self.memory.clone());
+                    context.borrow_mut().add_module(sym)
+                });
+                self.with_context(ctx, |interpreter| {
+                    interpreter.run(&module.body, vec![]).with_context(|| {
+                        format!(
+                            "failed to evaluate module '{}' ({:?})",
+                            module
+                                .alias
+                                .map_or("anonymous".to_string(), |sym| resolve!(sym)),
+                            module.path.clone().unwrap_or_default(),
+                        )
+                    })
+                })?;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads the value at a path, popping its index values from the stack.
+    fn fetch(
+        &self,
+        root: Root,
+        keys: &[Key],
+        stack: &mut Vec<Variable>,
+        slots: &[Variable],
+    ) -> Result<Variable> {
+        if let (Root::Local(slot), []) = (root, keys) {
+            return Ok(slots[slot].clone());
+        }
+        let (place, at) = self.follow(root, keys, stack, slots)?;
+        match place {
+            Place::Slot(slot) => read(&slots[slot], &at),
+            Place::Module(module) => module.borrow().get_owned(member(&at)?),
+            Place::Cell(cell) => read(&cell.borrow(), &at),
+        }
+    }
+
+    /// Changes the value at a path in place with `f`, popping its index values from the stack.
+    fn mutate(
+        &self,
+        root: Root,
+        keys: &[Key],
+        stack: &mut Vec<Variable>,
+        slots: &mut [Variable],
+        f: impl FnOnce(&mut Value) -> Result<Value>,
+    ) -> Result<Value> {
+        let cell = match (root, keys) {
+            (Root::Local(slot), []) => match &mut slots[slot] {
+                Variable::Owned(value) => return f(value),
+                other => raise!(Type, "cannot change {other:?}"),
+            },
+            _ => match self.follow(root, keys, stack, slots)? {
+                (Place::Slot(slot), at) => child(&slots[slot], &at)?,
+                (Place::Module(module), at) => module.borrow().get_ref(member(&at)?)?,
+                (Place::Cell(cell), at) => child(&cell.borrow(), &at)?,
+            },
+        };
+        let mut cell = cell.borrow_mut();
+        match &mut *cell {
+            Variable::Owned(value) => f(value),
+            other => raise!(Type, "cannot change {other:?}"),
+        }
+    }
+
+    /// Follows a path up to its last step, popping its index values from the stack. Returns the
+    /// place the last step applies to, along with that step.
+    fn follow(
+        &self,
+        root: Root,
+        keys: &[Key],
+        stack: &mut Vec<Variable>,
+        slots: &[Variable],
+    ) -> Result<(Place, At)> {
+        // resolve each step to its member symbol or index key
+        let indices = keys.iter().filter(|key| **key == Key::Index).count();
+        let mut indices = stack.split_off(stack.len() - indices).into_iter();
+        let mut steps = Vec::with_capacity(keys.len() + 1);
+        let mut place = match root {
+            Root::Local(slot) => Place::Slot(slot),
+            Root::Name(sym) => {
+                // named paths start by looking up their root in the current context
+                steps.push(At::Member(sym));
+                Place::Module(self.context.clone().unwrap_or(self.memory.clone()))
+            }
+        };
+        for key in keys {
+            steps.push(match key {
+                Key::Member(sym) => At::Member(*sym),
+                Key::Index => At::Index(as_key(&indices.next().unwrap())?), // safety: counted above
+            });
+        }
+
+        // step down to the last step
+        let last = steps.pop().context("empty path")?;
+        for at in steps {
+            place = match place {
+                Place::Slot(slot) => Place::Cell(child(&slots[slot], &at)?),
+                Place::Module(module) => {
+                    // modules first, then variables
+                    let module = module.borrow();
+                    let sym = member(&at)?;
+                    match module.get_module(sym) {
+                        Ok(module) => Place::Module(module),
+                        Err(_) => Place::Cell(module.get_ref(sym)?),
+                    }
+                }
+                Place::Cell(cell) => Place::Cell(child(&cell.borrow(), &at)?),
+            };
+        }
+        Ok((place, last))
+    }
+
+    /// Calls a function, external function, or structure constructor. Functions called with an
+    /// instance as their receiver run with the instance as their memory context. Calls are
+    /// recorded on the call stack as made at `site`, through `module` unless the receiver is an
+    /// instance.
+    fn call(
+        &mut self,
+        receiver: Variable,
+        callee: Variable,
+        args: Vec<Variable>,
+        site: Span,
+        module: Option<&str>,
+    ) -> Result<Variable> {
+        let context = match receiver {
+            Variable::Owned(Value::Instance { svt, .. }) => Some(svt),
+            _ => None,
+        };
+        let module = match context {
+            Some(_) => None,
+            None => module.map(String::from),
+        };
+        let (function, captures, frame) = match callee {
+            Variable::Owned(Value::Native(closure)) => {
+                // call closure with this interpreter as its host
+                let args = args.into_iter().map(Value::from).collect::<Vec<_>>();
+                let result = closure(self, &args)?;
+                return Ok(Variable::Owned(self.allocate(result)?));
+            }
+            Variable::Owned(Value::NativeMut(closure)) => {
+                let args = args.into_iter().map(Value::from).collect::<Vec<_>>();
+                self.charge_args(&args)?;
+                let result = mutate_copy(&*closure, &args)?;
+                return Ok(Variable::Owned(self.allocate(result)?));
+            }
+            Variable::Type(structure) | Variable::Owned(Value::Type(structure)) => {
+                let instance = self.instantiate(structure, |interpreter, constructor| {
+                    let chunk = interpreter.chunk(&constructor)?;
+                    let frame = Frame::new(&constructor, module, site);
+                    interpreter.traced(frame, |interpreter| {
+                        interpreter.invoke(&chunk, None, &Rc::default(), args)
+                    })?;
+                    Ok(())
+                })?;
+                return Ok(Variable::Owned(instance));
+            }
+            Variable::Owned(Value::Function { node, captures }) => {
+                (node.clone(), captures, Frame::new(&node, module, site))
+            }
+            Variable::Owned(other) => raise!(Type, "cannot call {other:?}"),
+        };
+        let chunk = self.chunk(&function)?;
+        self.traced(frame, |interpreter| match context {
+            Some(svt) => interpreter.with_context(Some(svt), |interpreter| {
+                interpreter.invoke(&chunk, Some(&function), &captures, args)
+            }),
+            None => interpreter.invoke(&chunk, Some(&function), &captures, args),
+        })
+    }
+
+    /// Runs the chunk of `function` with `args` as its parameters, and `captures` visible by
+    /// name. Constructors have no function value, and pass `None`.
+    fn invoke(
+        &mut self,
+        chunk: &Chunk,
+        function: Option<&Rc<ASTNode>>,
+        captures: &Rc<Captures>,
+        args: Vec<Variable>,
+    ) -> Result<Variable> {
+        check_arity(chunk.params, args.len())?;
+        self.with_frame(function, captures, |interpreter| {
+            interpreter.run(chunk, args)
+        })
+    }
+}
@@ -1,9 +1,13 @@
 //! The lexer breaks down text information into tokens, which can be used to assemble syntax.
 
+mod span;
 mod token;
-pub use token::Token;
+pub use span::Span;
+pub(crate) use span::WithSpan;
+pub use token::{SpannedToken, Token};
 
 use anyhow::{Context, Result, bail};
+use std::{iter::Peekable, path::Path, str::Chars, sync::Arc};
 mod tests;
 
 /// Lexer capture mode.
@@ -16,6 +20,46 @@ enum CaptureMode {
     Comment,
 }
 
+/// Character stream that tracks the location of every character it yields.
+struct SourceChars<'a> {
+    chars: Peekable<Chars<'a>>,
+    file: Option<Arc<Path>>,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> SourceChars<'a> {
+    fn new(buf: &'a str, file: Option<Arc<Path>>) -> Self {
+        Self {
+            chars: buf.chars().peekable(),
+            file,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Peek at the next character without consuming it.
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+}
+
+impl Iterator for SourceChars<'_> {
+    type Item = (char, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let c = self.chars.next()?;
+        let span = Span::new(self.file.clone(), self.line, self.column, 1);
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some((c, span))
+    }
+}
+
 /// The lexer transforms source code text into a sequence of tokens.
 ///
 /// The lexer operates in different capture modes to handle various language constructs
@@ -25,6 +69,12 @@ pub struct Lexer {
     keyword_register: String,
     string_register: String,
     equality_register: Option<Token>,
+    /// Location of the keyword register's contents.
+    keyword_span: Span,
+    /// Location of the number, string, or equality currently being captured.
+    token_span: Span,
+    /// File that spans will point to, if any.
+    file: Option<Arc<Path>>,
 }
 
 impl Default for Lexer {
@@ -41,53 +91,80 @@ impl Lexer {
             keyword_register: String::new(),
             string_register: String::new(),
             equality_register: None,
+            keyword_span: Span::default(),
+            token_span: Span::default(),
+            file: None,
+        }
+    }
+
+    /// Creates a new lexer whose spans point to the file at `path`.
+    pub fn with_file(path: impl AsRef<Path>) -> Self {
+        Self {
+            file: Some(Arc::from(path.as_ref())),
+            ..Self::new()
         }
     }
 
     /// Lexes the provided file, as a string, into a vector of tokens.
-    pub fn lex(&mut self, buf: String) -> Result<Vec<Token>> {
+    pub fn lex(&mut self, buf: String) -> Result<Vec<SpannedToken>> {
+        // errors are reported at the last character read
+        let mut location = Span::default();
+        self.lex_chars(&buf, &mut location).at(&location)
+    }
+
+    /// Lexes all characters of `buf`, keeping `location` up to date with the character that is
+    /// being processed.
+    fn lex_chars(&mut self, buf: &str, location: &mut Span) -> Result<Vec<SpannedToken>> {
         use Token::*;
-        let buf = buf.replace("\n", ";");
-        let mut chars = buf.chars().peekable();
+        let mut chars = SourceChars::new(buf, self.file.clone());
         let mut tokens = vec![];
         let mut mode = CaptureMode::General;
-        let mut c = chars.next().context("source file empty")?;
+        let (mut c, mut span) = chars.next().context("source file empty")?;
         loop {
+            *location = span.clone();
             match mode {
                 CaptureMode::General => {
                     match c {
                         // TODO this should just get moved out to its own mode vvv
 
                         // operators
-                        '+' => self.long_op(&mut chars, &mut tokens, '+', Increment, Add),
-                        '-' => self.long_op(&mut chars, &mut tokens, '-', Decrement, Sub),
-                        '*' => tokens.push(Mul),
-                        '/' => self.long_op(&mut chars, &mut tokens, '/', Floor, Div),
-                        '^' => tokens.push(Pow),
+                        '+' => Self::long_op(&mut chars, &mut tokens, &span, '+', Increment, Add),
+                        '-' => Self::long_op(&mut chars, &mut tokens, &span, '-', Decrement, Sub),
+                        '*' => emit(&mut tokens, Mul, &span),
+                        '/' => Self::long_op(&mut chars, &mut tokens, &span, '/', Floor, Div),
+                        '^' => emit(&mut tokens, Pow, &span),
+                        '%' => emit(&mut tokens, Mod, &span),
+                        '~' => emit(&mut tokens, BitXor, &span),
 
                         // equalities
                         '=' => {
                             self.equality_register = Some(Equal);
+                            self.token_span = span.clone();
                             mode = CaptureMode::Equality;
                         }
                         '!' => {
                             self.equality_register = Some(LogicalNot);
+                            self.token_span = span.clone();
                             mode = CaptureMode::Equality;
                         }
                         '>' => {
                             self.equality_register = Some(LogicalG);
+                            self.token_span = span.clone();
                             mode = CaptureMode::Equality;
                         }
                         '<' => {
                             self.equality_register = Some(LogicalL);
+                            self.token_span = span.clone();
                             mode = CaptureMode::Equality;
                         }
                         '&' => {
                             self.equality_register = Some(LogicalAnd);
+                            self.token_span = span.clone();
                             mode = CaptureMode::Equality;
                         }
                         '|' => {
                             self.equality_register = Some(LogicalOr);
+                            self.token_span = span.clone();
                             mode = CaptureMode::Equality;
                         }
 
@@ -95,57 +172,69 @@ impl Lexer {
                         c if c.is_numeric() && self.keyword_register.is_empty() => {
                             mode = CaptureMode::Number;
                             self.number_register.push(c);
+                            self.token_span = span.clone();
                         }
 
                         // quotes, for str & char
                         '\"' => {
                             mode = CaptureMode::String;
+                            self.token_span = span.clone();
                         }
                         '\'' => {
                             mode = CaptureMode::Char;
+                            self.token_span = span.clone();
                         }
 
                         // keywords and identifiers
-                        '(' | ')' | '[' | ']' | ',' | ' ' => {
-                            if let Some(token) = self.keyword_from_register() {
-                                // if the register contains a keyword, that takes priority
-                                tokens.push(token);
-                            } else if !self.keyword_register.is_empty() {
-                                // otherwise, it'd be an identifier
-                                tokens.push(Identifier(intern!(self.keyword_register.clone())));
-                            }
-                            self.keyword_register.clear();
+                        '(' | ')' | '[' | ']' | '{' | '}' | ',' | ':' | ' ' => {
+                            self.flush_keyword(&mut tokens);
 
                             // match delimiters
                             match c {
-                                '(' => tokens.push(ParenOpen),
-                                ')' => tokens.push(ParenClose),
-                                '[' => tokens.push(BracketOpen),
-                                ']' => tokens.push(BracketClose),
-                                ',' => tokens.push(Comma),
+                                '(' => emit(&mut tokens, ParenOpen, &span),
+                                ')' => emit(&mut tokens, ParenClose, &span),
+                                '[' => emit(&mut tokens, BracketOpen, &span),
+                                ']' => emit(&mut tokens, BracketClose, &span),
+                                '{' => emit(&mut tokens, BraceOpen, &span),
+                                '}' => emit(&mut tokens, BraceClose, &span),
+                                ',' => emit(&mut tokens, Comma, &span),
+                                ':' => emit(&mut tokens, Colon, &span),
                                 _ => {}
                             }
                         }
                         c if c.is_alphanumeric() || c == '_' => {
+                            if self.keyword_register.is_empty() {
+                                self.keyword_span = span.clone();
+                            } else {
+                                self.keyword_span = self.keyword_span.to(&span);
+                            }
                             self.keyword_register.push(c);
                         }
                         '.' => {
                             if !self.keyword_register.is_empty() {
-                                tokens.push(Identifier(intern!(self.keyword_register.clone())));
+                                let id = Identifier(intern!(self.keyword_register.clone()));
+                                emit(&mut tokens, id, &self.keyword_span);
                             }
                             self.keyword_register.clear();
-                            tokens.push(Dot);
+
+                            // `..` and `...` are used in patterns
+                            if chars.peek() == Some(&'.') {
+                                let (_, end) = chars.next().unwrap(); // safety: peek
+                                if chars.peek() == Some(&'.') {
+                                    let (_, end) = chars.next().unwrap(); // safety: peek
+                                    emit(&mut tokens, Spread, &span.to(&end));
+                                } else {
+                                    emit(&mut tokens, Range, &span.to(&end));
+                                }
+                            } else {
+                                emit(&mut tokens, Dot, &span);
+                            }
                         }
 
                         // endlines
                         ';' | '\n' => {
-                            if let Some(token) = self.keyword_from_register() {
-                                tokens.push(token);
-                            } else if !self.keyword_register.is_empty() {
-                                tokens.push(Identifier(intern!(self.keyword_register.clone())));
-                            }
-                            self.keyword_register.clear();
-                            tokens.push(Endl);
+                            self.flush_keyword(&mut tokens);
+                            emit(&mut tokens, Endl, &span);
                         }
 
                         // comments
@@ -159,29 +248,39 @@ impl Lexer {
                 }
                 CaptureMode::Comment => {
                     if c == '\n' || c == ';' {
-                        tokens.push(Endl);
+                        emit(&mut tokens, Endl, &span);
                         mode = CaptureMode::General;
                     }
                 }
                 CaptureMode::Equality => {
                     if let Some(token) = &self.equality_register {
+                        // spans of two-character operators cover both characters
+                        let long = self.token_span.to(&span);
                         match (token, c) {
-                            (Equal, '=') => tokens.push(LogicalEq),
-                            (Equal, _) => tokens.push(Equal),
-                            (LogicalL, '=') => tokens.push(LogicalLe),
-                            (LogicalL, _) => tokens.push(LogicalL),
-                            (LogicalG, '=') => tokens.push(LogicalGe),
-                            (LogicalG, _) => tokens.push(LogicalG),
-                            (LogicalAnd, '&') => tokens.push(LogicalAnd),
-                            (LogicalOr, '|') => tokens.push(LogicalOr),
-                            (LogicalNot, '=') => tokens.push(LogicalNeq),
-                            (LogicalNot, _) => {
+                            (Equal, '=') => emit(&mut tokens, LogicalEq, &long),
+                            (Equal, '>') => emit(&mut tokens, Arrow, &long),
+                            (Equal, _) => emit(&mut tokens, Equal, &self.token_span),
+                            (LogicalL, '=') => emit(&mut tokens, LogicalLe, &long),
+                            (LogicalL, '<') => emit(&mut tokens, ShiftL, &long),
+                            (LogicalL, _) => emit(&mut tokens, LogicalL, &self.token_span),
+                            (LogicalG, '=') => emit(&mut tokens, LogicalGe, &long),
+                            (LogicalG, '>') => emit(&mut tokens, ShiftR, &long),
+                            (LogicalG, _) => emit(&mut tokens, LogicalG, &self.token_span),
+                            (LogicalAnd, '&') => emit(&mut tokens, LogicalAnd, &long),
+                            (LogicalOr, '|') => emit(&mut tokens, LogicalOr, &long),
+                            (LogicalNot, '=') => emit(&mut tokens, LogicalNeq, &long),
+                            (LogicalNot | LogicalAnd | LogicalOr, _) => {
                                 // NOTE:
                                 // this bit is required to skip the character advancement that
                                 // occurs for all of the other branches here. this specifically
                                 // fixes double negatives (`!!true`). it's likely that there's
                                 // other bugs similar to this one that might need this workaround
-                                tokens.push(LogicalNot);
+                                let single = match token {
+                                    LogicalAnd => BitAnd,
+                                    LogicalOr => BitOr,
+                                    _ => LogicalNot,
+                                };
+                                emit(&mut tokens, single, &self.token_span);
                                 self.equality_register = None;
                                 mode = CaptureMode::General;
                                 continue;
@@ -195,25 +294,26 @@ impl Lexer {
                     mode = CaptureMode::General;
                 }
                 CaptureMode::Number => match c {
+                    // a second dot starts a range, like `1..5`
+                    '.' if chars.peek() == Some(&'.') => {
+                        self.flush_number(&mut tokens, location)?;
+                        mode = CaptureMode::General;
+                        continue;
+                    }
                     n if n.is_numeric() || n == '.' => {
                         self.number_register.push(n);
+                        self.token_span = self.token_span.to(&span);
                     }
                     _ => {
-                        if let Ok(number) = self.number_register.parse::<f32>() {
-                            // number parsed ok-- push token
-                            tokens.push(Number(number));
-                            self.number_register.clear();
-                        } else {
-                            // number failed to parse, bail
-                            bail!("cannot coerce {} to number", self.number_register);
-                        }
+                        self.flush_number(&mut tokens, location)?;
                         mode = CaptureMode::General;
                         continue;
                     }
                 },
                 CaptureMode::String => match c {
                     '\"' => {
-                        tokens.push(Str(self.string_register.clone()));
+                        let string = Str(self.string_register.clone());
+                        emit(&mut tokens, string, &self.token_span.to(&span));
                         self.string_register.clear();
                         mode = CaptureMode::General;
                     }
@@ -229,10 +329,10 @@ impl Lexer {
                         }
 
                         // skip second quote
-                        chars.next();
+                        let (_, end) = chars.next().unwrap(); // safety: peek
 
                         // push char token
-                        tokens.push(Char(c));
+                        emit(&mut tokens, Char(c), &self.token_span.to(&end));
                         mode = CaptureMode::General;
                     } else {
                         // if no char is found, this is an EOF
@@ -240,14 +340,54 @@ impl Lexer {
                     }
                 }
             }
-            if let Some(next_c) = chars.next() {
-                c = next_c;
+            if let Some(next) = chars.next() {
+                (c, span) = next;
             } else {
+                // the buffer may end without a delimiter, so flush whatever is still captured
+                match mode {
+                    CaptureMode::General => self.flush_keyword(&mut tokens),
+                    CaptureMode::Number => self.flush_number(&mut tokens, location)?,
+                    _ => {}
+                }
                 return Ok(tokens);
             }
         }
     }
 
+    /// Pushes the contents of the keyword register as either a keyword or an identifier, and
+    /// clears the register.
+    fn flush_keyword(&mut self, tokens: &mut Vec<SpannedToken>) {
+        if let Some(token) = self.keyword_from_register() {
+            // if the register contains a keyword, that takes priority
+            emit(tokens, token, &self.keyword_span);
+        } else if !self.keyword_register.is_empty() {
+            // otherwise, it'd be an identifier
+            let id = Token::Identifier(intern!(self.keyword_register.clone()));
+            emit(tokens, id, &self.keyword_span);
+        }
+        self.keyword_register.clear();
+    }
+
+    /// Pushes the contents of the number register as an integer or float, and clears the
+    /// register. Numbers with a decimal point are floats, and all others are integers.
+    fn flush_number(&mut self, tokens: &mut Vec<SpannedToken>, location: &mut Span) -> Result<()> {
+        let number = if self.number_register.contains('.') {
+            self.number_register.parse::<f64>().ok().map(Token::Float)
+        } else {
+            self.number_register.parse::<i64>().ok().map(Token::Int)
+        };
+        if let Some(number) = number {
+            // number parsed ok-- push token
+            emit(tokens, number, &self.token_span);
+            self.number_register.clear();
+            Ok(())
+        } else {
+            // number failed to parse, bail
+            *location = self.token_span.clone();
+            bail!("cannot coerce {} to number", self.number_register);
+        }
+    }
+
     /// Return the enum variant of the keyword stored in the keyword register.
     fn keyword_from_register(&self) -> Option<Token> {
         use Token::*;
@@ -260,7 +400,15 @@ impl Lexer {
             "if" => Some(If),
             "else" => Some(Else),
             "while" => Some(While),
+            "for" => Some(For),
+            "in" => Some(In),
             "break" => Some(Break),
+            "continue" => Some(Continue),
+            "match" => Some(Match),
+            "throw" => Some(Throw),
+            "try" => Some(Try),
+            "catch" => Some(Catch),
+            "finally" => Some(Finally),
             "do" => Some(BlockStart),
             "end" => Some(BlockEnd),
             "true" => Some(Bool(true)),
@@ -273,22 +421,29 @@ impl Lexer {
 
     /// Handles double-character operators like ++, --, //.
     fn long_op(
-        &self,
-        chars: &mut std::iter::Peekable<std::str::Chars>,
-        tokens: &mut Vec<Token>,
+        chars: &mut SourceChars,
+        tokens: &mut Vec<SpannedToken>,
+        span: &Span,
         expected_char: char,
         double_token: Token,
         single_token: Token,
     ) {
-        if let Some(peek_char) = chars.peek() {
-            if *peek_char == expected_char {
-                chars.next();
-                tokens.push(double_token);
-            } else {
-                tokens.push(single_token);
-            }
+        if let Some(peek_char) = chars.peek()
+            && *peek_char == expected_char
+        {
+            let (_, end) = chars.next().unwrap(); // safety: peek
+            emit(tokens, double_token, &span.to(&end));
         } else {
-            tokens.push(single_token);
+            emit(tokens, single_token, span);
         }
     }
 }
+
+/// Pushes a token with its location.
+#[inline]
+fn emit(tokens: &mut Vec<SpannedToken>, token: Token, span: &Span) {
+    tokens.push(SpannedToken {
+        token,
+        span: span.clone(),
+    });
+}
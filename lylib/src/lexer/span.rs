@@ -0,0 +1,102 @@
+//! Source locations. Every token and syntax node carries a `Span` so that errors raised while
+//! lexing, parsing, or interpreting can point back at the offending source line.
+
+use anyhow::Result;
+use std::{fmt::Display, path::Path, sync::Arc};
+
+/// A location within a source buffer.
+///
+/// Lines and columns are 1-based. A line of `0` marks a span with no known location, which is
+/// what nodes created outside of the parser (such as runtime values) hold.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// The file this span belongs to, if the buffer came from a file.
+    pub file: Option<Arc<Path>>,
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// Column number in characters, starting at 1.
+    pub column: usize,
+    /// Number of characters this span covers on its line.
+    pub length: usize,
+}
+
+impl Span {
+    /// Creates a new span covering `length` characters starting at `line` and `column`.
+    pub fn new(file: Option<Arc<Path>>, line: usize, column: usize, length: usize) -> Self {
+        Self {
+            file,
+            line,
+            column,
+            length,
+        }
+    }
+
+    /// Returns true if this span points at a real source location.
+    pub fn is_known(&self) -> bool {
+        self.line != 0
+    }
+
+    /// Returns a span from the start of `self` to the end of `end`. If the two spans are on
+    /// different lines, the result covers the rest of the starting line.
+    pub fn to(&self, end: &Span) -> Span {
+        let mut span = self.clone();
+        if end.line == self.line && end.column >= self.column {
+            span.length = end.column + end.length - self.column;
+        }
+        span
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}:{}", file.display(), self.line, self.column),
+            None => write!(f, "line {}, column {}", self.line, self.column),
+        }
+    }
+}
+
+/// Attaches source locations to errors.
+///
+/// Only the first span attached to an error is kept. Because errors are wrapped from the inside
+/// out, this means the most precise location wins, and outer nodes don't bury it.
+pub(crate) trait WithSpan<T> {
+    /// Attaches `span` to the error, if the error doesn't already carry a location.
+    fn at(self, span: &Span) -> Result<T>;
+}
+
+impl<T> WithSpan<T> for Result<T> {
+    #[inline]
+    fn at(self, span: &Span) -> Result<T> {
+        self.map_err(|e| {
+            if !span.is_known() || e.downcast_ref::<Span>().is_some() {
+                e
+            } else {
+                e.context(span.clone())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn span_to() {
+        let start = Span::new(None, 2, 5, 3);
+        assert_eq!(start.to(&Span::new(None, 2, 10, 2)).length, 7);
+        assert_eq!(start.to(&Span::new(None, 3, 1, 1)).length, 3);
+    }
+
+    #[test]
+    fn innermost_span_wins() {
+        let (inner, outer) = (Span::new(None, 4, 2, 1), Span::new(None, 1, 1, 1));
+        let err = Err::<(), _>(anyhow!("failure"))
+            .at(&inner)
+            .at(&outer)
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<Span>(), Some(&inner));
+    }
+}
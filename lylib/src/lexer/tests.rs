@@ -14,9 +14,9 @@ use Token::*;
 /// ```rust
 /// # use lylib::*;
 /// # use lylib::lexer::Token::*;
-/// lex_eq!("let x = 5;" => i => Let, Identifier(i.intern("x")), Equal, Number(5.), Endl);
+/// lex_eq!("let x = 5;" => i => Let, Identifier(i.intern("x")), Equal, Int(5), Endl);
 /// // or...
-/// lex_eq!("1 + 2;" => i => Number(1.), Add, Number(2.), Endl);
+/// lex_eq!("1 + 2;" => i => Int(1), Add, Int(2), Endl);
 /// ```
 #[macro_export]
 macro_rules! lex_eq {
@@ -27,7 +27,7 @@ macro_rules! lex_eq {
     (@compare $result:expr, $($token:expr),*) => {
         assert!($result.is_ok(), "Lexer failed: {:?}", $result);
         assert_eq!(
-            $result.unwrap(),
+            $result.unwrap().into_iter().map(|t| t.token).collect::<Vec<Token>>(),
             vec![$($token),*]
         );
     }
@@ -39,19 +39,19 @@ fn variable_assignment() {
         Let,
         Identifier(intern!("var1")),
         Equal,
-        Number(1.),
+        Int(1),
         Endl,
         Let,
         Identifier(intern!("var2")),
         Equal,
-        Number(2.),
+        Int(2),
         Endl,
         Let,
         Identifier(intern!("var1")),
         Dot,
         Identifier(intern!("var2")),
         Equal,
-        Number(3.),
+        Int(3),
         Endl
     );
 }
@@ -59,30 +59,64 @@ fn variable_assignment() {
 #[test]
 fn math() {
     lex_eq!("1+1+1+1;" =>
-        Number(1.),
+        Int(1),
         Add,
-        Number(1.),
+        Int(1),
         Add,
-        Number(1.),
+        Int(1),
         Add,
-        Number(1.),
+        Int(1),
         Endl
     );
 
     lex_eq!("32+12-7;" =>
-        Number(32.), Add, Number(12.), Sub, Number(7.), Endl
+        Int(32), Add, Int(12), Sub, Int(7), Endl
     );
 
     lex_eq!("0.5731 * 0.222 / 1^3 // 10;" =>
-        Number(0.5731),
+        Float(0.5731),
         Mul,
-        Number(0.222),
+        Float(0.222),
         Div,
-        Number(1.),
+        Int(1),
         Pow,
-        Number(3.),
+        Int(3),
         Floor,
-        Number(10.),
+        Int(10),
+        Endl
+    );
+}
+
+#[test]
+fn number_types() {
+    lex_eq!("1 1.0 2.5 9223372036854775807;" =>
+        Int(1), Float(1.0), Float(2.5), Int(i64::MAX), Endl
+    );
+
+    // integer literals that do not fit in 64 bits are rejected
+    assert!(Lexer::default().lex("9223372036854775808;".into()).is_err());
+}
+
+#[test]
+fn integer_operators() {
+    lex_eq!("7 % 2 & 3 | 4 ~ 5 << 1 >> 2 && true || false;" =>
+        Int(7),
+        Mod,
+        Int(2),
+        BitAnd,
+        Int(3),
+        BitOr,
+        Int(4),
+        BitXor,
+        Int(5),
+        ShiftL,
+        Int(1),
+        ShiftR,
+        Int(2),
+        LogicalAnd,
+        Bool(true),
+        LogicalOr,
+        Bool(false),
         Endl
     );
 }
@@ -109,28 +143,28 @@ fn unary() {
 #[test]
 fn logic() {
     lex_eq!("1 == 2; 1 != 2;" =>
-        Number(1.),
+        Int(1),
         LogicalEq,
-        Number(2.),
+        Int(2),
         Endl,
-        Number(1.),
+        Int(1),
         LogicalNeq,
-        Number(2.),
+        Int(2),
         Endl
     );
 
     lex_eq!("1 > 2 >= 3; 1 < 2 <= 3;" =>
-        Number(1.),
+        Int(1),
         LogicalG,
-        Number(2.),
+        Int(2),
         LogicalGe,
-        Number(3.),
+        Int(3),
         Endl,
-        Number(1.),
+        Int(1),
         LogicalL,
-        Number(2.),
+        Int(2),
         LogicalLe,
-        Number(3.),
+        Int(3),
         Endl
     );
 
@@ -150,9 +184,9 @@ fn logic() {
 fn conditionals() {
     lex_eq!("if 1 > 2 do end;" =>
         If,
-        Number(1.),
+        Int(1),
         LogicalG,
-        Number(2.),
+        Int(2),
         BlockStart,
         BlockEnd,
         Endl
@@ -160,14 +194,14 @@ fn conditionals() {
 
     lex_eq!("if 1 < 2 do; 1 + 1; end;" =>
         If,
-        Number(1.),
+        Int(1),
         LogicalL,
-        Number(2.),
+        Int(2),
         BlockStart,
         Endl,
-        Number(1.),
+        Int(1),
         Add,
-        Number(1.),
+        Int(1),
         Endl,
         BlockEnd,
         Endl
@@ -205,13 +239,13 @@ fn functions() {
     lex_eq!("function(1 + 2, 3 + 4)" =>
         Identifier(intern!("function")),
         ParenOpen,
-        Number(1.),
+        Int(1),
         Add,
-        Number(2.),
+        Int(2),
         Comma,
-        Number(3.),
+        Int(3),
         Add,
-        Number(4.),
+        Int(4),
         ParenClose
     );
 
@@ -250,12 +284,12 @@ fn bools() {
 fn parens() {
     lex_eq!("(1 + 1) + 1;" =>
         ParenOpen,
-        Number(1.),
+        Int(1),
         Add,
-        Number(1.),
+        Int(1),
         ParenClose,
         Add,
-        Number(1.),
+        Int(1),
         Endl
     );
 }
@@ -267,7 +301,7 @@ fn lists() {
         Identifier(intern!("list")),
         Equal,
         BracketOpen,
-        Number(0.),
+        Int(0),
         Comma,
         Bool(false),
         Comma,
@@ -279,7 +313,31 @@ fn lists() {
         Equal,
         Identifier(intern!("list")),
         BracketOpen,
-        Number(0.),
+        Int(0),
+        BracketClose,
+        Endl
+    );
+}
+
+#[test]
+fn dicts() {
+    lex_eq!("let d = {\"a\": 1, b: 2}; d[\"a\"];" =>
+        Let,
+        Identifier(intern!("d")),
+        Equal,
+        BraceOpen,
+        Str("a".into()),
+        Colon,
+        Int(1),
+        Comma,
+        Identifier(intern!("b")),
+        Colon,
+        Int(2),
+        BraceClose,
+        Endl,
+        Identifier(intern!("d")),
+        BracketOpen,
+        Str("a".into()),
         BracketClose,
         Endl
     );
@@ -290,6 +348,75 @@ fn loops() {
     lex_eq!("while true do; break; end;" =>
         While, Bool(true), BlockStart, Endl, Break, Endl, BlockEnd, Endl
     );
+    lex_eq!("outer: while true do; continue outer; end;" =>
+        Identifier(intern!("outer")),
+        Colon,
+        While,
+        Bool(true),
+        BlockStart,
+        Endl,
+        Continue,
+        Identifier(intern!("outer")),
+        Endl,
+        BlockEnd,
+        Endl
+    );
+    lex_eq!("for i, item in items do; end;" =>
+        For,
+        Identifier(intern!("i")),
+        Comma,
+        Identifier(intern!("item")),
+        In,
+        Identifier(intern!("items")),
+        BlockStart,
+        Endl,
+        BlockEnd,
+        Endl
+    );
+}
+
+#[test]
+fn patterns() {
+    lex_eq!("match x do 1..5 => a; [h, ...t] => t; end" =>
+        Match,
+        Identifier(intern!("x")),
+        BlockStart,
+        Int(1),
+        Range,
+        Int(5),
+        Arrow,
+        Identifier(intern!("a")),
+        Endl,
+        BracketOpen,
+        Identifier(intern!("h")),
+        Comma,
+        Spread,
+        Identifier(intern!("t")),
+        BracketClose,
+        Arrow,
+        Identifier(intern!("t")),
+        Endl,
+        BlockEnd
+    );
+}
+
+#[test]
+fn exceptions() {
+    lex_eq!("try do; throw e; catch err do; finally; end" =>
+        Try,
+        BlockStart,
+        Endl,
+        Throw,
+        Identifier(intern!("e")),
+        Endl,
+        Catch,
+        Identifier(intern!("err")),
+        BlockStart,
+        Endl,
+        Finally,
+        Endl,
+        BlockEnd
+    );
 }
 
 #[test]
@@ -316,7 +443,7 @@ fn structs() {
         Let,
         Identifier(intern!("value")),
         Equal,
-        Number(0.),
+        Int(0),
         Endl,
         BlockEnd,
         Endl,
@@ -328,3 +455,47 @@ fn structs() {
         Endl
     );
 }
+
+#[test]
+fn spans() {
+    let tokens = Lexer::default()
+        .lex("let x = 10\nx == \"ab\"\n  'c'".into())
+        .unwrap();
+    let spans: Vec<(usize, usize, usize)> = tokens
+        .iter()
+        .map(|t| (t.span.line, t.span.column, t.span.length))
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (1, 1, 3),  // let
+            (1, 5, 1),  // x
+            (1, 7, 1),  // =
+            (1, 9, 2),  // 10
+            (1, 11, 1), // endl
+            (2, 1, 1),  // x
+            (2, 3, 2),  // ==
+            (2, 6, 4),  // "ab"
+            (2, 10, 1), // endl
+            (3, 3, 3)   // 'c'
+        ]
+    );
+}
+
+#[test]
+fn trailing_tokens() {
+    // buffers may end without a delimiter
+    lex_eq!("let x = 10" => Let, Identifier(intern!("x")), Equal, Int(10));
+    lex_eq!("x + y" => Identifier(intern!("x")), Add, Identifier(intern!("y")));
+}
+
+#[test]
+fn error_location() {
+    let err = Lexer::default()
+        .lex("let a = 1\nlet b = 'cd'".into())
+        .unwrap_err();
+    let span = err
+        .downcast_ref::<Span>()
+        .expect("error should carry a span");
+    assert_eq!((span.line, span.column), (2, 10));
+}
@@ -10,7 +10,8 @@ impl Debug for Token {
             Token::Struct => write!(f, "Struct"),
             Token::Return => write!(f, "Return"),
             Token::Let => write!(f, "Let"),
-            Token::Number(n) => write!(f, "Number({n:?})"),
+            Token::Int(n) => write!(f, "Int({n:?})"),
+            Token::Float(n) => write!(f, "Float({n:?})"),
             Token::Bool(b) => write!(f, "Bool({b:?})"),
             Token::Str(s) => write!(f, "Str({s:?})"),
             Token::Char(c) => write!(f, "Char({c:?})"),
@@ -18,13 +19,26 @@ impl Debug for Token {
             Token::If => write!(f, "If"),
             Token::Else => write!(f, "Else"),
             Token::While => write!(f, "While"),
+            Token::For => write!(f, "For"),
+            Token::In => write!(f, "In"),
             Token::BlockStart => write!(f, "BlockStart"),
             Token::BlockEnd => write!(f, "BlockEnd"),
             Token::Break => write!(f, "Break"),
+            Token::Continue => write!(f, "Continue"),
+            Token::Match => write!(f, "Match"),
+            Token::Throw => write!(f, "Throw"),
+            Token::Try => write!(f, "Try"),
+            Token::Catch => write!(f, "Catch"),
+            Token::Finally => write!(f, "Finally"),
+            Token::Arrow => write!(f, "Arrow"),
+            Token::Range => write!(f, "Range"),
+            Token::Spread => write!(f, "Spread"),
             Token::ParenOpen => write!(f, "ParenOpen"),
             Token::ParenClose => write!(f, "ParenClose"),
             Token::BracketOpen => write!(f, "BracketOpen"),
             Token::BracketClose => write!(f, "BracketClose"),
+            Token::BraceOpen => write!(f, "BraceOpen"),
+            Token::BraceClose => write!(f, "BraceClose"),
             Token::LogicalNot => write!(f, "LogicalNot"),
             Token::LogicalEq => write!(f, "LogicalEq"),
             Token::LogicalNeq => write!(f, "LogicalNeq"),
@@ -42,9 +56,16 @@ impl Debug for Token {
             Token::Increment => write!(f, "Increment"),
             Token::Decrement => write!(f, "Decrement"),
             Token::Floor => write!(f, "Floor"),
+            Token::Mod => write!(f, "Mod"),
+            Token::BitAnd => write!(f, "BitAnd"),
+            Token::BitOr => write!(f, "BitOr"),
+            Token::BitXor => write!(f, "BitXor"),
+            Token::ShiftL => write!(f, "ShiftL"),
+            Token::ShiftR => write!(f, "ShiftR"),
             Token::Import => write!(f, "Import"),
             Token::As => write!(f, "As"),
             Token::Comma => write!(f, "Comma"),
+            Token::Colon => write!(f, "Colon"),
             Token::Dot => write!(f, "Dot"),
             Token::New => write!(f, "New"),
             Token::Endl => write!(f, "Endl"),
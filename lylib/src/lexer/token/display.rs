@@ -4,14 +4,75 @@ use std::fmt::Display;
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use Token::*;
-        match self {
-            Bool(v) => write!(f, "{v}"),
-            Str(v) => write!(f, "{v}"),
-            Number(v) => write!(f, "{v}"),
-            Char(v) => write!(f, "{v}"),
-            Identifier(v) => write!(f, "#{}", resolve!(*v)),
-            _ => write!(f, "{self:?}"),
-        }?;
-        Ok(())
+        let text = match self {
+            Bool(v) => return write!(f, "{v}"),
+            Str(v) => return write!(f, "{v}"),
+            Int(v) => return write!(f, "{v}"),
+            Float(v) => return write!(f, "{v:?}"),
+            Char(v) => return write!(f, "{v}"),
+            Identifier(v) => return write!(f, "#{}", resolve!(*v)),
+            Endl => return write!(f, "end of line"),
+
+            // other tokens are shown as they're written
+            Equal => "=",
+            Function => "func",
+            Struct => "struct",
+            Let => "let",
+            Undefined => "undefined",
+            If => "if",
+            Else => "else",
+            While => "while",
+            For => "for",
+            In => "in",
+            BlockStart => "do",
+            BlockEnd => "end",
+            Break => "break",
+            Continue => "continue",
+            Match => "match",
+            Return => "return",
+            Throw => "throw",
+            Try => "try",
+            Catch => "catch",
+            Finally => "finally",
+            ParenOpen => "(",
+            ParenClose => ")",
+            BracketOpen => "[",
+            BracketClose => "]",
+            BraceOpen => "{",
+            BraceClose => "}",
+            LogicalNot => "!",
+            LogicalEq => "==",
+            LogicalNeq => "!=",
+            LogicalG => ">",
+            LogicalGe => ">=",
+            LogicalL => "<",
+            LogicalLe => "<=",
+            LogicalAnd => "&&",
+            LogicalOr => "||",
+            Add => "+",
+            Sub => "-",
+            Mul => "*",
+            Div => "/",
+            Pow => "^",
+            Floor => "//",
+            Mod => "%",
+            Increment => "++",
+            Decrement => "--",
+            BitAnd => "&",
+            BitOr => "|",
+            BitXor => "~",
+            ShiftL => "<<",
+            ShiftR => ">>",
+            Import => "import",
+            As => "as",
+            Arrow => "=>",
+            Range => "..",
+            Spread => "...",
+            Comma => ",",
+            Colon => ":",
+            Dot => ".",
+            New => "new",
+        };
+        write!(f, "`{text}`")
     }
 }
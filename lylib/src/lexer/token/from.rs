@@ -4,13 +4,13 @@ use super::Token;
 
 impl From<isize> for Token {
     fn from(value: isize) -> Self {
-        Token::Number(value as f32)
+        Token::Int(value as i64)
     }
 }
 
-impl From<f32> for Token {
-    fn from(value: f32) -> Self {
-        Token::Number(value)
+impl From<f64> for Token {
+    fn from(value: f64) -> Self {
+        Token::Float(value)
     }
 }
 
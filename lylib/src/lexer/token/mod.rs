@@ -2,6 +2,8 @@ mod debug;
 mod display;
 mod from;
 
+use super::Span;
+
 /// Represents all possible tokens.
 #[derive(PartialEq, Clone)]
 pub enum Token {
@@ -13,7 +15,8 @@ pub enum Token {
     Let,
 
     // data types
-    Number(f32),
+    Int(i64),
+    Float(f64),
     Bool(bool),
     Str(String),
     Char(char),
@@ -23,16 +26,26 @@ pub enum Token {
     If,
     Else,
     While,
+    For,
+    In,
     BlockStart,
     BlockEnd,
     Break,
+    Continue,
+    Match,
     Return,
+    Throw,
+    Try,
+    Catch,
+    Finally,
 
     // delimiters
     ParenOpen,
     ParenClose,
     BracketOpen,
     BracketClose,
+    BraceOpen,
+    BraceClose,
 
     // logic
     LogicalNot,
@@ -52,20 +65,41 @@ pub enum Token {
     Div,
     Pow,
     Floor,
+    Mod,
     Increment,
     Decrement,
 
+    // bitwise ops
+    BitAnd,
+    BitOr,
+    BitXor,
+    ShiftL,
+    ShiftR,
+
     // modules
     Import,
     As,
 
+    // patterns
+    Arrow,
+    Range,
+    Spread,
+
     // other
     Comma,
+    Colon,
     Dot,
     New,
     Endl,
 }
 
+/// A token paired with the location it was lexed from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
 impl Token {
     /// Returns true if `self` is an operator.
     /// Returns true for both numeric and logical operators.
@@ -77,7 +111,13 @@ impl Token {
                 | Token::Mul
                 | Token::Div
                 | Token::Floor
+                | Token::Mod
                 | Token::Pow
+                | Token::BitAnd
+                | Token::BitOr
+                | Token::BitXor
+                | Token::ShiftL
+                | Token::ShiftR
                 | Token::LogicalL
                 | Token::LogicalLe
                 | Token::LogicalG
@@ -94,7 +134,7 @@ impl Token {
     pub(crate) fn is_literal(&self) -> bool {
         matches!(
             self,
-            Token::Number(_) | Token::Str(_) | Token::Char(_) | Token::Bool(_)
+            Token::Int(_) | Token::Float(_) | Token::Str(_) | Token::Char(_) | Token::Bool(_)
         )
     }
 }
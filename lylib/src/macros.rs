@@ -22,7 +22,20 @@ macro_rules! resolve {
     }};
 }
 
-/// Shorthand for creating a literal.
+/// Returns early with a runtime error of the given kind, formatting its message like `bail!`.
+/// The kind is seen by Lily programs that catch the error.
+macro_rules! raise {
+    ($kind:ident, $($arg:tt)*) => {
+        return Err(crate::interpreter::RuntimeError::new(
+            crate::interpreter::ErrorKind::$kind,
+            format!($($arg)*),
+        )
+        .into())
+    };
+}
+
+/// Shorthand for creating a literal. Used for testing.
+#[cfg(test)]
 macro_rules! lit {
     // numbers
     ($literal:literal) => {
@@ -31,7 +44,10 @@ macro_rules! lit {
 
     // literals
     ($token:expr) => {
-        Rc::new(ASTNode::Literal($token))
+        Rc::new(ASTNode::Literal {
+            token: $token,
+            span: crate::lexer::Span::default(),
+        })
     };
 }
 
@@ -48,10 +64,29 @@ macro_rules! ident {
 macro_rules! block {
     ($($node:expr),*) => {{
         let block = vec![$($node),*];
-        ASTNode::Block(block).into()
+        ASTNode::Block {
+            statements: block,
+            span: crate::lexer::Span::default(),
+        }
+        .into()
     }};
 }
 
+/// Shorthand for creating list values. Items are converted with `Value::from`. Used for testing.
+/// # Usage
+/// ```ignore
+/// list![1, "a", true, 'c'];
+/// list![list![1, 2], list![]]; // nested lists
+/// ```
+#[cfg(test)]
+macro_rules! list {
+    ($($item:expr),*) => {
+        crate::interpreter::Value::List(vec![$(
+            crate::interpreter::Variable::Owned(crate::interpreter::Value::from($item)).into()
+        ),*])
+    };
+}
+
 /// Shorthand for all AST nodes. Used for testing.
 /// # Usage
 /// ```
@@ -84,6 +119,9 @@ macro_rules! block {
 /// // lists
 /// node!([lit!(1), lit!(2), lit!(3)]);
 ///
+/// // dictionaries
+/// node!({lit!("a") => lit!(1), ident!("b") => lit!(2)});
+///
 /// // indexing
 /// node!(list[0]); // literal index
 /// node!(list[ident!("i")]); // expression index
@@ -104,6 +142,7 @@ macro_rules! node {
             lhs: lit!($lhs),
             op: $op,
             rhs: lit!($rhs),
+            span: crate::lexer::Span::default(),
         })
     };
     (op $lhs:expr, $op:expr, $rhs:expr) => { // literal (`op lit!(1), Add, lit!(1)`)
@@ -111,6 +150,7 @@ macro_rules! node {
             lhs: $lhs,
             op: $op,
             rhs: $rhs,
+            span: crate::lexer::Span::default(),
         })
     };
 
@@ -119,6 +159,7 @@ macro_rules! node {
         Rc::new(ASTNode::UnaryOp {
             target: $target,
             op: $op,
+            span: crate::lexer::Span::default(),
         })
     };
 
@@ -130,6 +171,7 @@ macro_rules! node {
         ASTNode::Declare {
             target: $id,
             value: $val,
+            span: crate::lexer::Span::default(),
         }
         .into()
     };
@@ -140,6 +182,7 @@ macro_rules! node {
         ASTNode::Assign {
             target: $id,
             value: $val,
+            span: crate::lexer::Span::default(),
         }
         .into()
     };
@@ -150,14 +193,75 @@ macro_rules! node {
             condition: $cond,
             if_body: $ifbody,
             else_body: $elsebody,
+            span: crate::lexer::Span::default(),
         }.into()
     };
 
-    // loops (`loop node!(..) => block!(..);`)
+    // loops (`loop node!(..) => block!(..);`, or `loop label: node!(..) => block!(..);`)
+    (loop $label:ident: $cond:expr => $body:expr;) => {
+        ASTNode::Loop {
+            label: Some(intern!(stringify!($label))),
+            condition: $cond,
+            body: $body,
+            span: crate::lexer::Span::default(),
+        }.into()
+    };
     (loop $cond:expr => $body:expr;) => {
         ASTNode::Loop {
+            label: None,
             condition: $cond,
             body: $body,
+            span: crate::lexer::Span::default(),
+        }.into()
+    };
+
+    // for loops (`for i, item in node!(..) => block!(..);`, or `for label: item in ..`)
+    (for $label:ident: $item:tt in $iter:expr => $body:expr;) => {
+        ASTNode::For {
+            label: Some(intern!(stringify!($label))),
+            index: None,
+            item: intern!(stringify!($item)),
+            iterable: $iter,
+            body: $body,
+            span: crate::lexer::Span::default(),
+        }.into()
+    };
+    (for $item:tt in $iter:expr => $body:expr;) => {
+        ASTNode::For {
+            label: None,
+            index: None,
+            item: intern!(stringify!($item)),
+            iterable: $iter,
+            body: $body,
+            span: crate::lexer::Span::default(),
+        }.into()
+    };
+    (for $index:tt, $item:tt in $iter:expr => $body:expr;) => {
+        ASTNode::For {
+            label: None,
+            index: Some(intern!(stringify!($index))),
+            item: intern!(stringify!($item)),
+            iterable: $iter,
+            body: $body,
+            span: crate::lexer::Span::default(),
+        }.into()
+    };
+
+    // ranges (`range lit!(0), ident!("n")`)
+    (range $start:expr, $end:expr) => {
+        std::rc::Rc::new(ASTNode::Range {
+            start: $start,
+            end: $end,
+            span: crate::lexer::Span::default(),
+        })
+    };
+
+    // matches (`match node!(..) => (Pattern::Wildcard, block!(..)), ..`)
+    (match $value:expr => $(($pattern:expr, $body:expr)),*) => {
+        ASTNode::Match {
+            value: $value,
+            arms: vec![$((std::rc::Rc::new($pattern), $body)),*],
+            span: crate::lexer::Span::default(),
         }.into()
     };
 
@@ -166,38 +270,84 @@ macro_rules! node {
         ASTNode::FunctionCall {
             target: $fn,
             arguments: vec![],
+            span: crate::lexer::Span::default(),
         }.into()
     };
     ($fn:tt($($arg:expr),*)) => { // implied calls (`a()`)
         ASTNode::FunctionCall {
             target: ident!(stringify!($fn)),
             arguments: vec![$($arg),*],
+            span: crate::lexer::Span::default(),
         }.into()
     };
     ($first:tt $(. $rest:tt)+ ($($arg:expr),*)) => { // deref-fn calls (`a.b.c()`)
         ASTNode::FunctionCall {
             target: node!($first $(. $rest)+),
             arguments: vec![$($arg),*],
+            span: crate::lexer::Span::default(),
+        }.into()
+    };
+
+    // anonymous functions
+    (func ($($arg:tt),*) => $body:expr) => {
+        ASTNode::Function {
+            id: None,
+            arguments: vec![$(intern!(stringify!($arg))),*],
+            body: $body,
+            span: crate::lexer::Span::default(),
         }.into()
     };
 
     // function declarations
     (func $fn:tt($($arg:tt),*) => $body:expr) => {
         ASTNode::Function {
-            id: crate::interpreter::ID::new_sym(intern!(stringify!($fn))),
+            id: Some(crate::interpreter::ID::new_sym(intern!(stringify!($fn)))),
             arguments: vec![$(intern!(stringify!($arg))),*],
             body: $body,
+            span: crate::lexer::Span::default(),
         }.into()
     };
 
-    // breaks
-    (break) => {
-        ASTNode::Break.into()
+    // breaks & continues (`break`, or `break label`)
+    (break $($label:ident)?) => {
+        ASTNode::Break {
+            label: None$(.or(Some(intern!(stringify!($label)))))?,
+            span: crate::lexer::Span::default(),
+        }.into()
+    };
+    (continue $($label:ident)?) => {
+        ASTNode::Continue {
+            label: None$(.or(Some(intern!(stringify!($label)))))?,
+            span: crate::lexer::Span::default(),
+        }.into()
     };
 
     // return statements (`return node!(..)`)
     (return $value:expr) => {
-        ASTNode::Return($value).into()
+        ASTNode::Return {
+            value: $value,
+            span: crate::lexer::Span::default(),
+        }.into()
+    };
+
+    // throw statements (`throw node!(..)`)
+    (throw $value:expr) => {
+        ASTNode::Throw {
+            value: $value,
+            span: crate::lexer::Span::default(),
+        }.into()
+    };
+
+    // try statements (`try block!(..); catch err => block!(..); finally block!(..)`), where the
+    // name, catch and finally are optional
+    (try $body:expr; $(catch $($name:ident)? => $handler:expr;)? $(finally $finally:expr)?) => {
+        ASTNode::Try {
+            body: $body,
+            name: None$($(.or(Some(intern!(stringify!($name)))))?)?,
+            handler: None$(.or(Some($handler)))?,
+            finally: None$(.or(Some($finally)))?,
+            span: crate::lexer::Span::default(),
+        }.into()
     };
 
     // modules (`mod xyz => block!(..)`)
@@ -206,6 +356,7 @@ macro_rules! node {
             alias: Some(intern!(stringify!($id)).into()),
             path: None,
             body: $body,
+            span: crate::lexer::Span::default(),
         }.into()
     };
 
@@ -214,23 +365,26 @@ macro_rules! node {
         ASTNode::Struct {
             id: crate::interpreter::ID::new_sym(intern!(stringify!($id))),
             body: $body,
-            // this template doesn't need to match for testing purposes, and is exempt
-            template: crate::parser::SVTable::default(),
+            span: crate::lexer::Span::default(),
         }.into()
     };
 
     // lists (`[1, 2, 3]`)
     ([$($item:expr),*]) => {
-        {
-            // convert nodes to variables and make new list
-            let values: Vec<Rc<ASTNode>> = vec![$($item),*];
-            let mut variables = vec![];
-            for value in values {
-                let variable: Variable = value.into();
-                variables.push(variable.into());
-            }
-            ASTNode::List(variables).into()
+        ASTNode::List {
+            items: vec![$($item),*],
+            span: crate::lexer::Span::default(),
         }
+        .into()
+    };
+
+    // dictionaries (`{lit!("a") => lit!(1)}`)
+    ({$($key:expr => $value:expr),*}) => {
+        ASTNode::Dict {
+            entries: vec![$(($key, $value)),*],
+            span: crate::lexer::Span::default(),
+        }
+        .into()
     };
 
     // indices (`list[0]`)
@@ -239,6 +393,7 @@ macro_rules! node {
         ASTNode::Index {
             target: $list,
             index: lit!($idx),
+            span: crate::lexer::Span::default(),
         }
         .into()
     };
@@ -247,6 +402,7 @@ macro_rules! node {
         ASTNode::Index {
             target: ident!(stringify!($list)),
             index: lit!($idx),
+            span: crate::lexer::Span::default(),
         }
         .into()
     };
@@ -255,6 +411,7 @@ macro_rules! node {
         ASTNode::Index {
             target: ident!(stringify!($list)),
             index: $idx,
+            span: crate::lexer::Span::default(),
         }
         .into()
     };
@@ -264,6 +421,7 @@ macro_rules! node {
         ASTNode::Deref {
             parent: $parent,
             child: $child,
+            span: crate::lexer::Span::default(),
         }.into()
     }};
     ($first:tt $(. $rest:tt)+) => {{ // implied derefs (`a.b.c`)
@@ -272,6 +430,7 @@ macro_rules! node {
             current = ASTNode::Deref {
                 parent: current,
                 child: ident!(stringify!($rest)),
+                span: crate::lexer::Span::default(),
             }.into();
         )+
         current
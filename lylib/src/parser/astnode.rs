@@ -1,81 +1,342 @@
 use super::*;
-use crate::interpreter::{ID, IDKind, SVTable, Variable};
+use crate::interpreter::{ID, IDKind};
+use crate::lexer::Span;
 use derivative::Derivative;
-use std::{cell::RefCell, fmt::Display};
+use std::fmt::Display;
 
 #[derive(Derivative)]
 #[derivative(Debug, PartialEq, Clone)]
 pub enum ASTNode {
     /// Represents a block of statements, grouped in a scope.
-    Block(Vec<Rc<ASTNode>>),
+    Block {
+        statements: Vec<Rc<ASTNode>>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
     /// Holds a block, but represents a separate module.
     Module {
         #[derivative(PartialEq = "ignore")]
         path: Option<PathBuf>,
         alias: Option<usize>,
         body: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
 
     Index {
         target: Rc<ASTNode>,
         index: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
     Assign {
         target: Rc<ASTNode>,
         value: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
     Declare {
         target: Rc<ASTNode>,
         value: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
     Deref {
         parent: Rc<ASTNode>,
         child: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
     Function {
-        id: ID,
+        /// Name of the function. Anonymous functions have none.
+        id: Option<ID>,
         arguments: Vec<usize>,
         body: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
     FunctionCall {
         target: Rc<ASTNode>,
         arguments: Vec<Rc<ASTNode>>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
     Struct {
         id: ID,
         body: Rc<ASTNode>,
-        #[derivative(PartialEq = "ignore")]
-        template: SVTable,
-    },
-    Instance {
-        kind: Rc<Variable>,
-        svt: Rc<RefCell<SVTable>>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
     Conditional {
         condition: Rc<ASTNode>,
         if_body: Rc<ASTNode>,
         else_body: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
     Loop {
+        /// Name that breaks & continues can refer to this loop by.
+        label: Option<usize>,
         condition: Rc<ASTNode>,
         body: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
+    /// Loops over the entries of a list, string, dictionary or range, binding each to `item`, and
+    /// its index or key to `index`, if there is one.
+    For {
+        label: Option<usize>,
+        index: Option<usize>,
+        item: usize,
+        iterable: Rc<ASTNode>,
+        body: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
+    /// Integers from `start`, up to but not including `end`. Only for loops use ranges, counting
+    /// through them without creating a list.
+    Range {
+        start: Rc<ASTNode>,
+        end: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
+    /// Evaluates the body of the first arm whose pattern matches `value`.
+    Match {
+        value: Rc<ASTNode>,
+        arms: Vec<(Rc<Pattern>, Rc<ASTNode>)>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
     Op {
         lhs: Rc<ASTNode>,
         op: Token,
         rhs: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
     UnaryOp {
         target: Rc<ASTNode>,
         op: Token,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
+    Return {
+        value: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
+    /// Leaves the loop labeled `label`, or the innermost loop if there is none.
+    Break {
+        label: Option<usize>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
+    /// Skips to the next iteration of the loop labeled `label`, or of the innermost loop if there
+    /// is none.
+    Continue {
+        label: Option<usize>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
+    /// Throws a value, to be caught by the innermost try around it.
+    Throw {
+        value: Rc<ASTNode>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
+    /// Executes `body`, running `handler` with the error bound to `name` if it fails. `finally`
+    /// runs afterwards, whether or not the body or handler failed.
+    Try {
+        body: Rc<ASTNode>,
+        name: Option<usize>,
+        handler: Option<Rc<ASTNode>>,
+        finally: Option<Rc<ASTNode>>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
+    Literal {
+        token: Token,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
     },
-    Return(Rc<ASTNode>),
-    Break,
+    List {
+        items: Vec<Rc<ASTNode>>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
+    /// Holds the key & value of every entry of a dictionary literal, in order.
+    Dict {
+        entries: Vec<(Rc<ASTNode>, Rc<ASTNode>)>,
+        #[derivative(Debug = "ignore", PartialEq = "ignore")]
+        span: Span,
+    },
+}
+
+/// A pattern that the arms of a `match` test values against.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    /// Matches anything, without binding it.
+    Wildcard,
+    /// Matches anything, binding it to a name.
+    Bind(usize),
+    /// Matches values equal to a literal.
     Literal(Token),
-    List(Vec<Rc<RefCell<Variable>>>),
+    /// Matches numbers or characters from a start, up to but not including an end.
+    Range(Token, Token),
+    /// Matches lists whose items match each pattern in turn. Lists with a rest pattern may be
+    /// longer, the rest of their items being matched against it as a list.
+    List(Vec<Pattern>, Option<Box<Pattern>>),
+    /// Matches instances of the structure named by a symbol, whose members match each pattern.
+    Struct(usize, Vec<(usize, Pattern)>),
+}
+
+impl Pattern {
+    /// Returns true if this pattern matches every value.
+    pub(crate) fn is_catch_all(&self) -> bool {
+        matches!(self, Pattern::Wildcard | Pattern::Bind(_))
+    }
+
+    /// Returns true if no value of the kinds `patterns` test is left unmatched by them. Along with
+    /// catch-alls, this holds for both boolean literals together, and for lists of every length,
+    /// whose items match anything, up to one with a rest pattern.
+    pub(crate) fn is_exhaustive(patterns: &[&Pattern]) -> bool {
+        if patterns.iter().any(|pattern| pattern.is_catch_all()) {
+            return true;
+        }
+        let has_bool = |value| patterns.contains(&&Pattern::Literal(Token::Bool(value)));
+        if has_bool(true) && has_bool(false) {
+            return true;
+        }
+
+        // the lengths of lists of catch-alls, and whether longer lists are matched too
+        let lists = patterns
+            .iter()
+            .filter_map(|pattern| match pattern {
+                Pattern::List(items, rest) if items.iter().all(Pattern::is_catch_all) => {
+                    Some((items.len(), rest.is_some()))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let longer = lists
+            .iter()
+            .filter(|(_, rest)| *rest)
+            .map(|(length, _)| *length)
+            .min();
+        longer.is_some_and(|longer| (0..longer).all(|length| lists.contains(&(length, false))))
+    }
+
+    /// Gets the names this pattern binds, in the order that they're bound when matching.
+    pub(crate) fn bindings(&self) -> Vec<usize> {
+        let mut names = vec![];
+        self.collect_bindings(&mut names);
+        names
+    }
+
+    fn collect_bindings(&self, names: &mut Vec<usize>) {
+        match self {
+            Pattern::Bind(sym) => names.push(*sym),
+            Pattern::List(items, rest) => items
+                .iter()
+                .chain(rest.as_deref())
+                .for_each(|item| item.collect_bindings(names)),
+            Pattern::Struct(_, fields) => fields
+                .iter()
+                .for_each(|(_, field)| field.collect_bindings(names)),
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::Range(..) => (),
+        }
+    }
 }
 
 impl ASTNode {
+    /// Returns the source location of this node. Nodes created outside of the parser have an
+    /// unknown location.
+    pub fn span(&self) -> &Span {
+        match self {
+            ASTNode::Block { span, .. }
+            | ASTNode::Module { span, .. }
+            | ASTNode::Index { span, .. }
+            | ASTNode::Assign { span, .. }
+            | ASTNode::Declare { span, .. }
+            | ASTNode::Deref { span, .. }
+            | ASTNode::Function { span, .. }
+            | ASTNode::FunctionCall { span, .. }
+            | ASTNode::Struct { span, .. }
+            | ASTNode::Conditional { span, .. }
+            | ASTNode::Loop { span, .. }
+            | ASTNode::For { span, .. }
+            | ASTNode::Range { span, .. }
+            | ASTNode::Match { span, .. }
+            | ASTNode::Op { span, .. }
+            | ASTNode::UnaryOp { span, .. }
+            | ASTNode::Return { span, .. }
+            | ASTNode::Break { span, .. }
+            | ASTNode::Continue { span, .. }
+            | ASTNode::Throw { span, .. }
+            | ASTNode::Try { span, .. }
+            | ASTNode::Literal { span, .. }
+            | ASTNode::List { span, .. }
+            | ASTNode::Dict { span, .. } => span,
+        }
+    }
+
+    /// Returns true if this node produces a value when used as a statement, rather than only
+    /// having an effect, like declarations and loops do.
+    pub(crate) fn is_expression(&self) -> bool {
+        match self {
+            ASTNode::UnaryOp { op, .. } => !matches!(op, Token::Increment | Token::Decrement),
+            ASTNode::Function { id, .. } => id.is_none(),
+            ASTNode::Index { .. }
+            | ASTNode::Deref { .. }
+            | ASTNode::FunctionCall { .. }
+            | ASTNode::Op { .. }
+            | ASTNode::Conditional { .. }
+            | ASTNode::Match { .. }
+            | ASTNode::Literal { .. }
+            | ASTNode::List { .. }
+            | ASTNode::Dict { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the dotted path named by a chain of identifiers & derefs, like `a.b.c`. Returns
+    /// `None` for other nodes.
+    pub(crate) fn path(&self) -> Option<String> {
+        match self {
+            ASTNode::Literal {
+                token: Token::Identifier(sym),
+                ..
+            } => Some(resolve!(*sym)),
+            ASTNode::Deref { parent, child, .. } => {
+                Some(format!("{}.{}", parent.path()?, child.path()?))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns true if this node is a chain of indices & derefs rooted at an identifier, naming a
+    /// variable that can be changed in place.
+    pub(crate) fn is_place(&self) -> bool {
+        match self {
+            ASTNode::Literal {
+                token: Token::Identifier(_),
+                ..
+            } => true,
+            ASTNode::Index { target, .. } => target.is_place(),
+            ASTNode::Deref { parent, child, .. } => {
+                matches!(
+                    **child,
+                    ASTNode::Literal {
+                        token: Token::Identifier(_),
+                        ..
+                    }
+                ) && parent.is_place()
+            }
+            _ => false,
+        }
+    }
+
     /// Returns a reference to the constructor of the structure represented by this node. If this
     /// node is not a structure, or no constructor was found, returns `None`.
     pub(crate) fn constructor(&self) -> Option<Rc<ASTNode>> {
@@ -85,11 +346,16 @@ impl ASTNode {
         };
 
         // find constructor function in struct body
-        if let (ASTNode::Block(nodes), IDKind::Symbol(struct_name)) = (body.as_ref(), id.get_kind())
+        if let (
+            ASTNode::Block {
+                statements: nodes, ..
+            },
+            IDKind::Symbol(struct_name),
+        ) = (body.as_ref(), id.get_kind())
         {
             for node in nodes {
                 // unwrap function node to get ID value, skipping others
-                let ASTNode::Function { id, .. } = node.as_ref() else {
+                let ASTNode::Function { id: Some(id), .. } = node.as_ref() else {
                     continue;
                 };
 
@@ -105,52 +371,32 @@ impl ASTNode {
         None
     }
 
-    /// Returns the truthiness of this node.
-    /// True booleans, non-zero numbers, lists, structure instances, and functions are truthy.
-    pub(crate) fn is_truthy(&self) -> bool {
-        match self {
-            ASTNode::Literal(Token::Bool(v)) => *v,
-            ASTNode::Literal(Token::Number(n)) => !(*n == 0.),
-            ASTNode::Literal(t) if *t != Token::Undefined => true,
-            ASTNode::List(_) => true,
-            ASTNode::Instance { .. } => true,
-            ASTNode::Function { .. } => true,
-            _ => false,
-        }
-    }
-
-    /// Returns `Err` if this index is out of bounds. Otherwise, returns `Ok(idx)`.
-    /// Technically applies to all literal numbers, but should be used to check if an index value
-    /// is within `0 < n < usize::MAX`.
-    pub(crate) fn as_index(&self) -> Result<usize> {
-        if let ASTNode::Literal(Token::Number(n)) = self {
-            if *n < 0. {
-                bail!("index values must be non-negative");
-            } else if *n > usize::MAX as f32 {
-                bail!("index value larger than {}", usize::MAX);
-            }
-            return Ok(*n as usize);
-        }
-        unreachable!("attempted to convert non-numeric type into index");
-    }
-
-    /// Get a new instance of the default SVT for this struct if applicable.
-    pub(crate) fn template(&self) -> Result<SVTable> {
-        if let ASTNode::Struct { template, .. } = self {
-            return Ok(template.clone());
-        }
-        bail!("cannot create template of non-structure value: {self:?}");
-    }
-
-    pub(crate) fn inner_to_owned(rc: &Rc<ASTNode>) -> ASTNode {
-        (**rc).clone()
+    /// Returns the default values of the fields of the structure represented by this node, in
+    /// order of declaration. If this node is not a structure, returns an empty list.
+    pub(crate) fn fields(&self) -> Vec<Rc<ASTNode>> {
+        let ASTNode::Struct { body, .. } = self else {
+            return vec![];
+        };
+        let ASTNode::Block {
+            statements: nodes, ..
+        } = body.as_ref()
+        else {
+            return vec![];
+        };
+        nodes
+            .iter()
+            .filter_map(|node| match node.as_ref() {
+                ASTNode::Declare { value, .. } => Some(value.clone()),
+                _ => None,
+            })
+            .collect()
     }
 }
 
 impl Display for ASTNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ASTNode::Literal(token) => write!(f, "{token}"),
+            ASTNode::Literal { token, .. } => write!(f, "{token}"),
             _ => write!(f, "{self:?}"),
         }?;
         Ok(())
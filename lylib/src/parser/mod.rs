@@ -1,10 +1,11 @@
 //! The parser converts lexed tokens into an abstract syntax tree.
 
-use crate::interpreter::{ID, MemoryInterface, SVTable, Variable};
-use crate::lexer::{Lexer, Token};
-use anyhow::{Context, Result, bail};
+use crate::diagnostics::Diagnostic;
+use crate::interpreter::ID;
+use crate::lexer::{Lexer, Span, SpannedToken, Token, WithSpan};
+use anyhow::{Context, Result, anyhow, bail};
 use std::collections::VecDeque;
-use std::{env, fs::File, io::Read, path::PathBuf, rc::Rc};
+use std::{env, fmt::Display, fs::File, io::Read, path::PathBuf, rc::Rc};
 
 pub mod astnode;
 pub use astnode::*;
@@ -12,21 +13,62 @@ mod tests;
 
 /// The parser converts a sequence of tokens into an Abstract Syntax Tree (AST).
 pub struct Parser {
-    tokens: VecDeque<Token>,
+    tokens: VecDeque<SpannedToken>,
     path: PathBuf,
+    /// Location of the last consumed token, not counting terminators.
+    last: Span,
+    /// If true, statements that fail to parse are recorded in `errors` instead of aborting.
+    recover: bool,
+    /// Errors recorded while recovering.
+    errors: Vec<anyhow::Error>,
+    /// Warnings about code that parsed, but is likely wrong.
+    warnings: Vec<Diagnostic>,
+    /// Number of blocks currently being parsed. The outermost block of a buffer is depth 1.
+    depth: usize,
+}
+
+/// Every syntax error found in a buffer parsed with recovery enabled.
+#[derive(Debug)]
+pub struct SyntaxErrors(pub Vec<anyhow::Error>);
+
+impl Display for SyntaxErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "found {} syntax errors", self.0.len())
+    }
+}
+
+impl std::error::Error for SyntaxErrors {}
+
+/// Describes the token found where something else was expected, for error messages. `None` is the
+/// end of the file.
+fn found(token: Option<&Token>) -> String {
+    match token {
+        Some(token) => token.to_string(),
+        None => "end of file".into(),
+    }
 }
 
 impl Parser {
     /// Creates a new parser over `tokens`.
     // TODO: this should most likely return a result, because if we can't get the current
     // directory, we should just abort (only happens when cwd has bad permissions/DNE)
-    pub fn new(tokens: Vec<Token>) -> Self {
+    pub fn new(tokens: Vec<SpannedToken>) -> Self {
         Self {
             tokens: tokens.into(),
             path: env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            last: Span::default(),
+            recover: false,
+            errors: vec![],
+            warnings: vec![],
+            depth: 0,
         }
     }
 
+    /// Takes the warnings found while parsing.
+    pub fn take_warnings(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.warnings)
+    }
+
     /// Sets the current working directory, used to set relative location of imports.
     pub fn set_pwd(&mut self, path: PathBuf) {
         self.path = path;
@@ -34,29 +76,54 @@ impl Parser {
 
     /// Peek at the next token. Returns `Err` on EOF.
     fn peek(&self) -> Result<&Token> {
-        self.tokens.front().context("unexpected EOF")
+        self.tokens
+            .front()
+            .map(|t| &t.token)
+            .context("unexpected end of file")
+            .at(&self.last)
     }
 
     /// Peek `n` positions ahead. Returns `Err` on EOF.
     fn peek_n(&self, n: usize) -> Result<&Token> {
-        self.tokens.get(n).context("unexpected EOF")
+        self.tokens
+            .get(n)
+            .map(|t| &t.token)
+            .context("unexpected end of file")
+            .at(&self.last)
+    }
+
+    /// Returns the location of the next token, or of the last token on EOF.
+    fn span(&self) -> Span {
+        match self.tokens.front() {
+            Some(token) => token.span.clone(),
+            None => self.last.clone(),
+        }
+    }
+
+    /// Returns a span from `start` up to the end of the last consumed token.
+    fn span_from(&self, start: &Span) -> Span {
+        start.to(&self.last)
     }
 
     /// Get and return the next token.
     fn next(&mut self) -> Option<Token> {
-        self.tokens.pop_front()
+        let SpannedToken { token, span } = self.tokens.pop_front()?;
+
+        // terminators are consumed along with the expressions they end, but shouldn't extend
+        // the spans of those expressions
+        if !matches!(token, Token::Endl | Token::BlockStart) {
+            self.last = span;
+        }
+        Some(token)
     }
 
     /// Throws an error if the next token is not `expected`.
     fn expect(&mut self, expected: Token) -> Result<()> {
+        let span = self.span();
         match self.next() {
             Some(token) if token == expected => Ok(()),
-            Some(token) => {
-                bail!("found {token:?}, expected {expected:?}");
-            }
-            _ => {
-                bail!("unexpected EOF")
-            }
+            Some(token) => Err(anyhow!("expected {expected}, found {token}")).at(&span),
+            _ => Err(anyhow!("unexpected end of file")).at(&span),
         }
     }
 
@@ -67,10 +134,14 @@ impl Parser {
             Token::LogicalAnd => 2,
             Token::LogicalEq | Token::LogicalNeq => 3,
             Token::LogicalL | Token::LogicalLe | Token::LogicalG | Token::LogicalGe => 4,
-            Token::Add | Token::Sub => 5,
-            Token::Mul | Token::Div | Token::Floor => 6,
-            Token::Pow => 7,
-            Token::Increment | Token::Decrement => 8,
+            Token::BitOr => 5,
+            Token::BitXor => 6,
+            Token::BitAnd => 7,
+            Token::ShiftL | Token::ShiftR => 8,
+            Token::Add | Token::Sub => 9,
+            Token::Mul | Token::Div | Token::Floor | Token::Mod => 10,
+            Token::Pow => 11,
+            Token::Increment | Token::Decrement => 12,
             _ => 0,
         }
     }
@@ -83,30 +154,130 @@ impl Parser {
 
     /// Parses all tokens with hidden module imports.
     pub fn parse_with_imports(&mut self, imports: Vec<Rc<ASTNode>>) -> Result<Rc<ASTNode>> {
+        let start = self.span();
+        self.depth += 1;
+        let statements = self.parse_block();
+        self.depth -= 1;
+        Ok(ASTNode::Block {
+            statements: [imports, statements?].concat(),
+            span: self.span_from(&start),
+        }
+        .into())
+    }
+
+    /// Parses all tokens with hidden module imports, recovering from syntax errors instead of
+    /// stopping at the first one. Statements that fail to parse are left out of the returned tree,
+    /// and every error is returned alongside it.
+    pub fn parse_recovering(
+        &mut self,
+        imports: Vec<Rc<ASTNode>>,
+    ) -> (Rc<ASTNode>, Vec<anyhow::Error>) {
+        self.recover = true;
+        let result = self.parse_with_imports(imports);
+        self.recover = false;
+        let mut errors = std::mem::take(&mut self.errors);
+        let ast = result.unwrap_or_else(|e| {
+            errors.push(e);
+            ASTNode::Block {
+                statements: vec![],
+                span: Span::default(),
+            }
+            .into()
+        });
+        (ast, errors)
+    }
+
+    /// Parses statements until the end of the current block.
+    fn parse_block(&mut self) -> Result<Vec<Rc<ASTNode>>> {
         let mut statements = vec![];
         while let Ok(token) = self.peek() {
-            if *token == Token::BlockEnd {
-                // consume block ends and expect endline
-                self.next();
-                self.expect(Token::Endl)?;
+            // block ends only close nested blocks; at the top level, they're parsed as statements
+            // so that they are reported
+            let nested = self.depth > 1;
+            if *token == Token::BlockEnd && nested {
+                self.parse_block_end()?;
                 break;
-            } else if *token == Token::Else {
-                // also counts as a block end for conditionals
+            } else if matches!(token, Token::Else | Token::Catch | Token::Finally) && nested {
+                // also counts as a block end for conditionals & try statements
                 break;
             } else if *token == Token::Endl {
                 // consume endlines
                 self.next();
             } else {
                 // otherwise, parse the next statement
-                statements.push(self.parse_statement()?);
+                let remaining = self.tokens.len();
+                match self.parse_statement() {
+                    Ok(statement) => statements.push(statement),
+                    Err(e) => {
+                        self.record(e)?;
+                        self.synchronize(self.tokens.len() != remaining);
+                    }
+                }
+            }
+        }
+        Ok(statements)
+    }
+
+    /// Consumes a block end. Block ends must end the line, unless the block is part of an
+    /// expression, like an anonymous function passed as an argument.
+    fn parse_block_end(&mut self) -> Result<()> {
+        self.expect(Token::BlockEnd)?;
+        let span = self.span();
+        match self.peek() {
+            Ok(
+                Token::Endl
+                | Token::Comma
+                | Token::ParenClose
+                | Token::BracketClose
+                | Token::BraceClose,
+            )
+            | Err(_) => Ok(()),
+            Ok(token) => {
+                let e = anyhow!("expected end of line, found {token}");
+                self.record(e).at(&span)
+            }
+        }
+    }
+
+    /// Records `err` if recovering from errors, or returns it otherwise.
+    fn record(&mut self, err: anyhow::Error) -> Result<()> {
+        if self.recover {
+            self.errors.push(err);
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    /// Skips the rest of a statement that failed to parse, stopping after the next endline or
+    /// before the end of the enclosing block. Blocks opened while skipping are skipped whole.
+    fn synchronize(&mut self, progressed: bool) {
+        // always consume the failing token, so it isn't parsed again
+        if !progressed && self.next() == Some(Token::Endl) {
+            return;
+        }
+        let mut depth = 0;
+        while let Ok(token) = self.peek() {
+            match token {
+                Token::BlockEnd | Token::Else | Token::Catch | Token::Finally if depth == 0 => {
+                    return;
+                }
+                Token::BlockStart => depth += 1,
+                Token::BlockEnd => depth -= 1,
+                Token::Endl if depth == 0 => {
+                    self.next();
+                    return;
+                }
+                _ => (),
             }
+            self.next();
         }
-        Ok(ASTNode::Block([imports, statements].concat()).into())
     }
 
     /// Parses a statement.
     fn parse_statement(&mut self) -> Result<Rc<ASTNode>> {
         // process all possible base statements
+        let start = self.span();
         let result = match self.peek()? {
             Token::Import => self.parse_import().context("failed to parse import"),
             Token::Let => self.parse_decl_var().context("failed to parse declaration"),
@@ -117,33 +288,77 @@ impl Parser {
             Token::Struct => self
                 .parse_decl_struct()
                 .context("failed to parse structure declaration"),
-            Token::While => self.parse_while().context("failed to parse while loop"),
-            Token::Identifier(_) => self.parse_expr(None),
+            Token::While => self.parse_while(None).context("failed to parse while loop"),
+            Token::For => self.parse_for(None).context("failed to parse for loop"),
+            Token::Identifier(_) if self.peek_n(1).is_ok_and(|t| *t == Token::Colon) => {
+                self.parse_labeled().context("failed to parse labeled loop")
+            }
+            Token::Identifier(_)
+            | Token::ParenOpen
+            | Token::BracketOpen
+            | Token::BraceOpen
+            | Token::Sub
+            | Token::LogicalNot
+            | Token::Match
+            | Token::New => self.parse_expr(None),
+            token if token.is_literal() => self.parse_expr(None),
             Token::Return => self
                 .parse_return()
                 .context("failed to parse return statement"),
-            Token::Break => self.parse_break(),
+            Token::Break | Token::Continue => self.parse_break(),
+            Token::Throw => self
+                .parse_throw()
+                .context("failed to parse throw statement"),
+            Token::Try => self.parse_try().context("failed to parse try statement"),
             Token::Increment | Token::Decrement => {
                 // safety: destructuring
                 self.parse_operator(Self::get_precedence(self.peek().unwrap()))
             }
-            _ => {
-                bail!("expected statement, found {:?}", self.peek());
-            }
+            token => Err(anyhow!("expected statement, found {token}")),
         };
 
-        // return result with added context
-        result.context("failed to parse statement")
+        result.at(&start)
     }
 
-    /// Parses breaks.
+    /// Parses breaks & continues, which may name the loop they apply to.
     fn parse_break(&mut self) -> Result<Rc<ASTNode>> {
-        self.expect(Token::Break)?;
-        Ok(ASTNode::Break.into())
+        let start = self.span();
+        let token = self.next();
+        let label = match self.peek() {
+            Ok(Token::Identifier(sym)) => {
+                let sym = *sym;
+                self.next();
+                Some(sym)
+            }
+            _ => None,
+        };
+        let span = self.span_from(&start);
+        match token {
+            Some(Token::Break) => Ok(ASTNode::Break { label, span }.into()),
+            Some(Token::Continue) => Ok(ASTNode::Continue { label, span }.into()),
+            token => bail!(
+                "expected break or continue, found {}",
+                found(token.as_ref())
+            ),
+        }
+    }
+
+    /// Parses a loop with a label in front of it, like `outer: while ...`.
+    fn parse_labeled(&mut self) -> Result<Rc<ASTNode>> {
+        let Some(Token::Identifier(label)) = self.next() else {
+            bail!("expected loop label");
+        };
+        self.expect(Token::Colon)?;
+        match self.peek()? {
+            Token::While => self.parse_while(Some(label)),
+            Token::For => self.parse_for(Some(label)),
+            token => bail!("expected loop after label, found {token}"),
+        }
     }
 
     /// Parses imports.
     fn parse_import(&mut self) -> Result<Rc<ASTNode>> {
+        let start = self.span();
         self.expect(Token::Import)?;
         if let Some(Token::Str(path)) = self.next() {
             // get full path
@@ -165,7 +380,10 @@ impl Parser {
                     self.next();
                 } else {
                     // if something other than an identifier is provided, this import is malformed
-                    bail!("expected identifier as alias, found {:?}", self.peek());
+                    bail!(
+                        "expected identifier as alias, found {}",
+                        found(self.peek().ok())
+                    );
                 }
             }
 
@@ -177,12 +395,13 @@ impl Parser {
                 .context("failed to read file data")?;
 
             // lex buffer into tokens
-            let tokens = Lexer::default()
+            let tokens = Lexer::with_file(&path)
                 .lex(buffer)
                 .context("failed to lex imported file")?;
 
             // create a parser and point it to the file's parent directory temporarily
             let mut parser = Self::new(tokens);
+            parser.recover = self.recover;
             path.pop();
             let temp = parser.path.clone();
             parser.set_pwd(path.clone());
@@ -193,6 +412,11 @@ impl Parser {
             let body = parser
                 .parse()
                 .context(format!("failed to parse module body\npath => {path:?}"))?;
+            for err in parser.errors.drain(..) {
+                self.errors
+                    .push(err.context(format!("failed to parse module body\npath => {path:?}")));
+            }
+            self.warnings.append(&mut parser.warnings);
 
             // reset old parser working directory
             parser.set_pwd(temp);
@@ -203,6 +427,7 @@ impl Parser {
                 alias,
                 path: Some(path),
                 body,
+                span: self.span_from(&start),
             }
             .into())
         } else {
@@ -213,29 +438,216 @@ impl Parser {
     /// Parses a conditional expression.
     fn parse_cond(&mut self) -> Result<Rc<ASTNode>> {
         // consume if token
+        let start = self.span();
         self.expect(Token::If)?;
 
         // get if expression and if body block
         let condition = self.parse_expr(None).context("failed to parse condition")?;
+        let span = self.span_from(&start);
         let if_body = self.parse().context("failed to parse if-body")?;
 
-        // process else body block, if present
-        let mut else_body = ASTNode::Block(vec![]).into();
+        // process else body block, if present. `else if` continues the chain, sharing its end
+        let mut else_body = ASTNode::Block {
+            statements: vec![],
+            span: Span::default(),
+        }
+        .into();
         if let Ok(Token::Else) = self.peek() {
             self.next();
-            else_body = self.parse().context("failed to parse else-body")?;
+            let start = self.span();
+            else_body = match self.peek() {
+                Ok(Token::If) => ASTNode::Block {
+                    statements: vec![self.parse_cond()?],
+                    span: self.span_from(&start),
+                }
+                .into(),
+                _ => self.parse().context("failed to parse else-body")?,
+            };
         }
 
         Ok(ASTNode::Conditional {
             condition,
             if_body,
             else_body,
+            span,
         }
         .into())
     }
 
-    /// Parses a list index.
-    fn parse_index(&mut self, target: Rc<ASTNode>) -> Result<Rc<ASTNode>> {
+    /// Parses a match, along with each of its arms. Warns about matches that may not be
+    /// exhaustive, and about arms that can never be reached.
+    fn parse_match(&mut self) -> Result<Rc<ASTNode>> {
+        let start = self.span();
+        self.expect(Token::Match)?;
+        let value = self
+            .parse_expr(None)
+            .context("failed to parse matched expression")?;
+        let span = self.span_from(&start);
+
+        // parse arms until the end of the match
+        let mut arms: Vec<(Rc<Pattern>, _)> = vec![];
+        let mut catch_all = false;
+        loop {
+            match self.peek()? {
+                Token::Endl => {
+                    self.next();
+                    continue;
+                }
+                Token::BlockEnd => break,
+                _ => (),
+            }
+
+            // arms after one that matches everything are never tested
+            let arm_start = self.span();
+            let pattern = self.parse_pattern().context("failed to parse pattern")?;
+            if catch_all {
+                self.warnings.push(
+                    Diagnostic::warning("unreachable match arm")
+                        .with_primary(self.span_from(&arm_start), "")
+                        .with_note("an earlier arm matches every value"),
+                );
+            }
+            catch_all |= pattern.is_catch_all();
+
+            // names may only be bound once per pattern
+            let mut bindings = pattern.bindings();
+            bindings.sort_unstable();
+            if let Some(name) = bindings.windows(2).find(|pair| pair[0] == pair[1]) {
+                bail!("'{}' is bound more than once in pattern", resolve!(name[0]));
+            }
+
+            // arm bodies are either blocks, or a single statement
+            self.expect(Token::Arrow)?;
+            let body = match self.peek()? {
+                Token::BlockStart => {
+                    self.next();
+                    self.parse().context("failed to parse match arm")?
+                }
+                _ => {
+                    let start = self.span();
+                    ASTNode::Block {
+                        statements: vec![self.parse_statement()?],
+                        span: self.span_from(&start),
+                    }
+                    .into()
+                }
+            };
+            arms.push((pattern.into(), body));
+        }
+        self.parse_block_end()?;
+
+        let patterns = arms
+            .iter()
+            .map(|(pattern, _)| &**pattern)
+            .collect::<Vec<_>>();
+        if !Pattern::is_exhaustive(&patterns) {
+            self.warnings.push(
+                Diagnostic::warning("match may not be exhaustive")
+                    .with_primary(span.clone(), "")
+                    .with_note("add a `_` arm to handle every other value"),
+            );
+        }
+        Ok(ASTNode::Match { value, arms, span }.into())
+    }
+
+    /// Parses a pattern of a match arm.
+    fn parse_pattern(&mut self) -> Result<Pattern> {
+        match self.next() {
+            // wildcards & bindings, or structures when followed by braces
+            Some(Token::Identifier(sym)) if self.peek()? == &Token::BraceOpen => {
+                self.next();
+                let mut fields = vec![];
+                while self.peek()? != &Token::BraceClose {
+                    let Some(Token::Identifier(field)) = self.next() else {
+                        bail!("expected field name in structure pattern");
+                    };
+                    let pattern = match self.peek()? {
+                        Token::Colon => {
+                            self.next();
+                            self.parse_pattern()?
+                        }
+                        _ => Pattern::Bind(field),
+                    };
+                    fields.push((field, pattern));
+                    match self.peek()? {
+                        Token::Comma => {
+                            self.next();
+                        }
+                        Token::BraceClose => (),
+                        token => bail!("expected comma or closing brace, found {token}"),
+                    }
+                }
+                self.expect(Token::BraceClose)?;
+                Ok(Pattern::Struct(sym, fields))
+            }
+            Some(Token::Identifier(sym)) if resolve!(sym) == "_" => Ok(Pattern::Wildcard),
+            Some(Token::Identifier(sym)) => Ok(Pattern::Bind(sym)),
+
+            // lists, with an optional rest pattern at the end
+            Some(Token::BracketOpen) => {
+                let mut items = vec![];
+                let mut rest = None;
+                while self.peek()? != &Token::BracketClose {
+                    if self.peek()? == &Token::Spread {
+                        self.next();
+                        let pattern = self.parse_pattern()?;
+                        if !pattern.is_catch_all() {
+                            bail!("expected name or `_` after `...`, found {pattern:?}");
+                        }
+                        rest = Some(Box::new(pattern));
+                        if self.peek()? != &Token::BracketClose {
+                            bail!("rest patterns must come last in lists");
+                        }
+                        break;
+                    }
+                    items.push(self.parse_pattern()?);
+                    match self.peek()? {
+                        Token::Comma => {
+                            self.next();
+                        }
+                        Token::BracketClose => (),
+                        token => bail!("expected comma or closing bracket, found {token}"),
+                    }
+                }
+                self.expect(Token::BracketClose)?;
+                Ok(Pattern::List(items, rest))
+            }
+
+            // literals & ranges
+            Some(token) => {
+                let start = self.pattern_literal(token)?;
+                if self.peek()? != &Token::Range {
+                    return Ok(Pattern::Literal(start));
+                }
+                self.next();
+                let end = self
+                    .next()
+                    .context("expected end of range, found end of file")?;
+                let end = self.pattern_literal(end)?;
+                Ok(Pattern::Range(start, end))
+            }
+            None => bail!("expected pattern, found end of file"),
+        }
+    }
+
+    /// Checks that `token` is a literal that can be used in a pattern, folding negative numbers.
+    fn pattern_literal(&mut self, token: Token) -> Result<Token> {
+        match token {
+            Token::Sub => match self.next() {
+                Some(Token::Int(n)) => Ok(Token::Int(-n)),
+                Some(Token::Float(n)) => Ok(Token::Float(-n)),
+                next => bail!(
+                    "expected number after `-` in pattern, found {}",
+                    found(next.as_ref())
+                ),
+            },
+            token if token.is_literal() => Ok(token),
+            token => bail!("expected pattern, found {token}"),
+        }
+    }
+
+    /// Parses a list index. `start` is the location of the indexed expression.
+    fn parse_index(&mut self, target: Rc<ASTNode>, start: &Span) -> Result<Rc<ASTNode>> {
         // if id is found, parse index value
         self.expect(Token::BracketOpen)?;
         let index = self
@@ -243,31 +655,100 @@ impl Parser {
             .context("failed to parse list index")?;
 
         // return newly made index node
-        Ok(ASTNode::Index { target, index }.into())
+        Ok(ASTNode::Index {
+            target,
+            index,
+            span: self.span_from(start),
+        }
+        .into())
     }
 
-    /// Parses a deref operation.
-    fn parse_deref(&mut self, parent: Rc<ASTNode>) -> Result<Rc<ASTNode>> {
+    /// Parses a deref operation. `start` is the location of the parent expression.
+    fn parse_deref(&mut self, parent: Rc<ASTNode>, start: &Span) -> Result<Rc<ASTNode>> {
         self.expect(Token::Dot)?;
 
         // expect an identifier after the dot
+        let span = self.span();
         let child = match self.next() {
-            Some(Token::Identifier(id)) => ASTNode::Literal(Token::Identifier(id)).into(),
-            Some(token) => bail!("expected identifier after '.', found {token:?}"),
-            None => bail!("unexpected EOF after '.'"),
+            Some(Token::Identifier(id)) => ASTNode::Literal {
+                token: Token::Identifier(id),
+                span: span.clone(),
+            }
+            .into(),
+            Some(token) => {
+                return Err(anyhow!("expected identifier after `.`, found {token}")).at(&span);
+            }
+            None => return Err(anyhow!("unexpected end of file after `.`")).at(&span),
         };
 
-        Ok(ASTNode::Deref { parent, child }.into())
+        Ok(ASTNode::Deref {
+            parent,
+            child,
+            span: self.span_from(start),
+        }
+        .into())
     }
 
     /// Parses a while loop.
-    fn parse_while(&mut self) -> Result<Rc<ASTNode>> {
+    fn parse_while(&mut self, label: Option<usize>) -> Result<Rc<ASTNode>> {
+        let start = self.span();
         self.expect(Token::While)?;
+        let condition = self
+            .parse_expr(None)
+            .context("failed to parse loop condition")?;
+        let span = self.span_from(&start);
         Ok(ASTNode::Loop {
-            condition: self
+            label,
+            condition,
+            body: self.parse().context("failed to parse loop body")?,
+            span,
+        }
+        .into())
+    }
+
+    /// Parses a for loop, which takes either an item, or an index and an item, before the iterated
+    /// expression or range.
+    fn parse_for(&mut self, label: Option<usize>) -> Result<Rc<ASTNode>> {
+        let start = self.span();
+        self.expect(Token::For)?;
+        let mut item = match self.next() {
+            Some(Token::Identifier(sym)) => sym,
+            next => bail!("expected identifier, found {}", found(next.as_ref())),
+        };
+        let mut index = None;
+        if self.peek()? == &Token::Comma {
+            self.next();
+            index = Some(item);
+            item = match self.next() {
+                Some(Token::Identifier(sym)) => sym,
+                next => bail!("expected identifier, found {}", found(next.as_ref())),
+            };
+        }
+        self.expect(Token::In)?;
+        let iterated = self.span();
+        let mut iterable = self
+            .parse_expr(None)
+            .context("failed to parse iterated expression")?;
+        if self.peek()? == &Token::Range {
+            self.next();
+            let end = self
                 .parse_expr(None)
-                .context("failed to parse loop condition")?,
+                .context("failed to parse end of range")?;
+            iterable = ASTNode::Range {
+                start: iterable,
+                end,
+                span: self.span_from(&iterated),
+            }
+            .into();
+        }
+        let span = self.span_from(&start);
+        Ok(ASTNode::For {
+            label,
+            index,
+            item,
+            iterable,
             body: self.parse().context("failed to parse loop body")?,
+            span,
         }
         .into())
     }
@@ -289,97 +770,102 @@ impl Parser {
 
     /// Parses a structure declaration.
     fn parse_decl_struct(&mut self) -> Result<Rc<ASTNode>> {
+        let start = self.span();
         self.expect(Token::Struct)?;
         match self.next() {
             Some(Token::Identifier(sym)) => {
+                let span = self.span_from(&start);
+
                 // expect endl before struct body
                 self.expect(Token::Endl)?;
 
                 // parse body in its entirety
                 let body = self.parse()?;
 
-                // find default fields
-                let mut default_fields = vec![];
-                let ASTNode::Block(body_nodes) = &*body else {
+                // check that the body only holds fields & methods
+                let ASTNode::Block {
+                    statements: body_nodes,
+                    ..
+                } = &*body
+                else {
                     unreachable!();
                 };
                 for node in body_nodes {
                     match &**node {
-                        // if the member is a structure variable, add an owned value
-                        ASTNode::Declare { target, value } => {
-                            // if this field is literal, add it, bail otherwise
-                            let ASTNode::Literal(Token::Identifier(variable)) = &**target else {
+                        // fields must be declared by name
+                        ASTNode::Declare { target, .. } => {
+                            if !matches!(
+                                **target,
+                                ASTNode::Literal {
+                                    token: Token::Identifier(_),
+                                    ..
+                                }
+                            ) {
                                 bail!("invalid default field '{target:?}'");
-                            };
-                            default_fields.push((
-                                ID::new_sym(*variable),
-                                Variable::Owned(ASTNode::inner_to_owned(value)),
-                            ));
-                        }
-
-                        // if the member is a function, add a reference to it
-                        ASTNode::Function { id, .. } => {
-                            default_fields.push((id.clone(), Variable::Function(node.clone())))
+                            }
                         }
-
+                        ASTNode::Function { .. } => {}
                         other => {
                             bail!("unexpected structure field: {other:?}")
                         }
                     }
                 }
 
-                // create a new variable table and instantiate default values
-                let mut template = SVTable::default();
-                for (target, value) in default_fields {
-                    // get the first value in the interned path
-                    let id = *target.to_path().first().unwrap();
-
-                    // add it to the table
-                    template.declare(id, value, 0)?;
-                }
-
-                // create structure & provide the new template
+                // create structure
                 let node = ASTNode::Struct {
                     id: ID::new_sym(sym),
                     body,
-                    template,
+                    span,
                 };
                 Ok(node.into())
             }
             other => {
-                bail!("expected identifier, found {other:?}")
+                bail!("expected identifier, found {}", found(other.as_ref()))
             }
         }
     }
 
     /// Parses a function declaration.
     fn parse_decl_fn(&mut self) -> Result<Rc<ASTNode>> {
+        self.parse_fn(true)
+    }
+
+    /// Parses a function. Named functions are declarations, while anonymous functions are
+    /// expressions, and take every identifier before their body as an argument.
+    fn parse_fn(&mut self, named: bool) -> Result<Rc<ASTNode>> {
+        let start = self.span();
         self.expect(Token::Function)?;
-        let next = self.next();
-        if let Some(Token::Identifier(sym)) = next {
-            // gather arguments
-            let mut arguments = vec![];
-            while let Token::Identifier(arg) = self.peek()? {
-                arguments.push(*arg);
-                self.next();
+        let id = if named {
+            match self.next() {
+                Some(Token::Identifier(sym)) => Some(ID::new_sym(sym)),
+                next => bail!("expected identifier, found {}", found(next.as_ref())),
             }
+        } else {
+            None
+        };
 
-            // consume block start
-            self.expect(Token::BlockStart)?;
+        // gather arguments
+        let mut arguments = vec![];
+        while let Token::Identifier(arg) = self.peek()? {
+            arguments.push(*arg);
+            self.next();
+        }
 
-            Ok(ASTNode::Function {
-                id: ID::new_sym(sym),
-                body: self.parse().context("failed to parse function body")?,
-                arguments,
-            }
-            .into())
-        } else {
-            bail!("expected identifier, found {next:?}");
+        // consume block start
+        let span = self.span_from(&start);
+        self.expect(Token::BlockStart)?;
+
+        Ok(ASTNode::Function {
+            id,
+            body: self.parse().context("failed to parse function body")?,
+            arguments,
+            span,
         }
+        .into())
     }
 
-    /// Parses a function call.
-    fn parse_call_fn(&mut self, target: Rc<ASTNode>) -> Result<Rc<ASTNode>> {
+    /// Parses a function call. `start` is the location of the called expression.
+    fn parse_call_fn(&mut self, target: Rc<ASTNode>, start: &Span) -> Result<Rc<ASTNode>> {
         // parse arguments
         self.expect(Token::ParenOpen)?;
         let mut args = vec![];
@@ -403,22 +889,80 @@ impl Parser {
         Ok(ASTNode::FunctionCall {
             target,
             arguments: args,
+            span: self.span_from(start),
         }
         .into())
     }
 
     /// Parses a return statement.
     fn parse_return(&mut self) -> Result<Rc<ASTNode>> {
+        let start = self.span();
         self.expect(Token::Return)?;
-        Ok(ASTNode::Return(
-            self.parse_expr(None)
-                .context("failed to parse return value")?,
-        )
+        let value = self
+            .parse_expr(None)
+            .context("failed to parse return value")?;
+        Ok(ASTNode::Return {
+            value,
+            span: self.span_from(&start),
+        }
+        .into())
+    }
+
+    /// Parses a throw statement.
+    fn parse_throw(&mut self) -> Result<Rc<ASTNode>> {
+        let start = self.span();
+        self.expect(Token::Throw)?;
+        let value = self
+            .parse_expr(None)
+            .context("failed to parse thrown value")?;
+        Ok(ASTNode::Throw {
+            value,
+            span: self.span_from(&start),
+        }
+        .into())
+    }
+
+    /// Parses a try statement. Its catch & finally clauses each end the block before them, and
+    /// share a single end, like `else` does.
+    fn parse_try(&mut self) -> Result<Rc<ASTNode>> {
+        let start = self.span();
+        self.expect(Token::Try)?;
+        self.expect(Token::BlockStart)?;
+        let span = self.span_from(&start);
+        let body = self.parse().context("failed to parse try body")?;
+
+        // the caught error may be bound to a name
+        let (mut name, mut handler) = (None, None);
+        if let Ok(Token::Catch) = self.peek() {
+            self.next();
+            if let Ok(Token::Identifier(sym)) = self.peek() {
+                name = Some(*sym);
+                self.next();
+            }
+            self.expect(Token::BlockStart)?;
+            handler = Some(self.parse().context("failed to parse catch body")?);
+        }
+        let mut finally = None;
+        if let Ok(Token::Finally) = self.peek() {
+            self.next();
+            finally = Some(self.parse().context("failed to parse finally body")?);
+        }
+        if handler.is_none() && finally.is_none() {
+            bail!("expected catch or finally after try body");
+        }
+
+        Ok(ASTNode::Try {
+            body,
+            name,
+            handler,
+            finally,
+            span,
+        }
         .into())
     }
 
-    /// Parses assignment to any target.
-    fn parse_assignment(&mut self, target: Rc<ASTNode>) -> Result<Rc<ASTNode>> {
+    /// Parses assignment to any target. `start` is the location of the target.
+    fn parse_assignment(&mut self, target: Rc<ASTNode>, start: &Span) -> Result<Rc<ASTNode>> {
         // parse value
         self.expect(Token::Equal)?;
         let value = self
@@ -426,12 +970,18 @@ impl Parser {
             .context("failed to parse assignment value")?;
 
         // return node
-        Ok(ASTNode::Assign { target, value }.into())
+        Ok(ASTNode::Assign {
+            target,
+            value,
+            span: self.span_from(start),
+        }
+        .into())
     }
 
     /// Parses a variable declaration.
     fn parse_decl_var(&mut self) -> Result<Rc<ASTNode>> {
         // parse id and value
+        let start = self.span();
         self.expect(Token::Let)?;
         let target = self
             .parse_expr(Some(Token::Equal))
@@ -441,21 +991,25 @@ impl Parser {
             .context("failed to parse declaration value")?;
 
         // return node
-        Ok(ASTNode::Declare { target, value }.into())
+        Ok(ASTNode::Declare {
+            target,
+            value,
+            span: self.span_from(&start),
+        }
+        .into())
     }
 
     /// Parses expressions, such as operators, indices, function calls, etc.
     fn parse_expr(&mut self, expect: Option<Token>) -> Result<Rc<ASTNode>> {
         // evaluate primary value
+        let start = self.span();
         let mut primary = match self.peek()? {
             Token::ParenOpen => {
                 self.next();
                 self.parse_expr(Some(Token::ParenClose))
                     .context("failed to parse parenthesised expression")?
             }
-            _ => self
-                .parse_primary()
-                .context("failed to parse primary expression")?,
+            _ => self.parse_primary()?,
         };
 
         // keep looping until we've found the largest possible primary
@@ -482,21 +1036,22 @@ impl Parser {
                         lhs: primary,
                         op,
                         rhs,
+                        span: self.span_from(&start),
                     }
                     .into()
                 }
 
                 // function calls
-                Ok(Token::ParenOpen) => self.parse_call_fn(primary)?,
+                Ok(Token::ParenOpen) => self.parse_call_fn(primary, &start)?,
 
                 // indexes
-                Ok(Token::BracketOpen) => self.parse_index(primary)?,
+                Ok(Token::BracketOpen) => self.parse_index(primary, &start)?,
 
                 // deref operations
-                Ok(Token::Dot) => self.parse_deref(primary)?,
+                Ok(Token::Dot) => self.parse_deref(primary, &start)?,
 
                 // assignments
-                Ok(Token::Equal) => self.parse_assignment(primary)?,
+                Ok(Token::Equal) => self.parse_assignment(primary, &start)?,
 
                 // break for all others
                 Ok(Token::Endl) | Ok(Token::BlockStart) => {
@@ -515,28 +1070,27 @@ impl Parser {
     /// Parses operators with precedence climbing
     fn parse_operator(&mut self, min_precedence: u8) -> Result<Rc<ASTNode>> {
         // Expand left-hand side first
+        let start = self.span();
         let mut left = match self.peek()? {
             Token::ParenOpen => {
                 self.next();
                 self.parse_expr(Some(Token::ParenClose))
                     .context("failed to parse parenthesised expression")?
             }
-            _ => self
-                .parse_primary()
-                .context("failed to parse primary in precedence expr")?,
+            _ => self.parse_primary()?,
         };
 
         // Handle high precedence operations like deref, function calls, and indexing
         loop {
-            match self.peek()? {
-                Token::Dot => {
-                    left = self.parse_deref(left)?;
+            match self.peek() {
+                Ok(Token::Dot) => {
+                    left = self.parse_deref(left, &start)?;
                 }
-                Token::ParenOpen => {
-                    left = self.parse_call_fn(left)?;
+                Ok(Token::ParenOpen) => {
+                    left = self.parse_call_fn(left, &start)?;
                 }
-                Token::BracketOpen => {
-                    left = self.parse_index(left)?;
+                Ok(Token::BracketOpen) => {
+                    left = self.parse_index(left, &start)?;
                 }
                 _ => break,
             }
@@ -544,8 +1098,10 @@ impl Parser {
 
         while let Ok(next) = self.peek() {
             // If the precedence of the `peek`ed token is lower than the minimum, break
-            // This means we've gotten to a point where the next token does *not* take precedence
-            if Self::get_precedence(next) < min_precedence {
+            // This means we've gotten to a point where the next token does *not* take precedence.
+            // Tokens without a precedence aren't operators, and always end the expression
+            let precedence = Self::get_precedence(next);
+            if precedence == 0 || precedence < min_precedence {
                 break;
             }
 
@@ -566,6 +1122,7 @@ impl Parser {
                 lhs: left,
                 op,
                 rhs: right,
+                span: self.span_from(&start),
             }
             .into();
         }
@@ -575,17 +1132,32 @@ impl Parser {
 
     /// Parses literal primaries.
     fn parse_primary(&mut self) -> Result<Rc<ASTNode>> {
+        let start = self.span();
         match self.peek()? {
             // process negative expressions
             Token::Sub => {
                 match *self.peek_n(1)? {
                     // This is a literal negative
-                    Token::Number(value) => {
+                    Token::Int(value) => {
                         // Consume both value and negative operator
                         self.next();
                         self.next();
 
-                        Ok(ASTNode::Literal(Token::Number(-value)).into())
+                        Ok(ASTNode::Literal {
+                            token: Token::Int(-value),
+                            span: self.span_from(&start),
+                        }
+                        .into())
+                    }
+                    Token::Float(value) => {
+                        self.next();
+                        self.next();
+
+                        Ok(ASTNode::Literal {
+                            token: Token::Float(-value),
+                            span: self.span_from(&start),
+                        }
+                        .into())
                     }
 
                     // This is a unary negative expression
@@ -599,6 +1171,7 @@ impl Parser {
                         Ok(ASTNode::UnaryOp {
                             target,
                             op: Token::Sub,
+                            span: self.span_from(&start),
                         }
                         .into())
                     }
@@ -606,44 +1179,67 @@ impl Parser {
             }
 
             // Literals
-            t if t.is_literal() => {
-                Ok(ASTNode::Literal(self.next().context("expected literal, found EOF")?).into())
+            t if t.is_literal() => Ok(ASTNode::Literal {
+                token: self.next().context("expected literal, found end of file")?,
+                span: start,
             }
+            .into()),
+
+            // Conditional expressions
+            Token::If => self
+                .parse_cond()
+                .context("failed to parse conditional expression"),
+
+            // Match expressions
+            Token::Match => self.parse_match().context("failed to parse match"),
+
+            // Anonymous functions
+            Token::Function => self
+                .parse_fn(false)
+                .context("failed to parse anonymous function"),
 
             // Identifiers
-            Token::Identifier(_) => {
-                Ok(ASTNode::Literal(self.next().context("expected literal, found EOF")?).into())
+            Token::Identifier(_) => Ok(ASTNode::Literal {
+                token: self.next().context("expected literal, found end of file")?,
+                span: start,
             }
+            .into()),
 
             // Unaries (!, ++, --)
             Token::LogicalNot => {
                 // consumes the `!` and creates a unary operator
                 self.next();
+                let target = self
+                    .parse_operator(Self::get_precedence(&Token::LogicalNot))
+                    .context("failed to parse logical not expression")?;
                 Ok(ASTNode::UnaryOp {
-                    target: self
-                        .parse_operator(Self::get_precedence(&Token::LogicalNot))
-                        .context("failed to parse logical not expression")?,
+                    target,
                     op: Token::LogicalNot,
+                    span: self.span_from(&start),
                 }
                 .into())
             }
             Token::Increment => {
                 self.next();
+                let target = self
+                    .parse_operator(Self::get_precedence(&Token::Increment))
+                    .context("failed to parse increment expression")?;
                 Ok(ASTNode::UnaryOp {
-                    target: self
-                        .parse_operator(Self::get_precedence(&Token::Increment))
-                        .context("failed to parse increment expression")?,
+                    target,
                     op: Token::Increment,
+                    span: self.span_from(&start),
                 }
                 .into())
             }
             Token::Decrement => {
                 self.next();
+                let target = self
+                    .parse_operator(Self::get_precedence(&Token::Decrement))
+                    .context("failed to parse decrement expression")?;
                 Ok(ASTNode::UnaryOp {
-                    target: self
-                        .parse_operator(Self::get_precedence(&Token::Decrement))
-                        .context("failed to parse decrement expression")?,
+                    target,
                     op: Token::Decrement,
+                    span: self.span_from(&start),
                 }
                 .into())
             }
@@ -651,20 +1247,26 @@ impl Parser {
             // Lists
             Token::BracketOpen => self.parse_list().context("failed to parse list"),
 
+            // Dictionaries
+            Token::BraceOpen => self.parse_dict().context("failed to parse dictionary"),
+
             // Structure instances
             Token::New => self
                 .parse_struct_instance()
                 .context("failed to parse new structure instance"),
 
-            _ => {
-                bail!("invalid primary '{:?}'", self.peek());
-            }
+            _ => Err(anyhow!(
+                "expected expression, found {}",
+                found(self.peek().ok())
+            ))
+            .at(&start),
         }
     }
 
     /// Parses lists.
     fn parse_list(&mut self) -> Result<Rc<ASTNode>> {
         // consume open bracket
+        let start = self.span();
         self.expect(Token::BracketOpen)?;
 
         // parse items individually
@@ -691,9 +1293,48 @@ impl Parser {
                 .context("failed to parse list item")?;
 
             // add item to the list
-            items.push(Variable::Owned(ASTNode::inner_to_owned(&item)).into())
+            items.push(item)
+        }
+
+        Ok(ASTNode::List {
+            items,
+            span: self.span_from(&start),
+        }
+        .into())
+    }
+
+    /// Parses dictionaries. Keys are expressions, separated from their values by colons.
+    fn parse_dict(&mut self) -> Result<Rc<ASTNode>> {
+        let start = self.span();
+        self.expect(Token::BraceOpen)?;
+
+        // parse entries individually. like lists, entries may span multiple lines
+        let mut entries = vec![];
+        loop {
+            match self.peek()? {
+                Token::BraceClose => {
+                    self.next();
+                    break;
+                }
+                Token::Endl => {
+                    self.next();
+                    continue;
+                }
+                _ => {}
+            }
+            let key = self
+                .parse_expr(Some(Token::Colon))
+                .context("failed to parse dictionary key")?;
+            let value = self
+                .parse_expr(Some(Token::Comma))
+                .context("failed to parse dictionary value")?;
+            entries.push((key, value));
         }
 
-        Ok(ASTNode::List(items).into())
+        Ok(ASTNode::Dict {
+            entries,
+            span: self.span_from(&start),
+        }
+        .into())
     }
 }
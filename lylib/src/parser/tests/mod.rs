@@ -1,12 +1,12 @@
 #![cfg(test)]
 
-use crate::{lexer::Token::*, parser::*, *};
+use crate::{lexer::Token::*, parser::*};
 
 /// Shorthand for creating and executing the parser, and comparing its output to an expression.
 #[macro_export]
 macro_rules! parse_eq {
     ($code:expr; $($block:expr),*) => {
-        (|| {
+        {
             let result = Parser::new(Lexer::default().lex($code.into()).unwrap()).parse();
             assert!(result.is_ok(), "Parser failed: {:?}", result);
             let result = result.unwrap(); // safety ^^^
@@ -14,10 +14,10 @@ macro_rules! parse_eq {
             if result != block {
                 panic!("expected: {:#?}\ngot: {:#?}", block, result);
             }
-        })()
+        }
     };
     ($code:expr, $path:expr; $($block:expr),*) => {
-        (|| {
+        {
             let mut parser = Parser::new(Lexer::default().lex($code.into()).unwrap());
             parser.set_pwd($path.into());
             let result = parser.parse();
@@ -27,7 +27,7 @@ macro_rules! parse_eq {
             if result != block {
                 panic!("expected: {:#?}\ngot: {:#?}", block, result);
             }
-        })()
+        }
     };
 }
 
@@ -61,6 +61,20 @@ fn lists() {
     );
 }
 
+#[test]
+fn dicts() {
+    parse_eq!(
+        "let d = {\"a\": 1, key: [2], 3: {}}; d[\"a\"] = 0; let multiline = {\n\"b\": 2,\n};";
+        node!(declare d => node!({
+            lit!("a") => lit!(1),
+            ident!("key") => node!([lit!(2)]),
+            lit!(3) => node!({})
+        })),
+        node!(assign node!(index ident!("d"), "a") => lit!(0)),
+        node!(declare multiline => node!({lit!("b") => lit!(2)}))
+    );
+}
+
 #[test]
 fn indices() {
     parse_eq!(
@@ -89,6 +103,32 @@ fn math() {
     );
 }
 
+#[test]
+fn expression_statements() {
+    parse_eq!(
+        "1 + 2; (3); [4]; -a;";
+        node!(op 1, Add, 2),
+        lit!(3),
+        node!([lit!(4)]),
+        node!(unary Sub, ident!("a"))
+    );
+}
+
+#[test]
+fn trailing_statements() {
+    // buffers may end without an endline
+    parse_eq!("let a = 1 + 2"; node!(declare a => node!(op 1, Add, 2)));
+    parse_eq!(
+        "if 1 do; end";
+        node!(
+            if lit!(1) =>
+                block!();
+            else =>
+                block!();
+        )
+    );
+}
+
 #[test]
 fn math_complex() {
     parse_eq!(
@@ -248,6 +288,73 @@ fn conditionals() {
     );
 }
 
+#[test]
+fn else_if_chains() {
+    parse_eq!(
+        "if a do; x = 1; else if b do; x = 2; else if c do; else; x = 3; end;
+        if a do; else if b do; end;";
+        node!(
+            if ident!("a") =>
+                block!(node!(assign x => lit!(1)));
+            else =>
+                block!(node!(
+                    if ident!("b") =>
+                        block!(node!(assign x => lit!(2)));
+                    else =>
+                        block!(node!(
+                            if ident!("c") =>
+                                block!();
+                            else =>
+                                block!(node!(assign x => lit!(3)));
+                        ));
+                ));
+        ),
+        node!(
+            if ident!("a") =>
+                block!();
+            else =>
+                block!(node!(
+                    if ident!("b") =>
+                        block!();
+                    else =>
+                        block!();
+                ));
+        )
+    );
+}
+
+#[test]
+fn conditional_expressions() {
+    parse_eq!(
+        "let a = if b do 1 else 2 end
+        let c = if d do; 1; else if e do; 2; end;
+        print(if f do g end);";
+        node!(declare a => node!(
+            if ident!("b") =>
+                block!(lit!(1));
+            else =>
+                block!(lit!(2));
+        )),
+        node!(declare c => node!(
+            if ident!("d") =>
+                block!(lit!(1));
+            else =>
+                block!(node!(
+                    if ident!("e") =>
+                        block!(lit!(2));
+                    else =>
+                        block!();
+                ));
+        )),
+        node!(print(node!(
+            if ident!("f") =>
+                block!(ident!("g"));
+            else =>
+                block!();
+        )))
+    );
+}
+
 #[test]
 fn arguments() {
     parse_eq!(
@@ -275,6 +382,20 @@ fn functions() {
     );
 }
 
+#[test]
+fn anonymous_functions() {
+    parse_eq!(
+        "let f = func a b do return a + b end; apply(func do; return 1; end, 2);";
+        node!(declare f => node!(func (a, b) => block!(
+            node!(return node!(op ident!("a"), Add, ident!("b")))
+        ))),
+        node!(apply(
+            node!(func () => block!(node!(return lit!(1)))),
+            lit!(2)
+        ))
+    );
+}
+
 #[test]
 fn function_calls() {
     parse_eq!(
@@ -321,4 +442,245 @@ fn loops() {
                 block!(node!(break));
         )
     );
+    parse_eq!(
+        "for item in items do; print(item); end;
+        for i, c in \"abc\" do; break; end;";
+        node!(
+            for item in ident!("items") =>
+                block!(node!(print(ident!("item"))));
+        ),
+        node!(
+            for i, c in lit!("abc") =>
+                block!(node!(break));
+        )
+    );
+    parse_eq!(
+        "outer: while true do; continue; break outer; end;
+        rows: for row in grid do; continue rows; end;";
+        node!(
+            loop outer: lit!(true) =>
+                block!(node!(continue), node!(break outer));
+        ),
+        node!(
+            for rows: row in ident!("grid") =>
+                block!(node!(continue rows));
+        )
+    );
+    parse_eq!(
+        "for i in 0..n - 1 do; end;";
+        node!(
+            for i in node!(range lit!(0), node!(op ident!("n"), Sub, lit!(1))) =>
+                block!();
+        )
+    );
+}
+
+#[test]
+fn matches() {
+    parse_eq!(
+        "let a = match b do
+          0 => 1
+          -2..5 => do; c; end
+          [first, ...rest] => first
+          Point { x: 0, y } => y
+          _ => 2
+        end";
+        node!(declare a => node!(match ident!("b") =>
+            (Pattern::Literal(Int(0)), block!(lit!(1))),
+            (Pattern::Range(Int(-2), Int(5)), block!(ident!("c"))),
+            (
+                Pattern::List(
+                    vec![Pattern::Bind(intern!("first"))],
+                    Some(Box::new(Pattern::Bind(intern!("rest"))))
+                ),
+                block!(ident!("first"))
+            ),
+            (
+                Pattern::Struct(
+                    intern!("Point"),
+                    vec![
+                        (intern!("x"), Pattern::Literal(Int(0))),
+                        (intern!("y"), Pattern::Bind(intern!("y")))
+                    ]
+                ),
+                block!(ident!("y"))
+            ),
+            (Pattern::Wildcard, block!(lit!(2)))
+        ))
+    );
+}
+
+#[test]
+fn match_warnings() {
+    let warnings = |code: &str| {
+        let mut parser = Parser::new(Lexer::default().lex(code.into()).unwrap());
+        parser.parse().unwrap();
+        parser
+            .take_warnings()
+            .into_iter()
+            .map(|warning| (warning.message, warning.primary.unwrap().span.line))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(warnings("match a do\n  _ => 1\nend"), vec![]);
+    assert_eq!(
+        warnings("match a do\n  1 => 1\n  2 => 2\nend"),
+        vec![("match may not be exhaustive".into(), 1)]
+    );
+    assert_eq!(
+        warnings("match a do\n  x => 1\n  _ => 2\nend"),
+        vec![("unreachable match arm".into(), 3)]
+    );
+
+    // booleans & lists of every length are covered without a catch-all
+    assert_eq!(
+        warnings("match a do\n  true => 1\n  false => 2\nend"),
+        vec![]
+    );
+    assert_eq!(
+        warnings("match a do\n  [] => 1\n  [x] => 2\n  [x, ...rest] => 3\nend"),
+        vec![]
+    );
+    assert_eq!(warnings("match a do\n  [...rest] => 1\nend"), vec![]);
+    assert_eq!(
+        warnings("match a do\n  [] => 1\n  [x, ...rest] => 2\nend"),
+        vec![]
+    );
+    assert_eq!(
+        warnings("match a do\n  true => 1\nend"),
+        vec![("match may not be exhaustive".into(), 1)]
+    );
+    assert_eq!(
+        warnings("match a do\n  [] => 1\n  [x, y, ...rest] => 2\nend"),
+        vec![("match may not be exhaustive".into(), 1)]
+    );
+    assert_eq!(
+        warnings("match a do\n  [] => 1\n  [1, ...rest] => 2\nend"),
+        vec![("match may not be exhaustive".into(), 1)]
+    );
+}
+
+#[test]
+fn match_duplicate_bindings() {
+    assert!(
+        Parser::new(
+            Lexer::default()
+                .lex("match a do [x, x] => 1 end".into())
+                .unwrap()
+        )
+        .parse()
+        .is_err()
+    );
+}
+
+#[test]
+fn exceptions() {
+    parse_eq!(
+        "try do
+          throw \"bad\"
+        catch err do
+          print(err)
+        finally
+          done()
+        end
+        try do; a(); catch do; end
+        try do; a(); finally; b(); end";
+        node!(
+            try block!(node!(throw lit!("bad")));
+            catch err => block!(node!(print(ident!("err"))));
+            finally block!(node!(done()))
+        ),
+        node!(try block!(node!(a())); catch => block!();),
+        node!(try block!(node!(a())); finally block!(node!(b())))
+    );
+
+    // a try needs a catch or finally
+    assert!(
+        Parser::new(Lexer::default().lex("try do; a(); end".into()).unwrap())
+            .parse()
+            .is_err()
+    );
+}
+
+#[test]
+fn spans() {
+    let ast = Parser::new(
+        Lexer::default()
+            .lex("let a = 1\n\nlet b = a + 2;".into())
+            .unwrap(),
+    )
+    .parse()
+    .unwrap();
+    let ASTNode::Block { statements, .. } = &*ast else {
+        panic!("expected block, found {ast:?}");
+    };
+    let ASTNode::Declare { value, span, .. } = &*statements[1] else {
+        panic!("expected declaration, found {:?}", statements[1]);
+    };
+    assert_eq!((span.line, span.column, span.length), (3, 1, 13));
+    let span = value.span();
+    assert_eq!((span.line, span.column, span.length), (3, 9, 5));
+
+    // operands carry their own location
+    let ASTNode::Op { rhs, .. } = &**value else {
+        panic!("expected operation, found {value:?}");
+    };
+    let span = rhs.span();
+    assert_eq!((span.line, span.column, span.length), (3, 13, 1));
+}
+
+#[test]
+fn error_location() {
+    let err = Parser::new(Lexer::default().lex("let a = 1\nlet b = )".into()).unwrap())
+        .parse()
+        .unwrap_err();
+    let span = err
+        .downcast_ref::<Span>()
+        .expect("error should carry a span");
+    assert_eq!((span.line, span.column), (2, 9));
+}
+
+/// Parses `code` with recovery, returning the number of statements parsed and the lines of every
+/// error found.
+fn recover(code: &str) -> (usize, Vec<usize>) {
+    let (ast, errors) =
+        Parser::new(Lexer::default().lex(code.into()).unwrap()).parse_recovering(vec![]);
+    let ASTNode::Block { statements, .. } = &*ast else {
+        panic!("expected block, found {ast:?}");
+    };
+    let lines = errors
+        .iter()
+        .map(|e| {
+            e.downcast_ref::<Span>()
+                .expect("error should carry a span")
+                .line
+        })
+        .collect();
+    (statements.len(), lines)
+}
+
+#[test]
+fn recover_multiple_errors() {
+    let (statements, lines) = recover("let a = )\nlet b = 2\nlet = 3\nlet c = b\n");
+    assert_eq!(statements, 2);
+    assert_eq!(lines, vec![1, 3]);
+}
+
+#[test]
+fn recover_in_blocks() {
+    let code = "func f do\n  let a = )\n  return 1\nend\nlet b = (\nif b do\n  let = 2\nend\n";
+    let (statements, lines) = recover(code);
+    assert_eq!(statements, 2);
+    assert_eq!(lines, vec![2, 5, 7]);
+}
+
+#[test]
+fn recover_stray_end() {
+    let (statements, lines) = recover("let a = 1\nend\nlet b = 2\n");
+    assert_eq!(statements, 2);
+    assert_eq!(lines, vec![2]);
+    assert!(
+        Parser::new(Lexer::default().lex("end\nlet b = 2\n".into()).unwrap())
+            .parse()
+            .is_err()
+    );
 }